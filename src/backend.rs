@@ -0,0 +1,545 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Read access to the database, wherever it comes from
+//!
+//! Code written against `DiscogsBackend` works the same with the
+//! `Discogs` client, with a `MemoryBackend` filled from a dump or by
+//! hand, or with any other store implementing the trait.
+
+use data_structures::*;
+use discogs::Discogs;
+use hyper::status::StatusCode;
+use query::*;
+use std::collections::BTreeMap;
+
+/// The parameters of a database search
+///
+/// Empty fields are not searched on
+#[derive(Debug, Clone, Default)]
+pub struct Search {
+    pub query: Option<String>,
+    pub search_type: Option<SearchType>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub label: Option<String>,
+    pub catno: Option<String>,
+    pub barcode: Option<String>,
+    pub year: Option<i32>,
+}
+
+/// The number of search results the API returns in its first page
+pub const SEARCH_PAGE_SIZE: usize = 50;
+
+/// The read operations of the database
+///
+/// Unknown ids fail with a `QueryError::NotFoundError`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use discogs::Discogs;
+/// use discogs::backend::*;
+/// use discogs::query::QueryError;
+///
+/// fn title<B: DiscogsBackend>(backend: &B, id: u32) -> Result<String, QueryError> {
+///     backend.get_release(id).map(|release| release.title)
+/// }
+///
+/// let online = title(&Discogs::new("USER_AGENT"), 8481);
+/// let offline = title(&MemoryBackend::new(), 8481);
+/// ```
+pub trait DiscogsBackend {
+    fn get_artist(&self, id: u32) -> Result<Artist, QueryError>;
+
+    fn get_artist_releases(&self, id: u32, page: i16, per_page: i16) -> Result<ArtistReleases, QueryError>;
+
+    fn get_label(&self, id: u32) -> Result<Label, QueryError>;
+
+    fn get_label_releases(&self, id: u32, page: i16, per_page: i16) -> Result<ArtistReleases, QueryError>;
+
+    fn get_master(&self, id: u32) -> Result<Master, QueryError>;
+
+    fn get_release(&self, id: u32) -> Result<Release, QueryError>;
+
+    /// The first `SEARCH_PAGE_SIZE` matches of `search`, like the first page
+    /// of results of the API
+    fn get_search_results(&self, search: &Search) -> Result<Vec<SearchResult>, QueryError>;
+}
+
+impl DiscogsBackend for Discogs {
+    fn get_artist(&self, id: u32) -> Result<Artist, QueryError> {
+        self.artist(id).get().map_err(|e| not_found_on_404(e, "artist", id))
+    }
+
+    fn get_artist_releases(&self, id: u32, page: i16, per_page: i16) -> Result<ArtistReleases, QueryError> {
+        self.artist(id)
+            .pagination(page, per_page)
+            .get_releases()
+            .map_err(|e| not_found_on_404(e, "artist", id))
+    }
+
+    fn get_label(&self, id: u32) -> Result<Label, QueryError> {
+        self.label(id).get().map_err(|e| not_found_on_404(e, "label", id))
+    }
+
+    fn get_label_releases(&self, id: u32, page: i16, per_page: i16) -> Result<ArtistReleases, QueryError> {
+        self.label(id)
+            .pagination(page, per_page)
+            .get_releases()
+            .map_err(|e| not_found_on_404(e, "label", id))
+    }
+
+    fn get_master(&self, id: u32) -> Result<Master, QueryError> {
+        self.master(id).get().map_err(|e| not_found_on_404(e, "master", id))
+    }
+
+    fn get_release(&self, id: u32) -> Result<Release, QueryError> {
+        self.release(id).get().map_err(|e| not_found_on_404(e, "release", id))
+    }
+
+    fn get_search_results(&self, search: &Search) -> Result<Vec<SearchResult>, QueryError> {
+        let mut builder = self.search();
+
+        if let Some(ref query) = search.query {
+            builder.query(query.clone());
+        }
+        if let Some(search_type) = search.search_type {
+            builder.search_type(search_type);
+        }
+        if let Some(ref title) = search.title {
+            builder.title(title.clone());
+        }
+        if let Some(ref artist) = search.artist {
+            builder.artist(artist.clone());
+        }
+        if let Some(ref label) = search.label {
+            builder.label(label.clone());
+        }
+        if let Some(ref catno) = search.catno {
+            builder.catno(catno.clone());
+        }
+        if let Some(ref barcode) = search.barcode {
+            builder.barcode(barcode.clone());
+        }
+        if let Some(year) = search.year {
+            builder.year(year);
+        }
+
+        builder.get()
+    }
+}
+
+/// A backend holding everything in memory
+///
+/// # Examples
+///
+/// ```
+/// use discogs::backend::*;
+/// use discogs::data_structures::Artist;
+///
+/// let mut backend = MemoryBackend::new();
+/// backend.insert_artist(Artist::new(1, "The Persuader".to_string(), "".to_string()));
+///
+/// assert_eq!(backend.get_artist(1).ok().unwrap().name, "The Persuader".to_string());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBackend {
+    artists: BTreeMap<u32, Artist>,
+    labels: BTreeMap<u32, Label>,
+    masters: BTreeMap<u32, Master>,
+    releases: BTreeMap<u32, Release>,
+}
+
+fn not_found(kind: &str, id: u32) -> QueryError {
+    QueryError::NotFoundError {
+        reason: format!("No {} with id {}", kind, id)
+    }
+}
+
+// The API answers unknown ids with a 404
+fn not_found_on_404(error: QueryError, kind: &str, id: u32) -> QueryError {
+    match error {
+        QueryError::HyperStatusError { ref response } if response.status == StatusCode::NotFound => {
+            not_found(kind, id)
+        },
+        error => error,
+    }
+}
+
+fn contains(text: &str, pattern: &str) -> bool {
+    text.to_lowercase().contains(&pattern.to_lowercase())
+}
+
+fn digits(text: &str) -> String {
+    text.chars().filter(|c| c.is_digit(10)).collect()
+}
+
+/// A page of `releases`, numbered from 1 like the API
+fn page_of(releases: Vec<Release>, page: i16, per_page: i16) -> ArtistReleases {
+    let per_page = if per_page < 1 { 1 } else { per_page };
+    let page = if page < 1 { 1 } else { page };
+    let items = releases.len() as i64;
+    let pages = ((items + per_page as i64 - 1) / per_page as i64) as i16;
+
+    ArtistReleases {
+        pagination: Pagination {
+            per_page: per_page,
+            page: page,
+            items: items,
+            pages: pages,
+            urls: PaginationUrls {
+                next: None,
+                last: None,
+            },
+        },
+        releases: releases.into_iter()
+                          .skip((page as usize - 1) * per_page as usize)
+                          .take(per_page as usize)
+                          .collect(),
+    }
+}
+
+impl MemoryBackend {
+    pub fn new() -> MemoryBackend {
+        MemoryBackend::default()
+    }
+
+    pub fn insert_artist(&mut self, artist: Artist) -> &mut MemoryBackend {
+        self.artists.insert(artist.id, artist);
+        self
+    }
+
+    pub fn insert_label(&mut self, label: Label) -> &mut MemoryBackend {
+        self.labels.insert(label.id, label);
+        self
+    }
+
+    pub fn insert_master(&mut self, master: Master) -> &mut MemoryBackend {
+        self.masters.insert(master.id, master);
+        self
+    }
+
+    pub fn insert_release(&mut self, release: Release) -> &mut MemoryBackend {
+        self.releases.insert(release.id, release);
+        self
+    }
+
+    fn artist_matches(artist: &Artist, search: &Search) -> bool {
+        search.title.is_none() && search.artist.is_none() && search.label.is_none() &&
+        search.catno.is_none() && search.barcode.is_none() && search.year.is_none() &&
+        search.query.as_ref().map_or(true, |q| contains(&artist.name, q))
+    }
+
+    fn label_matches(label: &Label, search: &Search) -> bool {
+        search.title.is_none() && search.artist.is_none() && search.catno.is_none() &&
+        search.barcode.is_none() && search.year.is_none() &&
+        search.query.as_ref().map_or(true, |q| contains(&label.name, q)) &&
+        search.label.as_ref().map_or(true, |l| contains(&label.name, l))
+    }
+
+    fn credits_match(artists: &Option<Vec<Artist>>, pattern: &str) -> bool {
+        artists.as_ref().map_or(false, |a| a.iter().any(|a| contains(&a.name, pattern)))
+    }
+
+    fn master_matches(master: &Master, search: &Search) -> bool {
+        let title = master.title.as_ref().map_or("", |t| t.as_str());
+
+        search.label.is_none() && search.catno.is_none() && search.barcode.is_none() &&
+        search.query.as_ref().map_or(true, |q| contains(title, q)) &&
+        search.title.as_ref().map_or(true, |t| contains(title, t)) &&
+        search.artist.as_ref().map_or(true, |a| MemoryBackend::credits_match(&master.artists, a)) &&
+        search.year.map_or(true, |y| master.year == Some(y as u32))
+    }
+
+    fn release_matches(release: &Release, search: &Search) -> bool {
+        let labels = release.labels.as_ref().map_or(&[][..], |l| &l[..]);
+        let identifiers = release.identifiers.as_ref().map_or(&[][..], |i| &i[..]);
+
+        search.query.as_ref().map_or(true, |q| contains(&release.title, q)) &&
+        search.title.as_ref().map_or(true, |t| contains(&release.title, t)) &&
+        search.artist.as_ref().map_or(true, |a| MemoryBackend::credits_match(&release.artists, a)) &&
+        search.label.as_ref().map_or(true, |l| labels.iter().any(|label| contains(&label.name, l))) &&
+        search.catno.as_ref().map_or(true, |c| {
+            labels.iter().any(|label| label.catno.as_ref().map_or(false, |catno| contains(catno, c)))
+        }) &&
+        search.barcode.as_ref().map_or(true, |b| {
+            identifiers.iter().any(|i| i.identifier_type == "Barcode" && digits(&i.value) == digits(b))
+        }) &&
+        search.year.map_or(true, |y| release.year == y as u32)
+    }
+}
+
+impl DiscogsBackend for MemoryBackend {
+    fn get_artist(&self, id: u32) -> Result<Artist, QueryError> {
+        self.artists.get(&id).cloned().ok_or_else(|| not_found("artist", id))
+    }
+
+    fn get_artist_releases(&self, id: u32, page: i16, per_page: i16) -> Result<ArtistReleases, QueryError> {
+        if !self.artists.contains_key(&id) {
+            return Err(not_found("artist", id));
+        }
+
+        let releases = self.releases.values()
+                                    .filter(|r| r.artists.as_ref().map_or(false, |a| a.iter().any(|a| a.id == id)))
+                                    .cloned()
+                                    .collect();
+        Ok(page_of(releases, page, per_page))
+    }
+
+    fn get_label(&self, id: u32) -> Result<Label, QueryError> {
+        self.labels.get(&id).cloned().ok_or_else(|| not_found("label", id))
+    }
+
+    fn get_label_releases(&self, id: u32, page: i16, per_page: i16) -> Result<ArtistReleases, QueryError> {
+        if !self.labels.contains_key(&id) {
+            return Err(not_found("label", id));
+        }
+
+        let releases = self.releases.values()
+                                    .filter(|r| r.labels.as_ref().map_or(false, |l| l.iter().any(|l| l.id == id)))
+                                    .cloned()
+                                    .collect();
+        Ok(page_of(releases, page, per_page))
+    }
+
+    fn get_master(&self, id: u32) -> Result<Master, QueryError> {
+        self.masters.get(&id).cloned().ok_or_else(|| not_found("master", id))
+    }
+
+    fn get_release(&self, id: u32) -> Result<Release, QueryError> {
+        self.releases.get(&id).cloned().ok_or_else(|| not_found("release", id))
+    }
+
+    fn get_search_results(&self, search: &Search) -> Result<Vec<SearchResult>, QueryError> {
+        let wants = |search_type: SearchType| search.search_type.map_or(true, |t| t == search_type);
+        let mut results = Vec::new();
+
+        if wants(SearchType::Artist) {
+            results.extend(self.artists.values()
+                                       .filter(|a| MemoryBackend::artist_matches(a, search))
+                                       .map(|a| SearchResult::Artist { data: a.clone() }));
+        }
+        if wants(SearchType::Label) {
+            results.extend(self.labels.values()
+                                      .filter(|l| MemoryBackend::label_matches(l, search))
+                                      .map(|l| SearchResult::Label { data: l.clone() }));
+        }
+        if wants(SearchType::Master) {
+            results.extend(self.masters.values()
+                                       .filter(|m| MemoryBackend::master_matches(m, search))
+                                       .map(|m| SearchResult::Master { data: m.clone() }));
+        }
+        if wants(SearchType::Release) {
+            results.extend(self.releases.values()
+                                        .filter(|r| MemoryBackend::release_matches(r, search))
+                                        .map(|r| SearchResult::Release { data: r.clone() }));
+        }
+
+        results.truncate(SEARCH_PAGE_SIZE);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_structures::*;
+    use discogs::*;
+    use mockito::mock;
+    use serde_json;
+    use serde_json::to_string;
+
+    fn release(id: u32, title: &str, artist: u32, catno: &str, barcode: &str) -> Release {
+        serde_json::from_value(json!({
+            "id": id,
+            "title": title,
+            "status": "Accepted",
+            "resource_url": "",
+            "year": 1999,
+            "artists": [{ "id": artist, "name": format!("Artist {}", artist), "resource_url": "" }],
+            "labels": [{ "id": 5, "name": "Svek", "resource_url": "", "catno": catno }],
+            "identifiers": [{ "type": "Barcode", "value": barcode }]
+        })).unwrap()
+    }
+
+    fn backend() -> MemoryBackend {
+        let mut backend = MemoryBackend::new();
+        backend.insert_artist(Artist::new(1, "Artist 1".to_string(), "".to_string()))
+               .insert_label(Label::new(5, "Svek".to_string(), "".to_string()))
+               .insert_release(release(1, "Stockholm", 1, "SK032", "7 332334 432436"))
+               .insert_release(release(2, "Vasastaden", 2, "SK033", ""))
+               .insert_release(release(3, "Östermalm", 1, "SK034", ""));
+        backend
+    }
+
+    /// Application code that only knows about the trait
+    fn first_release_title<B: DiscogsBackend>(backend: &B, artist: u32) -> Option<String> {
+        backend.get_artist_releases(artist, 1, 1)
+               .ok()
+               .and_then(|r| r.releases.into_iter().next())
+               .map(|r| r.title)
+    }
+
+    #[test]
+    fn test_memory_get() {
+        let backend = backend();
+
+        assert_eq!(backend.get_release(2).ok().unwrap().title, "Vasastaden".to_string());
+        match backend.get_master(1) {
+            Err(QueryError::NotFoundError { .. }) => (),
+            other => panic!("Expected a NotFoundError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_memory_releases_are_paginated() {
+        let backend = backend();
+
+        let page = backend.get_artist_releases(1, 2, 1).ok().unwrap();
+        assert_eq!(page.pagination.pages, 2);
+        assert_eq!(page.releases[0].id, 3);
+
+        assert_eq!(backend.get_label_releases(5, 1, 50).ok().unwrap().pagination.items, 3);
+        assert_eq!(first_release_title(&backend, 1), Some("Stockholm".to_string()));
+    }
+
+    #[test]
+    fn test_memory_search() {
+        let backend = backend();
+
+        let ids = |results: Vec<SearchResult>| -> Vec<u32> {
+            results.into_iter()
+                   .map(|r| match r {
+                       SearchResult::Release { data } => data.id,
+                       SearchResult::Artist { data } => data.id,
+                       SearchResult::Label { data } => data.id,
+                       SearchResult::Master { data } => data.id,
+                   })
+                   .collect()
+        };
+
+        let mut search = Search::default();
+        search.catno = Some("sk033".to_string());
+        assert_eq!(ids(backend.get_search_results(&search).ok().unwrap()), vec![2]);
+
+        let mut search = Search::default();
+        search.barcode = Some("7332334432436".to_string());
+        assert_eq!(ids(backend.get_search_results(&search).ok().unwrap()), vec![1]);
+
+        let mut search = Search::default();
+        search.artist = Some("artist 1".to_string());
+        search.search_type = Some(SearchType::Release);
+        assert_eq!(ids(backend.get_search_results(&search).ok().unwrap()), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_memory_search_returns_the_first_page() {
+        let mut backend = MemoryBackend::new();
+        for id in 1..(SEARCH_PAGE_SIZE as u32 + 10) {
+            backend.insert_release(release(id, "Stockholm", 1, "", ""));
+        }
+
+        let mut search = Search::default();
+        search.title = Some("stockholm".to_string());
+        assert_eq!(backend.get_search_results(&search).ok().unwrap().len(), SEARCH_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_discogs_backend_not_found() {
+        mock("GET", "/releases/4701")
+            .with_status(404)
+            .with_header("content-type", "application/json")
+            .with_body("{\"message\": \"Release not found.\"}")
+            .create_for(|| {
+                match Discogs::new("USER_AGENT").get_release(4701) {
+                    Err(QueryError::NotFoundError { .. }) => (),
+                    other => panic!("Expected a NotFoundError, got {:?}", other),
+                }
+            });
+    }
+
+    #[test]
+    fn test_discogs_backend() {
+        mock("GET", "/artists/1/releases?page=1&per_page=1")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "pagination": {
+                    "per_page": 1,
+                    "items": 3,
+                    "page": 1,
+                    "urls": {},
+                    "pages": 3
+                },
+                "releases": [{
+                    "id": 1,
+                    "title": "Stockholm",
+                    "status": "Accepted",
+                    "resource_url": "",
+                    "year": 1999
+                }]
+            })).unwrap().as_str())
+            .create_for(|| {
+                let client = Discogs::new("USER_AGENT");
+
+                assert_eq!(first_release_title(&client, 1), Some("Stockholm".to_string()));
+            });
+    }
+
+    // The page of results of a search, the mocks only match the expected urls
+    fn search_page() -> String {
+        to_string(&json!({
+            "pagination": {
+                "per_page": 50,
+                "items": 0,
+                "page": 1,
+                "urls": {},
+                "pages": 0
+            },
+            "results": []
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_discogs_backend_search() {
+        let mut client = Discogs::new("USER_AGENT");
+        client.key("KEY").secret("SECRET");
+
+        mock("GET", "/database/search?catno=SK%2F032")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(search_page().as_str())
+            .create_for(|| {
+                let mut search = Search::default();
+                search.catno = Some("SK/032".to_string());
+
+                assert!(client.get_search_results(&search).ok().unwrap().is_empty());
+            });
+
+        mock("GET", "/database/search?artist=The%20Persuader&q=stockholm")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(search_page().as_str())
+            .create_for(|| {
+                let mut search = Search::default();
+                search.query = Some("stockholm".to_string());
+                search.artist = Some("The Persuader".to_string());
+
+                assert!(client.get_search_results(&search).ok().unwrap().is_empty());
+            });
+    }
+}
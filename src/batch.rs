@@ -0,0 +1,281 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+
+use discogs::Discogs;
+use query::QueryError;
+use std::panic;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+/// The default number of queries of a batch sent at once
+pub const DEFAULT_PARALLELISM: usize = 4;
+
+/// How far along a batch is
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BatchProgress {
+    pub total: usize,
+
+    // Finished queries, the failed ones included
+    pub done: usize,
+    pub failed: usize,
+}
+
+/// Stops a batch, the handle can be cloned and moved to other threads
+///
+/// The queries not yet sent fail with `QueryError::Cancelled`,
+/// the ones already sent are finished.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Cancellation {
+    pub fn new() -> Cancellation {
+        Cancellation::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Fetches many ids at once
+///
+/// Up to `parallelism` queries are sent at the same time. They wait on the
+/// rate limiter of the client, shared with its clones and their queries, so
+/// a batch never exceeds the budget of the other callers. The results are in
+/// the order of the ids.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use discogs::Discogs;
+/// use discogs::batch::Cancellation;
+///
+/// let mut client = Discogs::new("USER_AGENT");
+/// client.key("CLIENT_KEY").secret("CLIENT_SECRET");
+///
+/// let ids: Vec<u32> = (1..5001).collect();
+/// let cancellation = Cancellation::new();
+///
+/// let releases = client.releases(&ids)
+///                      .parallelism(8)
+///                      .progress(|progress| println!("{}/{}", progress.done, progress.total))
+///                      .cancellation(cancellation.clone())
+///                      .get();
+///
+/// for (id, release) in ids.iter().zip(releases) {
+///     if let Err(e) = release {
+///         println!("{} failed: {:?}", id, e);
+///     }
+/// }
+/// ```
+pub struct BatchQueryBuilder<T> {
+    ids: Vec<u32>,
+
+    // The query of one id
+    fetch: Arc<Fn(&Discogs, u32) -> Result<T, QueryError> + Send + Sync>,
+
+    client: Discogs,
+
+    parallelism: usize,
+    progress: Option<Arc<Fn(&BatchProgress) + Send + Sync>>,
+    cancellation: Cancellation,
+}
+
+impl<T: Send + 'static> BatchQueryBuilder<T> {
+    /// Creates a new instance of `BatchQueryBuilder` running `fetch` for every id
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::batch::BatchQueryBuilder;
+    ///
+    /// let bqb = BatchQueryBuilder::new(&[4567, 4568],
+    ///                                  |client: &Discogs, id| client.master(id).get(),
+    ///                                  &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new<F>(ids: &[u32], fetch: F, client: &Discogs) -> BatchQueryBuilder<T>
+        where F: Fn(&Discogs, u32) -> Result<T, QueryError> + Send + Sync + 'static
+    {
+        BatchQueryBuilder {
+            ids: ids.to_vec(),
+            fetch: Arc::new(fetch),
+            client: client.clone(),
+            parallelism: DEFAULT_PARALLELISM,
+            progress: None,
+            cancellation: Cancellation::new(),
+        }
+    }
+
+    /// Sets the number of queries sent at once, at least one
+    pub fn parallelism(&mut self, parallelism: usize) -> &mut BatchQueryBuilder<T> {
+        self.parallelism = if parallelism == 0 { 1 } else { parallelism };
+        self
+    }
+
+    /// Calls `callback` after every query, from the thread that sent it
+    pub fn progress<F>(&mut self, callback: F) -> &mut BatchQueryBuilder<T>
+        where F: Fn(&BatchProgress) + Send + Sync + 'static
+    {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Stops the batch when `cancellation` is cancelled
+    pub fn cancellation(&mut self, cancellation: Cancellation) -> &mut BatchQueryBuilder<T> {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Perform the queries, blocking until all of them are done
+    pub fn get(&self) -> Vec<Result<T, QueryError>> {
+        let ids = Arc::new(self.ids.clone());
+        let next = Arc::new(AtomicUsize::new(0));
+        let results: Arc<Mutex<Vec<Option<Result<T, QueryError>>>>> =
+            Arc::new(Mutex::new(self.ids.iter().map(|_| None).collect()));
+        let progress = Arc::new(Mutex::new(BatchProgress { total: self.ids.len(), ..BatchProgress::default() }));
+
+        let workers = if self.parallelism < self.ids.len() { self.parallelism } else { self.ids.len() };
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                let ids = ids.clone();
+                let next = next.clone();
+                let results = results.clone();
+                let progress = progress.clone();
+                let fetch = self.fetch.clone();
+                let client = self.client.clone();
+                let callback = self.progress.clone();
+                let cancellation = self.cancellation.clone();
+
+                thread::spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    if index >= ids.len() {
+                        break;
+                    }
+
+                    let result = if cancellation.is_cancelled() {
+                        Err(QueryError::Cancelled)
+                    } else {
+                        fetch(&client, ids[index])
+                    };
+                    let failed = result.is_err();
+                    results.lock().unwrap()[index] = Some(result);
+
+                    let mut state = progress.lock().unwrap();
+                    state.done += 1;
+                    if failed {
+                        state.failed += 1;
+                    }
+                    if let Some(ref callback) = callback {
+                        callback(&state);
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            if let Err(cause) = handle.join() {
+                panic::resume_unwind(cause);
+            }
+        }
+
+        let results = match Arc::try_unwrap(results) {
+            Ok(results) => results.into_inner().unwrap(),
+            Err(_) => unreachable!(),
+        };
+        results.into_iter().map(|result| result.unwrap()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use discogs::*;
+    use batch::*;
+    use query::QueryError;
+    use mockito::mock;
+    use serde_json::to_string;
+    use std::sync::{Arc, Mutex};
+
+    fn client() -> Discogs {
+        let mut client = Discogs::new("USER_AGENT");
+        client.rate_limit(6000);
+        client
+    }
+
+    #[test]
+    fn test_results_are_in_order() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let progress = seen.clone();
+
+        mock("GET", "/releases/4701")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "id": 4701,
+                "title": "title",
+                "resource_url": "resource_url",
+                "year": 2000,
+                "status": "Accepted"
+            })).unwrap().as_str())
+            .create_for(|| {
+                let releases = client().releases(&[4701, 4702, 4701, 4701])
+                                       .parallelism(3)
+                                       .progress(move |p| progress.lock().unwrap().push(*p))
+                                       .get();
+
+                assert_eq!(releases.len(), 4);
+                assert_eq!(releases[0].as_ref().ok().unwrap().id, 4701);
+                assert!(releases[1].is_err());
+                assert_eq!(releases[2].as_ref().ok().unwrap().id, 4701);
+                assert_eq!(releases[3].as_ref().ok().unwrap().id, 4701);
+            });
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 4);
+        assert_eq!(*seen.last().unwrap(), BatchProgress { total: 4, done: 4, failed: 1 });
+    }
+
+    #[test]
+    fn test_cancelled_batch() {
+        let cancellation = Cancellation::new();
+        cancellation.cancel();
+
+        let artists = client().artists(&[4703, 4704])
+                              .cancellation(cancellation)
+                              .get();
+
+        assert_eq!(artists.len(), 2);
+        for artist in artists {
+            match artist {
+                Err(QueryError::Cancelled) => {},
+                _ => panic!("Expected the query to be cancelled"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_batch() {
+        assert!(client().labels(&[]).get().is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Serves a caching proxy of the Discogs API, see `discogs::proxy`
+
+extern crate discogs;
+
+use discogs::{Discogs, API_RATE_LIMIT};
+use discogs::proxy::Proxy;
+use discogs::query::ResponseCache;
+use std::env;
+use std::io;
+use std::io::Write;
+use std::process;
+
+const USAGE: &'static str = "Usage: discogs-proxy [options]
+
+Options:
+    --listen ADDRESS      Address to serve on (default 127.0.0.1:8080)
+    --upstream URL        Url of the API (default https://api.discogs.com)
+    --user-agent AGENT    User agent sent upstream (default discogs-proxy)
+    --key KEY             Application key
+    --secret SECRET       Application secret
+    --token TOKEN         User token, instead of the key and secret
+    --rate-limit N        Requests per minute sent upstream (default 240)
+    --cache-dir DIR       Keep the responses in DIR instead of in memory
+    --cache-size N        Responses kept in memory (default 10000)
+    --cache-bytes N       Bytes of responses kept in DIR (default 1073741824)
+    --ttl SECONDS         How long responses are served from the cache (default 3600)
+    --private-token TOKEN Token callers send in X-Proxy-Token to reach anything but the database
+                          and the lists, like the orders, or to write (refused otherwise)";
+
+fn fail(message: &str) -> ! {
+    let _ = writeln!(io::stderr(), "{}\n\n{}", message, USAGE);
+    process::exit(1);
+}
+
+fn number<T: std::str::FromStr>(name: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| fail(&format!("{} expects a number, got {}", name, value)))
+}
+
+fn main() {
+    let mut listen = "127.0.0.1:8080".to_owned();
+    let mut upstream = None;
+    let mut user_agent = "discogs-proxy".to_owned();
+    let mut key = None;
+    let mut secret = None;
+    let mut token = None;
+    let mut rate_limit = API_RATE_LIMIT;
+    let mut cache_dir = None;
+    let mut cache_size = 10000;
+    let mut cache_bytes = 1024 * 1024 * 1024;
+    let mut ttl = None;
+    let mut private_token = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{}", USAGE);
+            return;
+        }
+
+        let value = args.next().unwrap_or_else(|| fail(&format!("Missing the value of {}", arg)));
+        match arg.as_str() {
+            "--listen" => listen = value,
+            "--upstream" => upstream = Some(value),
+            "--user-agent" => user_agent = value,
+            "--key" => key = Some(value),
+            "--secret" => secret = Some(value),
+            "--token" => token = Some(value),
+            "--rate-limit" => rate_limit = number(&arg, &value),
+            "--cache-dir" => cache_dir = Some(value),
+            "--cache-size" => cache_size = number(&arg, &value),
+            "--cache-bytes" => cache_bytes = number(&arg, &value),
+            "--ttl" => ttl = Some(number(&arg, &value)),
+            "--private-token" => private_token = Some(value),
+            _ => fail(&format!("Unknown option {}", arg)),
+        }
+    }
+
+    let mut client = Discogs::new(&user_agent);
+    if let Some(upstream) = upstream {
+        client.api_endpoint(&upstream);
+    }
+    if let Some(key) = key {
+        client.key(&key);
+    }
+    if let Some(secret) = secret {
+        client.secret(&secret);
+    }
+    if let Some(token) = token {
+        client.token(&token);
+    }
+
+    let mut cache = match cache_dir {
+        Some(dir) => ResponseCache::disk(&dir, cache_bytes).unwrap_or_else(|e| fail(&format!("Can not use {}: {:?}", dir, e))),
+        None => ResponseCache::memory(cache_size),
+    };
+    if let Some(ttl) = ttl {
+        cache.ttl(ttl);
+    }
+
+    let mut proxy = Proxy::new(client, rate_limit, cache);
+    if let Some(private_token) = private_token {
+        proxy.private_token(&private_token);
+    }
+    match proxy.listen(&listen) {
+        // Dropping the server waits for it, so it is served until killed
+        Ok(_server) => println!("Serving on {}", listen),
+        Err(e) => fail(&format!("Can not listen on {}: {:?}", listen, e)),
+    }
+}
@@ -13,6 +13,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use discogs::Discogs;
 use data_structures::*;
 use query::*;
 use serde_json;
@@ -85,6 +86,16 @@ impl Artist {
             realname: None,
         }
     }
+
+    /// The image that best fits `width` x `height`, see `Image::best`
+    pub fn best_image(&self, width: u32, height: u32) -> Option<&Image> {
+        self.images.as_ref().and_then(|images| Image::best(images, width, height))
+    }
+
+    /// The uri of the best image for `width` x `height`
+    pub fn best_image_uri(&self, width: u32, height: u32) -> Option<&str> {
+        self.best_image(width, height).map(|image| image.uri_for(width, height))
+    }
 }
 
 // TODO: make a more comprehensive test
@@ -95,20 +106,17 @@ impl PartialEq for Artist {
 }
 
 
+#[derive(Clone)]
 pub struct ArtistQueryBuilder {
     //artist id
     id: u32,
 
-    api_endpoint: String,
-    user_agent: String,
-
-    // Optional key and secret if necessary
-    key: Option<String>,
-    secret: Option<String>,
+    // The client making the query, sharing its credentials and state
+    client: Discogs,
 
     page: i16,
     per_page: i16,
-    releases : bool
+    releases : bool,
 }
 
 impl ArtistQueryBuilder {
@@ -117,28 +125,20 @@ impl ArtistQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::ArtistQueryBuilder;
     ///
     /// let aqb = ArtistQueryBuilder::new(4567,
-    ///                                   discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    ///                                   &Discogs::new("USER_AGENT"));
     /// ```
     pub fn new(id: u32,
-               api_endpoint: String,
-               user_agent: String,
-               key: Option<String>,
-               secret: Option<String>) -> ArtistQueryBuilder {
+               client: &Discogs) -> ArtistQueryBuilder {
         ArtistQueryBuilder {
             id: id,
-            api_endpoint: api_endpoint,
-            user_agent: user_agent,
-            key: key,
-            secret: secret,
+            client: client.clone(),
             page : 1,
             per_page : 50,
-            releases : false
+            releases : false,
         }
     }
 
@@ -213,27 +213,38 @@ impl ArtistQueryBuilder {
 
 }
 
-impl QueryBuilder for ArtistQueryBuilder {
-    fn get_key(&self) -> Option<String> {
-        self.key.clone()
+#[cfg(feature = "async")]
+impl ArtistQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<Artist> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+
+    /// `get_releases` without blocking the caller
+    pub fn get_releases_async(&self) -> QueryFuture<ArtistReleases> {
+        let mut query = self.clone();
+        self.client.spawn(move || query.get_releases())
     }
+}
 
-    fn get_secret(&self) -> Option<String> {
-        self.secret.clone()
+impl QueryBuilder for ArtistQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
     }
 
     fn get_query_url(&self) -> String {
 
         match self.releases {
-            false => format!("{}{}/{}", self.api_endpoint, ARTIST_ENDPOINT, self.id),
-            true => format!("{}{}/{}/releases?page={}&per_page={}", self.api_endpoint, ARTIST_ENDPOINT, self.id, self.page, self.per_page)
+            false => format!("{}{}/{}", self.client.get_api_endpoint(), ARTIST_ENDPOINT, self.id),
+            true => format!("{}{}/{}/releases?page={}&per_page={}", self.client.get_api_endpoint(), ARTIST_ENDPOINT, self.id, self.page, self.per_page)
         }
 
     }
 
 
-    fn get_user_agent(&self) -> String {
-        self.user_agent.clone()
+    fn get_cache(&self) -> Option<ResponseCache> {
+        self.client.get_response_cache()
     }
 }
 
@@ -254,10 +265,10 @@ mod tests {
         let qb = aqb(789);
 
         assert_eq!(qb.id, 789);
-        assert_eq!(qb.api_endpoint, API_URL.to_owned());
-        assert_eq!(qb.user_agent, "USER_AGENT".to_string());
-        assert!(qb.key.is_none());
-        assert!(qb.secret.is_none());
+        assert_eq!(qb.client.get_api_endpoint(), API_URL.to_owned());
+        assert_eq!(qb.client.get_user_agent(), "USER_AGENT".to_string());
+        assert!(qb.client.get_key().is_none());
+        assert!(qb.client.get_secret().is_none());
     }
 
     #[test]
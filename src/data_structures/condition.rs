@@ -0,0 +1,237 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+/// The grading of the media of an item for sale
+///
+/// Conditions are ordered from `Poor` (lowest) to `Mint` (highest)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum MediaCondition {
+    #[serde(rename = "Mint (M)")]
+    Mint,
+    #[serde(rename = "Near Mint (NM or M-)")]
+    NearMint,
+    #[serde(rename = "Very Good Plus (VG+)")]
+    VeryGoodPlus,
+    #[serde(rename = "Very Good (VG)")]
+    VeryGood,
+    #[serde(rename = "Good Plus (G+)")]
+    GoodPlus,
+    #[serde(rename = "Good (G)")]
+    Good,
+    #[serde(rename = "Fair (F)")]
+    Fair,
+    #[serde(rename = "Poor (P)")]
+    Poor,
+}
+
+impl MediaCondition {
+    pub fn to_string(&self) -> String {
+        match *self {
+            MediaCondition::Mint => "Mint (M)".to_string(),
+            MediaCondition::NearMint => "Near Mint (NM or M-)".to_string(),
+            MediaCondition::VeryGoodPlus => "Very Good Plus (VG+)".to_string(),
+            MediaCondition::VeryGood => "Very Good (VG)".to_string(),
+            MediaCondition::GoodPlus => "Good Plus (G+)".to_string(),
+            MediaCondition::Good => "Good (G)".to_string(),
+            MediaCondition::Fair => "Fair (F)".to_string(),
+            MediaCondition::Poor => "Poor (P)".to_string()
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match *self {
+            MediaCondition::Mint => 7,
+            MediaCondition::NearMint => 6,
+            MediaCondition::VeryGoodPlus => 5,
+            MediaCondition::VeryGood => 4,
+            MediaCondition::GoodPlus => 3,
+            MediaCondition::Good => 2,
+            MediaCondition::Fair => 1,
+            MediaCondition::Poor => 0
+        }
+    }
+}
+
+impl FromStr for MediaCondition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MediaCondition, String> {
+        match s {
+            "Mint (M)" => Ok(MediaCondition::Mint),
+            "Near Mint (NM or M-)" => Ok(MediaCondition::NearMint),
+            "Very Good Plus (VG+)" => Ok(MediaCondition::VeryGoodPlus),
+            "Very Good (VG)" => Ok(MediaCondition::VeryGood),
+            "Good Plus (G+)" => Ok(MediaCondition::GoodPlus),
+            "Good (G)" => Ok(MediaCondition::Good),
+            "Fair (F)" => Ok(MediaCondition::Fair),
+            "Poor (P)" => Ok(MediaCondition::Poor),
+            _ => Err(format!("Unknown media condition {}", s))
+        }
+    }
+}
+
+impl Ord for MediaCondition {
+    fn cmp(&self, other: &MediaCondition) -> Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+impl PartialOrd for MediaCondition {
+    fn partial_cmp(&self, other: &MediaCondition) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The grading of the sleeve of an item for sale
+///
+/// Conditions are ordered from `Poor` to `Mint`, the ungraded
+/// `Generic`, `NotGraded` and `NoCover` sleeves rank below `Poor`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum SleeveCondition {
+    #[serde(rename = "Mint (M)")]
+    Mint,
+    #[serde(rename = "Near Mint (NM or M-)")]
+    NearMint,
+    #[serde(rename = "Very Good Plus (VG+)")]
+    VeryGoodPlus,
+    #[serde(rename = "Very Good (VG)")]
+    VeryGood,
+    #[serde(rename = "Good Plus (G+)")]
+    GoodPlus,
+    #[serde(rename = "Good (G)")]
+    Good,
+    #[serde(rename = "Fair (F)")]
+    Fair,
+    #[serde(rename = "Poor (P)")]
+    Poor,
+    Generic,
+    #[serde(rename = "Not Graded")]
+    NotGraded,
+    #[serde(rename = "No Cover")]
+    NoCover,
+}
+
+impl SleeveCondition {
+    pub fn to_string(&self) -> String {
+        match *self {
+            SleeveCondition::Mint => "Mint (M)".to_string(),
+            SleeveCondition::NearMint => "Near Mint (NM or M-)".to_string(),
+            SleeveCondition::VeryGoodPlus => "Very Good Plus (VG+)".to_string(),
+            SleeveCondition::VeryGood => "Very Good (VG)".to_string(),
+            SleeveCondition::GoodPlus => "Good Plus (G+)".to_string(),
+            SleeveCondition::Good => "Good (G)".to_string(),
+            SleeveCondition::Fair => "Fair (F)".to_string(),
+            SleeveCondition::Poor => "Poor (P)".to_string(),
+            SleeveCondition::Generic => "Generic".to_string(),
+            SleeveCondition::NotGraded => "Not Graded".to_string(),
+            SleeveCondition::NoCover => "No Cover".to_string()
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match *self {
+            SleeveCondition::Mint => 10,
+            SleeveCondition::NearMint => 9,
+            SleeveCondition::VeryGoodPlus => 8,
+            SleeveCondition::VeryGood => 7,
+            SleeveCondition::GoodPlus => 6,
+            SleeveCondition::Good => 5,
+            SleeveCondition::Fair => 4,
+            SleeveCondition::Poor => 3,
+            SleeveCondition::Generic => 2,
+            SleeveCondition::NotGraded => 1,
+            SleeveCondition::NoCover => 0
+        }
+    }
+}
+
+impl FromStr for SleeveCondition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SleeveCondition, String> {
+        match s {
+            "Mint (M)" => Ok(SleeveCondition::Mint),
+            "Near Mint (NM or M-)" => Ok(SleeveCondition::NearMint),
+            "Very Good Plus (VG+)" => Ok(SleeveCondition::VeryGoodPlus),
+            "Very Good (VG)" => Ok(SleeveCondition::VeryGood),
+            "Good Plus (G+)" => Ok(SleeveCondition::GoodPlus),
+            "Good (G)" => Ok(SleeveCondition::Good),
+            "Fair (F)" => Ok(SleeveCondition::Fair),
+            "Poor (P)" => Ok(SleeveCondition::Poor),
+            "Generic" => Ok(SleeveCondition::Generic),
+            "Not Graded" => Ok(SleeveCondition::NotGraded),
+            "No Cover" => Ok(SleeveCondition::NoCover),
+            _ => Err(format!("Unknown sleeve condition {}", s))
+        }
+    }
+}
+
+impl Ord for SleeveCondition {
+    fn cmp(&self, other: &SleeveCondition) -> Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+impl PartialOrd for SleeveCondition {
+    fn partial_cmp(&self, other: &SleeveCondition) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data_structures::*;
+    use serde_json;
+
+    #[test]
+    fn test_media_condition_order() {
+        assert!(MediaCondition::Mint > MediaCondition::NearMint);
+        assert!(MediaCondition::VeryGoodPlus > MediaCondition::VeryGood);
+        assert!(MediaCondition::Fair > MediaCondition::Poor);
+
+        let mut conditions = vec![MediaCondition::Good,
+                                  MediaCondition::Mint,
+                                  MediaCondition::Poor];
+        conditions.sort();
+        assert_eq!(conditions, vec![MediaCondition::Poor,
+                                    MediaCondition::Good,
+                                    MediaCondition::Mint]);
+    }
+
+    #[test]
+    fn test_sleeve_condition_order() {
+        assert!(SleeveCondition::Mint > SleeveCondition::Poor);
+        assert!(SleeveCondition::Poor > SleeveCondition::Generic);
+        assert!(SleeveCondition::NotGraded > SleeveCondition::NoCover);
+    }
+
+    #[test]
+    fn test_condition_from_str() {
+        assert_eq!("Very Good Plus (VG+)".parse::<MediaCondition>(), Ok(MediaCondition::VeryGoodPlus));
+        assert_eq!("Not Graded".parse::<SleeveCondition>(), Ok(SleeveCondition::NotGraded));
+        assert!("Not Graded".parse::<MediaCondition>().is_err());
+    }
+
+    #[test]
+    fn test_condition_serialization() {
+        assert_eq!(serde_json::to_string(&MediaCondition::NearMint).unwrap(),
+                   "\"Near Mint (NM or M-)\"".to_string());
+        assert_eq!(serde_json::from_str::<SleeveCondition>("\"No Cover\"").unwrap(),
+                   SleeveCondition::NoCover);
+    }
+}
@@ -12,19 +12,313 @@
 //
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
+use discogs::Discogs;
+use query::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// Whether an image is the main image of an entity
+///
+/// Types discogs may add later are kept as `Unknown`
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum ImageType {
+    Primary,
+    Secondary,
+    Unknown(String),
+}
+
+impl ImageType {
+    pub fn to_string(&self) -> String {
+        match *self {
+            ImageType::Primary => "primary".to_string(),
+            ImageType::Secondary => "secondary".to_string(),
+            ImageType::Unknown(ref other) => other.clone()
+        }
+    }
+}
+
+impl FromStr for ImageType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ImageType, String> {
+        match s {
+            "primary" => Ok(ImageType::Primary),
+            "secondary" => Ok(ImageType::Secondary),
+            other => Ok(ImageType::Unknown(other.to_string()))
+        }
+    }
+}
+
+impl Serialize for ImageType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl Deserialize for ImageType {
+    fn deserialize<D>(deserializer: D) -> Result<ImageType, D::Error>
+        where D: Deserializer
+    {
+        let s = try!(String::deserialize(deserializer));
+        Ok(s.parse().unwrap())
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Image {
     pub resource_url: String,
-    // TODO: do a enum type
     #[serde(rename = "type")]
-    pub image_type: String,
+    pub image_type: ImageType,
     pub uri: String,
     pub uri150: String,
     pub height: u32,
     pub width: u32,
 }
 
-
 impl Image {
+    /// Picks the image that best fits `width` x `height`
+    ///
+    /// Primary images are preferred, then the closest dimensions
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::Image;
+    ///
+    /// let artist = Discogs::new("USER_AGENT").artist(4567).get().ok().unwrap();
+    ///
+    /// if let Some(ref images) = artist.images {
+    ///     let cover = Image::best(images, 600, 600);
+    /// }
+    /// ```
+    pub fn best(images: &[Image], width: u32, height: u32) -> Option<&Image> {
+        images.iter().min_by_key(|image| {
+            let distance = (image.width as i64 - width as i64).abs() +
+                           (image.height as i64 - height as i64).abs();
+
+            (image.image_type != ImageType::Primary, distance)
+        })
+    }
+
+    /// The uri to display this image at `width` x `height`,
+    /// the thumbnail when it is large enough
+    pub fn uri_for(&self, width: u32, height: u32) -> &str {
+        if width <= 150 && height <= 150 {
+            &self.uri150
+        } else {
+            &self.uri
+        }
+    }
+
+    /// Downloads the full size image
+    ///
+    /// The request goes through the image rate limit and cache of `client`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let client = Discogs::new("USER_AGENT");
+    /// let release = client.release(8481).get().ok().unwrap();
+    ///
+    /// if let Some(ref images) = release.images {
+    ///     let bytes = images[0].download(&client);
+    /// }
+    /// ```
+    pub fn download(&self, client: &Discogs) -> Result<Vec<u8>, QueryError> {
+        client.image(&self.uri).get()
+    }
+
+    /// Downloads the 150px thumbnail of the image
+    pub fn download_thumbnail(&self, client: &Discogs) -> Result<Vec<u8>, QueryError> {
+        client.image(&self.uri150).get()
+    }
+}
+
+#[derive(Clone)]
+pub struct ImageQueryBuilder {
+    // absolute uri of the image
+    uri: String,
+
+    // The client making the query, sharing its credentials and state
+    client: Discogs,
+}
+
+impl ImageQueryBuilder {
+    /// Creates a new instance of `ImageQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::ImageQueryBuilder;
+    ///
+    /// let iqb = ImageQueryBuilder::new("https://img.discogs.com/R-8481.jpg".to_string(),
+    ///                                  &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(uri: String,
+               client: &Discogs) -> ImageQueryBuilder {
+        ImageQueryBuilder {
+            uri: uri,
+            client: client.clone()
+        }
+    }
+
+    /// Perform request
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let bytes = Discogs::new("USER_AGENT")
+    ///                       .image("https://img.discogs.com/R-8481.jpg")
+    ///                       .get();
+    /// ```
+    pub fn get(&self) -> Result<Vec<u8>, QueryError> {
+        let cache = self.client.get_image_cache();
+
+        if let Some(ref cache) = cache {
+            if let Some(bytes) = cache.get(&self.uri) {
+                return Ok(bytes);
+            }
+        }
+
+        let bytes = try!(self.perform_bytes_request());
+
+        if let Some(ref cache) = cache {
+            // The image was downloaded, failing to cache it is not an error
+            let _ = cache.put(&self.uri, &bytes);
+        }
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(feature = "async")]
+impl ImageQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<Vec<u8>> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+}
+
+impl QueryBuilder for ImageQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        self.uri.clone()
+    }
+
+    // Images have their own, lower, rate limit
+    fn get_rate_limiter(&self) -> Arc<Mutex<RateLimiter>> {
+        self.client.image_rate_limiter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use discogs::*;
+    use data_structures::*;
+    use query::ImageCache;
+    use mockito::mock;
+    use serde_json;
+    use std::env;
+    use std::fs;
+
+    fn image() -> Image {
+        serde_json::from_value(json!({
+            "resource_url": format!("{}/images/R-1.jpg", API_URL),
+            "type": "primary",
+            "uri": format!("{}/images/R-1.jpg", API_URL),
+            "uri150": format!("{}/images/R-150-1.jpg", API_URL),
+            "height": 600,
+            "width": 600
+        })).unwrap()
+    }
+
+    fn sized(image_type: &str, width: u32, height: u32) -> Image {
+        serde_json::from_value(json!({
+            "resource_url": "",
+            "type": image_type,
+            "uri": format!("{}x{}", width, height),
+            "uri150": format!("150-{}x{}", width, height),
+            "height": height,
+            "width": width
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_image_type() {
+        assert_eq!(image().image_type, ImageType::Primary);
+        assert_eq!(sized("secondary", 1, 1).image_type, ImageType::Secondary);
+        assert_eq!(sized("back", 1, 1).image_type, ImageType::Unknown("back".to_string()));
+
+        let json = serde_json::to_value(&sized("back", 1, 1)).unwrap();
+        assert_eq!(json["type"], json!("back"));
+    }
+
+    #[test]
+    fn test_best_image() {
+        let images = vec![sized("secondary", 600, 600),
+                          sized("primary", 1000, 1000),
+                          sized("primary", 500, 480),
+                          sized("back", 600, 600)];
+
+        let best = Image::best(&images, 600, 600).unwrap();
+        assert_eq!(best.uri, "500x480".to_string());
+        assert_eq!(best.uri_for(600, 600), "500x480");
+        assert_eq!(best.uri_for(150, 150), "150-500x480");
+
+        assert_eq!(Image::best(&images[..1], 150, 150).unwrap().uri, "600x600".to_string());
+        assert!(Image::best(&[], 150, 150).is_none());
+    }
+
+    #[test]
+    fn test_download_thumbnail() {
+        mock("GET", "/images/R-150-1.jpg")
+            .with_status(200)
+            .with_header("content-type", "image/jpeg")
+            .with_body("thumbnail")
+            .create_for(|| {
+                let bytes = image().download_thumbnail(&Discogs::new("USER_AGENT"));
+
+                assert_eq!(bytes.ok(), Some(b"thumbnail".to_vec()));
+            });
+    }
+
+    #[test]
+    fn test_download_is_cached() {
+        let dir = env::temp_dir().join("discogs-rs-image-download");
+        let _ = fs::remove_dir_all(&dir);
+
+        let client = || {
+            let mut client = Discogs::new("USER_AGENT");
+            client.image_cache(ImageCache::new(&dir, 1024).ok().unwrap());
+            client
+        };
+
+        mock("GET", "/images/R-1.jpg")
+            .with_status(200)
+            .with_header("content-type", "image/jpeg")
+            .with_body("image")
+            .create_for(|| {
+                assert_eq!(image().download(&client()).ok(), Some(b"image".to_vec()));
+            });
+
+        // Served from the cache once the mock is gone
+        assert_eq!(image().download(&client()).ok(), Some(b"image".to_vec()));
+    }
 }
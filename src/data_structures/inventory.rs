@@ -0,0 +1,326 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use discogs::Discogs;
+use data_structures::*;
+use query::*;
+use serde_json;
+
+/// The endpoint for a user's resources
+const USER_ENDPOINT: &'static str = "/users";
+
+
+#[derive(Deserialize, Debug)]
+pub struct Inventory {
+    pub pagination: Pagination,
+    pub listings: Vec<Listing>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum InventorySort {
+    Listed,
+    Price,
+    Item,
+    Artist,
+    Label,
+    Catno,
+    Audio,
+    Status,
+}
+
+impl InventorySort {
+    pub fn to_string(&self) -> String {
+        match *self {
+            InventorySort::Listed => "listed".to_string(),
+            InventorySort::Price => "price".to_string(),
+            InventorySort::Item => "item".to_string(),
+            InventorySort::Artist => "artist".to_string(),
+            InventorySort::Label => "label".to_string(),
+            InventorySort::Catno => "catno".to_string(),
+            InventorySort::Audio => "audio".to_string(),
+            InventorySort::Status => "status".to_string()
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct InventoryQueryBuilder {
+    username: String,
+
+    // The client making the query, filtering by status is only honored
+    // when its token authenticates the owner of the inventory
+    client: Discogs,
+
+    page: i16,
+    per_page: i16,
+    status: Option<ListingStatus>,
+    sort: Option<InventorySort>,
+    sort_order: Option<SortOrder>,
+}
+
+impl InventoryQueryBuilder {
+    /// Creates a new instance of `InventoryQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::InventoryQueryBuilder;
+    ///
+    /// let iqb = InventoryQueryBuilder::new("username".to_string(),
+    ///                                      &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(username: String,
+               client: &Discogs) -> InventoryQueryBuilder {
+        InventoryQueryBuilder {
+            username: username,
+            client: client.clone(),
+            page: 1,
+            per_page: 50,
+            status: None,
+            sort: None,
+            sort_order: None,
+        }
+    }
+
+    pub fn pagination(&mut self, page: i16, per_page: i16) -> &mut InventoryQueryBuilder {
+        self.page = page;
+        self.per_page = per_page;
+        self
+    }
+
+    /// Only return listings with the given status
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::ListingStatus;
+    ///
+    /// let client = Discogs::new("USER_AGENT");
+    /// client.inventory("username").status(ListingStatus::Draft);
+    /// ```
+    pub fn status(&mut self, status: ListingStatus) -> &mut InventoryQueryBuilder {
+        self.status = Some(status);
+        self
+    }
+
+    /// Sort the listings by the given field
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::{InventorySort, SortOrder};
+    ///
+    /// let client = Discogs::new("USER_AGENT");
+    /// client.inventory("username").sort(InventorySort::Price, SortOrder::Descending);
+    /// ```
+    pub fn sort(&mut self, sort: InventorySort, sort_order: SortOrder) -> &mut InventoryQueryBuilder {
+        self.sort = Some(sort);
+        self.sort_order = Some(sort_order);
+        self
+    }
+
+    /// Perform request
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let inventory = Discogs::new("USER_AGENT")
+    ///                       .inventory("username")
+    ///                       .get();
+    /// ```
+    pub fn get(&self) -> Result<Inventory, QueryError> {
+        let result: Result<String, QueryError> = self.perform_request();
+
+        if let Err(error) = result {
+            return Err(error);
+        } else {
+            let result_string = result.ok().unwrap();
+            let json = serde_json::from_str(&result_string);
+
+            if let Ok(inventory) = json {
+                return Ok(inventory);
+            } else {
+                return Err(QueryError::JsonDecodeError {
+                    serde_err: json.err()
+                });
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl InventoryQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<Inventory> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+}
+
+impl QueryBuilder for InventoryQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        let mut url = format!("{}{}/{}/inventory?page={}&per_page={}",
+                              self.client.get_api_endpoint(),
+                              USER_ENDPOINT,
+                              encode_component(&self.username),
+                              self.page,
+                              self.per_page);
+
+        if let Some(ref status) = self.status {
+            url.push_str(&format!("&status={}", encode_component(&status.to_string())));
+        }
+        if let Some(ref sort) = self.sort {
+            url.push_str(&format!("&sort={}", encode_component(&sort.to_string())));
+        }
+        if let Some(ref sort_order) = self.sort_order {
+            url.push_str(&format!("&sort_order={}", encode_component(&sort_order.to_string())));
+        }
+
+        url
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use discogs::*;
+    use data_structures::*;
+    use query::QueryBuilder;
+    use mockito::mock;
+    use serde_json;
+    use serde_json::to_string;
+
+    fn iqb(username: &str) -> InventoryQueryBuilder {
+        Discogs::new("USER_AGENT").inventory(username)
+    }
+
+    #[test]
+    fn test_request_builder() {
+        let qb = iqb("seller");
+
+        assert_eq!(qb.username, "seller".to_string());
+        assert_eq!(qb.client.get_api_endpoint(), API_URL.to_owned());
+        assert_eq!(qb.client.get_user_agent(), "USER_AGENT".to_string());
+        assert!(qb.client.get_key().is_none());
+        assert!(qb.client.get_secret().is_none());
+        assert!(qb.client.get_token().is_none());
+    }
+
+    #[test]
+    fn test_query_url_filters() {
+        let mut qb = iqb("seller");
+        qb.status(ListingStatus::ForSale)
+          .sort(InventorySort::Price, SortOrder::Descending);
+
+        assert_eq!(qb.get_query_url(),
+                   format!("{}/users/seller/inventory?page=1&per_page=50&status=For%20Sale&sort=price&sort_order=desc",
+                           API_URL));
+    }
+
+    #[test]
+    fn test_query_url_encodes_the_username() {
+        assert_eq!(iqb("a b/c?").get_query_url(),
+                   format!("{}/users/a%20b%2Fc%3F/inventory?page=1&per_page=50", API_URL));
+
+        // Not a valid endpoint, but building the url does not panic
+        let mut client = Discogs::new("USER_AGENT");
+        client.api_endpoint("not a url");
+        assert_eq!(client.inventory("seller").get_query_url(),
+                   "not a url/users/seller/inventory?page=1&per_page=50".to_string());
+    }
+
+    #[test]
+    fn test_perform_inventory_request() {
+        mock("GET", "/users/seller/inventory?page=1&per_page=1&status=For%20Sale")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "pagination": {
+                    "per_page": 1,
+                    "items": 2,
+                    "page": 1,
+                    "urls": {
+                        "last": "https://api.discogs.com/users/seller/inventory?per_page=1&page=2",
+                        "next": "https://api.discogs.com/users/seller/inventory?per_page=1&page=2"
+                    },
+                    "pages": 2
+                },
+                "listings": [{
+                    "id": 150899904,
+                    "resource_url": "https://api.discogs.com/marketplace/listings/150899904",
+                    "status": "For Sale",
+                    "condition": "Near Mint (NM or M-)",
+                    "sleeve_condition": "Very Good Plus (VG+)",
+                    "price": {
+                        "currency": "USD",
+                        "value": 149.0
+                    },
+                    "ships_from": "United States",
+                    "allow_offers": false,
+                    "comments": "Still sealed",
+                    "audio": false,
+                    "posted": "2014-07-01T10:20:17-07:00",
+                    "seller": {
+                        "username": "seller",
+                        "id": 493556,
+                        "resource_url": "https://api.discogs.com/users/seller"
+                    },
+                    "release": {
+                        "id": 5610049,
+                        "resource_url": "https://api.discogs.com/releases/5610049",
+                        "description": "Lord Finesse - Down For The Underground",
+                        "catalog_number": "DR-001",
+                        "year": 2014
+                    }
+                }]
+            })).unwrap().as_str())
+            .create_for(|| {
+                let inventory = Discogs::new("USER_AGENT")
+                    .inventory("seller")
+                    .pagination(1, 1)
+                    .status(ListingStatus::ForSale)
+                    .get()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(inventory.pagination.items, 2);
+                assert_eq!(inventory.listings.len(), 1);
+
+                let listing = &inventory.listings[0];
+                assert_eq!(listing.id, 150899904);
+                assert_eq!(listing.status, ListingStatus::ForSale);
+                assert_eq!(listing.condition, MediaCondition::NearMint);
+                assert_eq!(listing.sleeve_condition, Some(SleeveCondition::VeryGoodPlus));
+                assert_eq!(listing.price, Price::new(149.0, Currency::USD));
+                assert_eq!(listing.ships_from, Some("United States".to_string()));
+                assert_eq!(listing.allow_offers, Some(false));
+                assert_eq!(listing.release.id, 5610049);
+                assert_eq!(listing.release.catalog_number, Some("DR-001".to_string()));
+            });
+    }
+}
@@ -0,0 +1,481 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use data_structures::*;
+use query::*;
+use std::collections::HashMap;
+
+/// The marketplace listings endpoint, used to fill in `Listing::resource_url`
+const LISTING_ENDPOINT: &'static str = "/marketplace/listings";
+
+/// The releases endpoint, used to fill in `ListingRelease::resource_url`
+const RELEASE_ENDPOINT: &'static str = "/releases";
+
+
+/// Splits csv `text` into its records, following RFC 4180
+pub fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, QueryError> {
+    parse_csv_lines(text).map(|records| records.into_iter().map(|(_, record)| record).collect())
+}
+
+/// Splits csv `text` into its records along with the line each record starts on
+///
+/// Quoted fields may span several lines, so this is not the index of the record.
+pub fn parse_csv_lines(text: &str) -> Result<Vec<(usize, Vec<String>)>, QueryError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                },
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                },
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => record.push(::std::mem::replace(&mut field, String::new())),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' => {
+                record.push(::std::mem::replace(&mut field, String::new()));
+                records.push((start, ::std::mem::replace(&mut record, Vec::new())));
+                line += 1;
+                start = line;
+            },
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(QueryError::CsvDecodeError {
+            line: line,
+            reason: "Unterminated quoted field".to_string(),
+        });
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((start, record));
+    }
+
+    Ok(records)
+}
+
+/// Formats a single csv record, quoting the fields that need it
+pub fn write_csv_record(fields: &[String]) -> String {
+    let mut record = fields.iter()
+        .map(|f| {
+            if f.contains(',') || f.contains('"') || f.contains('\n') || f.contains('\r') {
+                format!("\"{}\"", f.replace("\"", "\"\""))
+            } else {
+                f.clone()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+    record.push('\n');
+    record
+}
+
+/// A csv record, indexed by the column names of the header
+struct CsvRow<'a> {
+    line: usize,
+    columns: &'a HashMap<String, usize>,
+    fields: &'a [String],
+}
+
+impl<'a> CsvRow<'a> {
+    fn optional(&self, column: &str) -> Option<String> {
+        self.columns.get(column)
+            .and_then(|&i| self.fields.get(i))
+            .and_then(|f| if f.is_empty() { None } else { Some(f.clone()) })
+    }
+
+    fn required(&self, column: &str) -> Result<String, QueryError> {
+        self.optional(column).ok_or(QueryError::CsvDecodeError {
+            line: self.line,
+            reason: format!("Missing value for column {}", column),
+        })
+    }
+
+    fn error(&self, reason: String) -> QueryError {
+        QueryError::CsvDecodeError {
+            line: self.line,
+            reason: reason,
+        }
+    }
+}
+
+/// Parses an inventory export into listings
+///
+/// The export does not carry the currency of the prices nor the urls
+/// of the resources, so these are filled in from `currency` and `api_endpoint`
+///
+/// # Examples
+///
+/// ```
+/// use discogs::data_structures::*;
+///
+/// let csv = "listing_id,release_id,status,price,media_condition\n\
+///            41578241,5610049,For Sale,149.00,Mint (M)\n";
+///
+/// let listings = listings_from_csv(csv, Currency::USD, discogs::API_URL).ok().unwrap();
+/// assert_eq!(listings[0].release.id, 5610049);
+/// ```
+pub fn listings_from_csv(text: &str,
+                         currency: Currency,
+                         api_endpoint: &str) -> Result<Vec<Listing>, QueryError> {
+    let records = try!(parse_csv_lines(text));
+    let mut records = records.iter();
+
+    let columns: HashMap<String, usize> = match records.next() {
+        Some(&(_, ref header)) => header.iter()
+                              .enumerate()
+                              .map(|(i, name)| (name.trim().to_string(), i))
+                              .collect(),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut listings = Vec::new();
+
+    for &(line, ref fields) in records {
+        // Skip blank lines
+        if fields.len() == 1 && fields[0].is_empty() {
+            continue;
+        }
+
+        let row = CsvRow {
+            line: line,
+            columns: &columns,
+            fields: fields,
+        };
+
+        listings.push(try!(listing_from_row(&row, currency, api_endpoint)));
+    }
+
+    Ok(listings)
+}
+
+fn listing_from_row(row: &CsvRow,
+                    currency: Currency,
+                    api_endpoint: &str) -> Result<Listing, QueryError> {
+    let id: u64 = try!(try!(row.required("listing_id")).parse()
+                       .map_err(|_| row.error("Invalid listing_id".to_string())));
+    let release_id: u32 = try!(try!(row.required("release_id")).parse()
+                               .map_err(|_| row.error("Invalid release_id".to_string())));
+    let price: f64 = try!(try!(row.required("price")).parse()
+                          .map_err(|_| row.error("Invalid price".to_string())));
+    let status: ListingStatus = try!(try!(row.required("status")).parse()
+                                     .map_err(|e| row.error(e)));
+    let condition: MediaCondition = try!(try!(row.required("media_condition")).parse()
+                                         .map_err(|e| row.error(e)));
+
+    let sleeve_condition = match row.optional("sleeve_condition") {
+        Some(s) => Some(try!(s.parse::<SleeveCondition>().map_err(|e| row.error(e)))),
+        None => None,
+    };
+
+    let artist = row.optional("artist");
+    let title = row.optional("title");
+    let description = match (artist.clone(), title.clone()) {
+        (Some(a), Some(t)) => Some(format!("{} - {}", a, t)),
+        _ => None,
+    };
+
+    Ok(Listing {
+        id: id,
+        resource_url: format!("{}{}/{}", api_endpoint, LISTING_ENDPOINT, id),
+        status: status,
+        condition: condition,
+        sleeve_condition: sleeve_condition,
+        price: Price::new(price, currency),
+        release: ListingRelease {
+            id: release_id,
+            resource_url: format!("{}{}/{}", api_endpoint, RELEASE_ENDPOINT, release_id),
+            description: description,
+            catalog_number: row.optional("catno"),
+            year: None,
+            thumb: None,
+            artist: artist,
+            title: title,
+            format: row.optional("format"),
+        },
+        uri: None,
+        ships_from: None,
+        allow_offers: row.optional("accept_offer").map(|a| a == "Y"),
+        comments: row.optional("comments"),
+        audio: None,
+        posted: row.optional("listed"),
+        seller: None,
+        location: row.optional("location"),
+        external_id: row.optional("external_id"),
+        weight: row.optional("weight").and_then(|w| w.parse().ok()),
+        format_quantity: row.optional("format_quantity").and_then(|q| q.parse().ok()),
+    })
+}
+
+/// The columns accepted when adding listings
+const ADD_COLUMNS: &'static [&'static str] = &["release_id", "price", "media_condition",
+                                               "sleeve_condition", "comments", "accept_offer",
+                                               "status", "external_id", "location", "weight",
+                                               "format_quantity"];
+
+/// The columns accepted when changing listings
+const CHANGE_COLUMNS: &'static [&'static str] = &["listing_id", "price", "media_condition",
+                                                  "sleeve_condition", "comments", "accept_offer",
+                                                  "status", "external_id", "location", "weight",
+                                                  "format_quantity"];
+
+/// The columns accepted when deleting listings
+const DELETE_COLUMNS: &'static [&'static str] = &["listing_id"];
+
+fn listing_field(listing: &Listing, column: &str) -> String {
+    match column {
+        "listing_id" => listing.id.to_string(),
+        "release_id" => listing.release.id.to_string(),
+        "price" => format!("{:.2}", listing.price.value),
+        "media_condition" => listing.condition.to_string(),
+        "sleeve_condition" => listing.sleeve_condition.map(|c| c.to_string()).unwrap_or_default(),
+        "comments" => listing.comments.clone().unwrap_or_default(),
+        "accept_offer" => match listing.allow_offers {
+            Some(true) => "Y".to_string(),
+            Some(false) => "N".to_string(),
+            None => String::new(),
+        },
+        "status" => listing.status.to_string(),
+        "external_id" => listing.external_id.clone().unwrap_or_default(),
+        "location" => listing.location.clone().unwrap_or_default(),
+        "weight" => listing.weight.map(|w| w.to_string()).unwrap_or_default(),
+        "format_quantity" => listing.format_quantity.map(|q| q.to_string()).unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// Checks that discogs will accept `listing` in an add or change upload
+fn validate_upload_listing(listing: &Listing, line: usize) -> Result<(), QueryError> {
+    if !listing.price.value.is_finite() || listing.price.value <= 0.0 {
+        return Err(QueryError::InvalidRequestError {
+            reason: format!("Row {}: listing price must be positive, got {}", line, listing.price.value),
+        });
+    }
+
+    match listing.status {
+        ListingStatus::ForSale | ListingStatus::Draft => Ok(()),
+        ref status => Err(QueryError::InvalidRequestError {
+            reason: format!("Row {}: listings can not be uploaded as {}", line, status.to_string()),
+        }),
+    }
+}
+
+/// Generates the csv for an inventory upload of `upload_type`
+///
+/// Adding listings ignores their `id`, while deleting only uses it.
+/// Prices must be positive and statuses either `ForSale` or `Draft`
+/// for additions and changes.
+///
+/// # Examples
+///
+/// ```
+/// use discogs::data_structures::*;
+///
+/// let csv = "listing_id,release_id,status,price,media_condition\n\
+///            41578241,5610049,For Sale,149.00,Mint (M)\n";
+///
+/// let listings = listings_from_csv(csv, Currency::USD, discogs::API_URL).ok().unwrap();
+/// let upload = listings_to_csv(&listings, &UploadType::Delete).ok().unwrap();
+///
+/// assert_eq!(upload, "listing_id\n41578241\n".to_string());
+/// ```
+pub fn listings_to_csv(listings: &[Listing], upload_type: &UploadType) -> Result<String, QueryError> {
+    let columns = match *upload_type {
+        UploadType::Add => ADD_COLUMNS,
+        UploadType::Change => CHANGE_COLUMNS,
+        UploadType::Delete => DELETE_COLUMNS,
+    };
+
+    let header: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+    let mut csv = write_csv_record(&header);
+
+    for (i, listing) in listings.iter().enumerate() {
+        if *upload_type != UploadType::Delete {
+            try!(validate_upload_listing(listing, i + 2));
+        }
+
+        let fields: Vec<String> = columns.iter().map(|c| listing_field(listing, c)).collect();
+        csv.push_str(&write_csv_record(&fields));
+    }
+
+    Ok(csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use data_structures::*;
+    use query::QueryError;
+    use super::{parse_csv, parse_csv_lines, write_csv_record};
+
+    #[test]
+    fn test_parse_csv_quoting() {
+        let records = parse_csv("a,\"b,c\",\"say \"\"hi\"\"\"\r\n1,\"two\nlines\",\n").ok().unwrap();
+
+        assert_eq!(records, vec![
+            vec!["a".to_string(), "b,c".to_string(), "say \"hi\"".to_string()],
+            vec!["1".to_string(), "two\nlines".to_string(), "".to_string()],
+        ]);
+    }
+
+    #[test]
+    fn test_parse_csv_unterminated_quote() {
+        match parse_csv("a,\"b\n") {
+            Err(QueryError::CsvDecodeError { line, .. }) => assert_eq!(line, 2),
+            other => panic!("Expected a CsvDecodeError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_write_csv_record() {
+        let fields = vec!["plain".to_string(),
+                          "with, comma".to_string(),
+                          "with \"quotes\"".to_string()];
+
+        assert_eq!(write_csv_record(&fields),
+                   "plain,\"with, comma\",\"with \"\"quotes\"\"\"\n".to_string());
+        assert_eq!(parse_csv(&write_csv_record(&fields)).ok().unwrap(), vec![fields]);
+    }
+
+    #[test]
+    fn test_listings_from_csv() {
+        let csv = "listing_id,artist,title,label,catno,format,release_id,status,price,listed,comments,media_condition,sleeve_condition,accept_offer,external_id,weight,format_quantity,flat_shipping,location\n\
+                   41578241,Persuader,Stockholm,Svek,SK032,\"2x12\"\"\",1,For Sale,42.00,2017-01-10 10:00:00,\"Plays great, no skips\",Very Good Plus (VG+),Generic,Y,A-12,230,1,,Shelf 3\n\
+                   41578242,,,,,,2,Draft,7.5,,,Good (G),,N,,,,,\n";
+
+        let listings = listings_from_csv(csv, Currency::EUR, "https://api.discogs.com").ok().unwrap();
+
+        assert_eq!(listings.len(), 2);
+
+        assert_eq!(listings[0].id, 41578241);
+        assert_eq!(listings[0].resource_url, "https://api.discogs.com/marketplace/listings/41578241".to_string());
+        assert_eq!(listings[0].status, ListingStatus::ForSale);
+        assert_eq!(listings[0].price, Price::new(42.0, Currency::EUR));
+        assert_eq!(listings[0].condition, MediaCondition::VeryGoodPlus);
+        assert_eq!(listings[0].sleeve_condition, Some(SleeveCondition::Generic));
+        assert_eq!(listings[0].comments, Some("Plays great, no skips".to_string()));
+        assert_eq!(listings[0].allow_offers, Some(true));
+        assert_eq!(listings[0].release.id, 1);
+        assert_eq!(listings[0].release.format, Some("2x12\"".to_string()));
+        assert_eq!(listings[0].release.description, Some("Persuader - Stockholm".to_string()));
+        assert_eq!(listings[0].location, Some("Shelf 3".to_string()));
+
+        assert_eq!(listings[1].status, ListingStatus::Draft);
+        assert_eq!(listings[1].sleeve_condition, None);
+        assert_eq!(listings[1].allow_offers, Some(false));
+    }
+
+    #[test]
+    fn test_listings_from_csv_invalid_condition() {
+        let csv = "listing_id,release_id,status,price,media_condition\n\
+                   1,2,For Sale,1.00,Brand New\n";
+
+        match listings_from_csv(csv, Currency::USD, "https://api.discogs.com") {
+            Err(QueryError::CsvDecodeError { line, .. }) => assert_eq!(line, 2),
+            other => panic!("Expected a CsvDecodeError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_errors_report_the_physical_line() {
+        let csv = "listing_id,release_id,status,price,media_condition,comments\n\
+                   1,2,For Sale,1.00,Mint (M),\"two\nlines\"\n\
+                   3,4,For Sale,1.00,Brand New,\n";
+
+        match listings_from_csv(csv, Currency::USD, "https://api.discogs.com") {
+            Err(QueryError::CsvDecodeError { line, .. }) => assert_eq!(line, 4),
+            other => panic!("Expected a CsvDecodeError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_csv_lines() {
+        let records = parse_csv_lines("a,b\n\"x\ny\",z\nc,d").ok().unwrap();
+        let lines: Vec<usize> = records.iter().map(|&(line, _)| line).collect();
+
+        assert_eq!(lines, vec![1, 2, 4]);
+    }
+
+    fn listings() -> Vec<Listing> {
+        let csv = "listing_id,release_id,status,price,media_condition,sleeve_condition,comments,accept_offer\n\
+                   41578241,1,For Sale,42.5,Mint (M),Generic,\"Sealed, hype sticker\",Y\n\
+                   41578242,2,Draft,7,Good (G),,,\n";
+
+        listings_from_csv(csv, Currency::USD, "https://api.discogs.com").ok().unwrap()
+    }
+
+    #[test]
+    fn test_listings_to_add_csv() {
+        let csv = listings_to_csv(&listings(), &UploadType::Add).ok().unwrap();
+
+        assert_eq!(csv,
+                   "release_id,price,media_condition,sleeve_condition,comments,accept_offer,status,external_id,location,weight,format_quantity\n\
+                    1,42.50,Mint (M),Generic,\"Sealed, hype sticker\",Y,For Sale,,,,\n\
+                    2,7.00,Good (G),,,,Draft,,,,\n".to_string());
+    }
+
+    #[test]
+    fn test_listings_to_change_csv_round_trip() {
+        let csv = listings_to_csv(&listings(), &UploadType::Change).ok().unwrap();
+        let parsed = parse_csv(&csv).ok().unwrap();
+
+        assert_eq!(parsed[0][0], "listing_id".to_string());
+        assert_eq!(parsed[1][0], "41578241".to_string());
+        assert_eq!(parsed[1][4], "Sealed, hype sticker".to_string());
+    }
+
+    #[test]
+    fn test_listings_to_csv_rejects_invalid_rows() {
+        let mut sold = listings();
+        sold[1].status = ListingStatus::Sold;
+
+        match listings_to_csv(&sold, &UploadType::Add) {
+            Err(QueryError::InvalidRequestError { .. }) => (),
+            other => panic!("Expected an InvalidRequestError, got {:?}", other),
+        }
+
+        let mut free = listings();
+        free[0].price.value = 0.0;
+
+        match listings_to_csv(&free, &UploadType::Change) {
+            Err(QueryError::InvalidRequestError { .. }) => (),
+            other => panic!("Expected an InvalidRequestError, got {:?}", other),
+        }
+
+        // Deletes only need the listing id
+        assert!(listings_to_csv(&sold, &UploadType::Delete).is_ok());
+    }
+}
@@ -0,0 +1,486 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use discogs::Discogs;
+use data_structures::*;
+use query::*;
+use serde_json;
+use hyper::header::Location;
+use hyper::method::Method;
+use std::thread;
+use std::time::Duration;
+
+/// The inventory export endpoint
+const EXPORT_ENDPOINT: &'static str = "/inventory/export";
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InventoryExport {
+    pub id: u32,
+    pub status: String,
+    pub url: Option<String>,
+    pub download_url: Option<String>,
+    pub filename: Option<String>,
+    pub created_ts: Option<String>,
+    pub finished_ts: Option<String>,
+}
+
+impl InventoryExport {
+    /// Whether the export is ready to be downloaded
+    pub fn is_finished(&self) -> bool {
+        self.status == "success"
+    }
+
+    /// Whether discogs gave up on the export
+    pub fn is_failed(&self) -> bool {
+        self.status == "failed"
+    }
+}
+
+// TODO: make a more comprehensive test
+impl PartialEq for InventoryExport {
+    fn eq(&self, other: &InventoryExport) -> bool {
+        other.id == self.id
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InventoryExports {
+    pub pagination: Pagination,
+    pub items: Vec<InventoryExport>,
+}
+
+
+#[derive(Clone)]
+pub struct InventoryExportsQueryBuilder {
+    // The client making the query, its token is required for every export request
+    client: Discogs,
+
+    page: i16,
+    per_page: i16,
+    request: bool,
+}
+
+impl InventoryExportsQueryBuilder {
+    /// Creates a new instance of `InventoryExportsQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::InventoryExportsQueryBuilder;
+    ///
+    /// let ieqb = InventoryExportsQueryBuilder::new(&Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(client: &Discogs) -> InventoryExportsQueryBuilder {
+        InventoryExportsQueryBuilder {
+            client: client.clone(),
+            page: 1,
+            per_page: 50,
+            request: false,
+        }
+    }
+
+    pub fn pagination(&mut self, page: i16, per_page: i16) -> &mut InventoryExportsQueryBuilder {
+        self.page = page;
+        self.per_page = per_page;
+        self
+    }
+
+    /// Requests a new export of the inventory, returning its id
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let id = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .inventory_exports()
+    ///                       .request();
+    /// ```
+    pub fn request(&mut self) -> Result<u32, QueryError> {
+        self.request = true;
+
+        let (headers, _) = try!(self.send_request(Method::Post, None));
+
+        // The id of the new export is only sent in the location header
+        match headers.get::<Location>() {
+            Some(location) => location.0
+                                      .rsplit('/')
+                                      .next()
+                                      .and_then(|id| id.parse().ok())
+                                      .ok_or(QueryError::EmptyResponseError),
+            None => Err(QueryError::EmptyResponseError),
+        }
+    }
+
+    /// Perform request for the previous exports
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let exports = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .inventory_exports()
+    ///                       .get();
+    /// ```
+    pub fn get(&mut self) -> Result<InventoryExports, QueryError> {
+        self.request = false;
+
+        let result: Result<String, QueryError> = self.perform_request();
+
+        if let Err(error) = result {
+            return Err(error);
+        } else {
+            let result_string = result.ok().unwrap();
+            let json = serde_json::from_str(&result_string);
+
+            if let Ok(exports) = json {
+                return Ok(exports);
+            } else {
+                return Err(QueryError::JsonDecodeError {
+                    serde_err: json.err()
+                });
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl InventoryExportsQueryBuilder {
+    /// `request` without blocking the caller
+    pub fn request_async(&self) -> QueryFuture<u32> {
+        let mut query = self.clone();
+        self.client.spawn(move || query.request())
+    }
+
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<InventoryExports> {
+        let mut query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+}
+
+impl QueryBuilder for InventoryExportsQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        match self.request {
+            false => format!("{}{}?page={}&per_page={}", self.client.get_api_endpoint(), EXPORT_ENDPOINT, self.page, self.per_page),
+            true => format!("{}{}", self.client.get_api_endpoint(), EXPORT_ENDPOINT)
+        }
+    }
+}
+
+
+#[derive(Clone)]
+pub struct InventoryExportQueryBuilder {
+    //export id
+    id: u32,
+
+    // The client making the query, its token is required for every export request
+    client: Discogs,
+
+    download: bool,
+}
+
+impl InventoryExportQueryBuilder {
+    /// Creates a new instance of `InventoryExportQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::InventoryExportQueryBuilder;
+    ///
+    /// let ieqb = InventoryExportQueryBuilder::new(599632,
+    ///                                             &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(id: u32,
+               client: &Discogs) -> InventoryExportQueryBuilder {
+        InventoryExportQueryBuilder {
+            id: id,
+            client: client.clone(),
+            download: false,
+        }
+    }
+
+    /// Perform request for the status of the export
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let export = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .inventory_export(599632)
+    ///                       .get();
+    /// ```
+    pub fn get(&mut self) -> Result<InventoryExport, QueryError> {
+        self.download = false;
+
+        let result: Result<String, QueryError> = self.perform_request();
+
+        if let Err(error) = result {
+            return Err(error);
+        } else {
+            let result_string = result.ok().unwrap();
+            let json = serde_json::from_str(&result_string);
+
+            if let Ok(export) = json {
+                return Ok(export);
+            } else {
+                return Err(QueryError::JsonDecodeError {
+                    serde_err: json.err()
+                });
+            }
+        }
+    }
+
+    /// Polls the status of the export every `interval` until it either
+    /// finishes, fails or `max_attempts` requests have been made
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    /// use std::time::Duration;
+    ///
+    /// let export = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .inventory_export(599632)
+    ///                       .wait(Duration::from_secs(10), 30);
+    /// ```
+    pub fn wait(&mut self, interval: Duration, max_attempts: u32) -> Result<InventoryExport, QueryError> {
+        let mut export = try!(self.get());
+        let mut attempts = 1;
+
+        while !export.is_finished() && !export.is_failed() && attempts < max_attempts {
+            thread::sleep(interval);
+            export = try!(self.get());
+            attempts += 1;
+        }
+
+        Ok(export)
+    }
+
+    /// Downloads the export as csv
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let csv = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .inventory_export(599632)
+    ///                       .download();
+    /// ```
+    pub fn download(&mut self) -> Result<String, QueryError> {
+        self.download = true;
+        self.perform_request()
+    }
+
+    /// Downloads the export and parses it into listings priced in `currency`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::Currency;
+    ///
+    /// let listings = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .inventory_export(599632)
+    ///                       .download_listings(Currency::USD);
+    /// ```
+    pub fn download_listings(&mut self, currency: Currency) -> Result<Vec<Listing>, QueryError> {
+        let csv = try!(self.download());
+        listings_from_csv(&csv, currency, &self.client.get_api_endpoint())
+    }
+}
+
+#[cfg(feature = "async")]
+impl InventoryExportQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<InventoryExport> {
+        let mut query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+
+    /// `wait` without blocking the caller
+    pub fn wait_async(&self, interval: Duration, max_attempts: u32) -> QueryFuture<InventoryExport> {
+        let mut query = self.clone();
+        self.client.spawn(move || query.wait(interval, max_attempts))
+    }
+
+    /// `download` without blocking the caller
+    pub fn download_async(&self) -> QueryFuture<String> {
+        let mut query = self.clone();
+        self.client.spawn(move || query.download())
+    }
+
+    /// `download_listings` without blocking the caller
+    pub fn download_listings_async(&self, currency: Currency) -> QueryFuture<Vec<Listing>> {
+        let mut query = self.clone();
+        self.client.spawn(move || query.download_listings(currency))
+    }
+}
+
+impl QueryBuilder for InventoryExportQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        match self.download {
+            false => format!("{}{}/{}", self.client.get_api_endpoint(), EXPORT_ENDPOINT, self.id),
+            true => format!("{}{}/{}/download", self.client.get_api_endpoint(), EXPORT_ENDPOINT, self.id)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use discogs::*;
+    use data_structures::*;
+    use mockito::mock;
+    use serde_json;
+    use serde_json::to_string;
+
+    fn export_json(status: &str) -> serde_json::Value {
+        json!({
+            "id": 599632,
+            "status": status,
+            "url": "https://api.discogs.com/inventory/export/599632",
+            "download_url": "https://api.discogs.com/inventory/export/599632/download",
+            "filename": "seller-inventory-20170927-1259.csv",
+            "created_ts": "2017-09-27T12:59:02",
+            "finished_ts": "2017-09-27T12:59:02"
+        })
+    }
+
+    #[test]
+    fn test_request_builder() {
+        let qb = Discogs::new("USER_AGENT").inventory_export(599632);
+
+        assert_eq!(qb.id, 599632);
+        assert_eq!(qb.client.get_api_endpoint(), API_URL.to_owned());
+        assert_eq!(qb.client.get_user_agent(), "USER_AGENT".to_string());
+        assert!(qb.client.get_token().is_none());
+    }
+
+    #[test]
+    fn test_perform_export_request() {
+        mock("POST", "/inventory/export")
+            .with_status(200)
+            .with_header("location", "https://api.discogs.com/inventory/export/599632")
+            .create_for(|| {
+                let id = Discogs::new("USER_AGENT")
+                    .token("USER_TOKEN")
+                    .inventory_exports()
+                    .request()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(id, 599632);
+            });
+    }
+
+    #[test]
+    fn test_perform_exports_request() {
+        mock("GET", "/inventory/export?page=1&per_page=50")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "pagination": {
+                    "per_page": 50,
+                    "items": 1,
+                    "page": 1,
+                    "urls": {},
+                    "pages": 1
+                },
+                "items": [export_json("success")]
+            })).unwrap().as_str())
+            .create_for(|| {
+                let exports = Discogs::new("USER_AGENT")
+                    .token("USER_TOKEN")
+                    .inventory_exports()
+                    .get()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(exports.items.len(), 1);
+                assert!(exports.items[0].is_finished());
+            });
+    }
+
+    #[test]
+    fn test_perform_export_status_request() {
+        mock("GET", "/inventory/export/599632")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&export_json("pending")).unwrap().as_str())
+            .create_for(|| {
+                let export = Discogs::new("USER_AGENT")
+                    .token("USER_TOKEN")
+                    .inventory_export(599632)
+                    .get()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(export.id, 599632);
+                assert!(!export.is_finished());
+                assert!(!export.is_failed());
+            });
+    }
+
+    #[test]
+    fn test_perform_export_download_request() {
+        mock("GET", "/inventory/export/599632/download")
+            .with_status(200)
+            .with_header("content-type", "text/csv")
+            .with_body("listing_id,release_id,status,price,media_condition,sleeve_condition\n\
+                        41578241,1,For Sale,42.00,Mint (M),Near Mint (NM or M-)\n")
+            .create_for(|| {
+                let listings = Discogs::new("USER_AGENT")
+                    .token("USER_TOKEN")
+                    .inventory_export(599632)
+                    .download_listings(Currency::GBP)
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(listings.len(), 1);
+                assert_eq!(listings[0].id, 41578241);
+                assert_eq!(listings[0].price, Price::new(42.0, Currency::GBP));
+                assert_eq!(listings[0].sleeve_condition, Some(SleeveCondition::NearMint));
+            });
+    }
+}
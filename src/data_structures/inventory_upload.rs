@@ -0,0 +1,428 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use discogs::Discogs;
+use data_structures::*;
+use query::*;
+use serde_json;
+use hyper::header::Location;
+use hyper::method::Method;
+
+/// The inventory upload endpoint
+const UPLOAD_ENDPOINT: &'static str = "/inventory/upload";
+
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum UploadType {
+    #[serde(rename = "add")]
+    Add,
+    #[serde(rename = "change")]
+    Change,
+    #[serde(rename = "delete")]
+    Delete,
+}
+
+impl UploadType {
+    pub fn to_string(&self) -> String {
+        match *self {
+            UploadType::Add => "add".to_string(),
+            UploadType::Change => "change".to_string(),
+            UploadType::Delete => "delete".to_string()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InventoryUpload {
+    pub id: u32,
+    pub status: String,
+    #[serde(rename = "type")]
+    pub upload_type: Option<UploadType>,
+    pub results: Option<String>,
+    pub filename: Option<String>,
+    pub created_ts: Option<String>,
+    pub finished_ts: Option<String>,
+}
+
+impl InventoryUpload {
+    /// Whether discogs finished processing the upload
+    pub fn is_finished(&self) -> bool {
+        self.status == "success"
+    }
+
+    /// Whether discogs gave up on the upload
+    pub fn is_failed(&self) -> bool {
+        self.status == "failed"
+    }
+}
+
+// TODO: make a more comprehensive test
+impl PartialEq for InventoryUpload {
+    fn eq(&self, other: &InventoryUpload) -> bool {
+        other.id == self.id
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InventoryUploads {
+    pub pagination: Pagination,
+    pub items: Vec<InventoryUpload>,
+}
+
+
+#[derive(Clone)]
+pub struct InventoryUploadsQueryBuilder {
+    // The client making the query, its token is required for every upload request
+    client: Discogs,
+
+    page: i16,
+    per_page: i16,
+    upload_type: Option<UploadType>,
+}
+
+impl InventoryUploadsQueryBuilder {
+    /// Creates a new instance of `InventoryUploadsQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::InventoryUploadsQueryBuilder;
+    ///
+    /// let iuqb = InventoryUploadsQueryBuilder::new(&Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(client: &Discogs) -> InventoryUploadsQueryBuilder {
+        InventoryUploadsQueryBuilder {
+            client: client.clone(),
+            page: 1,
+            per_page: 50,
+            upload_type: None,
+        }
+    }
+
+    pub fn pagination(&mut self, page: i16, per_page: i16) -> &mut InventoryUploadsQueryBuilder {
+        self.page = page;
+        self.per_page = per_page;
+        self
+    }
+
+    /// Uploads `listings` as a csv of the given type, returning the id of the upload
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::*;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.token("USER_TOKEN");
+    ///
+    /// let listings = client.inventory("username").get().ok().unwrap().listings;
+    /// let id = client.inventory_uploads().upload(&listings, UploadType::Change);
+    /// ```
+    pub fn upload(&mut self, listings: &[Listing], upload_type: UploadType) -> Result<u32, QueryError> {
+        let csv = try!(listings_to_csv(listings, &upload_type));
+
+        let mut multipart = Multipart::new();
+        multipart.file("upload", "inventory.csv", "text/csv", &csv);
+
+        self.upload_type = Some(upload_type);
+        let sent = self.send_request(Method::Post, Some(RequestBody::Multipart(multipart)));
+        self.upload_type = None;
+        let (headers, _) = try!(sent);
+
+        // The id of the new upload is only sent in the location header
+        match headers.get::<Location>() {
+            Some(location) => location.0
+                                      .rsplit('/')
+                                      .next()
+                                      .and_then(|id| id.parse().ok())
+                                      .ok_or(QueryError::EmptyResponseError),
+            None => Err(QueryError::EmptyResponseError),
+        }
+    }
+
+    /// Adds `listings` to the inventory
+    pub fn add(&mut self, listings: &[Listing]) -> Result<u32, QueryError> {
+        self.upload(listings, UploadType::Add)
+    }
+
+    /// Changes the listings in the inventory to match `listings`
+    pub fn change(&mut self, listings: &[Listing]) -> Result<u32, QueryError> {
+        self.upload(listings, UploadType::Change)
+    }
+
+    /// Deletes `listings` from the inventory
+    pub fn delete(&mut self, listings: &[Listing]) -> Result<u32, QueryError> {
+        self.upload(listings, UploadType::Delete)
+    }
+
+    /// Perform request for the previous uploads
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let uploads = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .inventory_uploads()
+    ///                       .get();
+    /// ```
+    pub fn get(&mut self) -> Result<InventoryUploads, QueryError> {
+        self.upload_type = None;
+
+        let result: Result<String, QueryError> = self.perform_request();
+
+        if let Err(error) = result {
+            return Err(error);
+        } else {
+            let result_string = result.ok().unwrap();
+            let json = serde_json::from_str(&result_string);
+
+            if let Ok(uploads) = json {
+                return Ok(uploads);
+            } else {
+                return Err(QueryError::JsonDecodeError {
+                    serde_err: json.err()
+                });
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl InventoryUploadsQueryBuilder {
+    /// `upload` without blocking the caller
+    pub fn upload_async(&self, listings: &[Listing], upload_type: UploadType) -> QueryFuture<u32> {
+        let mut query = self.clone();
+        let listings = listings.to_vec();
+        self.client.spawn(move || query.upload(&listings, upload_type))
+    }
+
+    /// `add` without blocking the caller
+    pub fn add_async(&self, listings: &[Listing]) -> QueryFuture<u32> {
+        let mut query = self.clone();
+        let listings = listings.to_vec();
+        self.client.spawn(move || query.add(&listings))
+    }
+
+    /// `change` without blocking the caller
+    pub fn change_async(&self, listings: &[Listing]) -> QueryFuture<u32> {
+        let mut query = self.clone();
+        let listings = listings.to_vec();
+        self.client.spawn(move || query.change(&listings))
+    }
+
+    /// `delete` without blocking the caller
+    pub fn delete_async(&self, listings: &[Listing]) -> QueryFuture<u32> {
+        let mut query = self.clone();
+        let listings = listings.to_vec();
+        self.client.spawn(move || query.delete(&listings))
+    }
+
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<InventoryUploads> {
+        let mut query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+}
+
+impl QueryBuilder for InventoryUploadsQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        match self.upload_type {
+            None => format!("{}{}?page={}&per_page={}", self.client.get_api_endpoint(), UPLOAD_ENDPOINT, self.page, self.per_page),
+            Some(ref upload_type) => format!("{}{}/{}", self.client.get_api_endpoint(), UPLOAD_ENDPOINT, upload_type.to_string())
+        }
+    }
+}
+
+
+#[derive(Clone)]
+pub struct InventoryUploadQueryBuilder {
+    //upload id
+    id: u32,
+
+    // The client making the query, its token is required for every upload request
+    client: Discogs,
+}
+
+impl InventoryUploadQueryBuilder {
+    /// Creates a new instance of `InventoryUploadQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::InventoryUploadQueryBuilder;
+    ///
+    /// let iuqb = InventoryUploadQueryBuilder::new(119615,
+    ///                                             &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(id: u32,
+               client: &Discogs) -> InventoryUploadQueryBuilder {
+        InventoryUploadQueryBuilder {
+            id: id,
+            client: client.clone()
+        }
+    }
+
+    /// Perform request for the status and results of the upload
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let upload = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .inventory_upload(119615)
+    ///                       .get();
+    /// ```
+    pub fn get(&self) -> Result<InventoryUpload, QueryError> {
+        let result: Result<String, QueryError> = self.perform_request();
+
+        if let Err(error) = result {
+            return Err(error);
+        } else {
+            let result_string = result.ok().unwrap();
+            let json = serde_json::from_str(&result_string);
+
+            if let Ok(upload) = json {
+                return Ok(upload);
+            } else {
+                return Err(QueryError::JsonDecodeError {
+                    serde_err: json.err()
+                });
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl InventoryUploadQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<InventoryUpload> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+}
+
+impl QueryBuilder for InventoryUploadQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        format!("{}{}/{}", self.client.get_api_endpoint(), UPLOAD_ENDPOINT, self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use discogs::*;
+    use data_structures::*;
+    use query::QueryBuilder;
+    use mockito::mock;
+    use serde_json;
+    use serde_json::to_string;
+
+    fn listings() -> Vec<Listing> {
+        let csv = "listing_id,release_id,status,price,media_condition\n\
+                   41578241,1,For Sale,42.00,Mint (M)\n";
+
+        listings_from_csv(csv, Currency::USD, API_URL).ok().unwrap()
+    }
+
+    #[test]
+    fn test_upload_query_urls() {
+        let mut qb = Discogs::new("USER_AGENT").inventory_uploads();
+        assert_eq!(qb.get_query_url(), format!("{}/inventory/upload?page=1&per_page=50", API_URL));
+
+        qb.upload_type = Some(UploadType::Change);
+        assert_eq!(qb.get_query_url(), format!("{}/inventory/upload/change", API_URL));
+    }
+
+    #[test]
+    fn test_perform_upload_request() {
+        mock("POST", "/inventory/upload/delete")
+            .with_status(200)
+            .with_header("location", "https://api.discogs.com/inventory/upload/119615")
+            .create_for(|| {
+                let id = Discogs::new("USER_AGENT")
+                    .token("USER_TOKEN")
+                    .inventory_uploads()
+                    .delete(&listings())
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(id, 119615);
+            });
+    }
+
+    #[test]
+    fn test_failed_upload_resets_the_type() {
+        mock("POST", "/inventory/upload/add")
+            .with_status(500)
+            .create_for(|| {
+                let mut qb = Discogs::new("USER_AGENT").token("USER_TOKEN").inventory_uploads();
+
+                assert!(qb.add(&listings()).is_err());
+                assert_eq!(qb.upload_type, None);
+                assert_eq!(qb.get_query_url(), format!("{}/inventory/upload?page=1&per_page=50", API_URL));
+            });
+    }
+
+    #[test]
+    fn test_perform_upload_status_request() {
+        mock("GET", "/inventory/upload/119615")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "id": 119615,
+                "status": "success",
+                "type": "change",
+                "results": "CSV file contains 1 records.<p>Processed 1 records.",
+                "filename": "inventory.csv",
+                "created_ts": "2017-09-27T12:50:39",
+                "finished_ts": "2017-09-27T12:50:40"
+            })).unwrap().as_str())
+            .create_for(|| {
+                let upload = Discogs::new("USER_AGENT")
+                    .token("USER_TOKEN")
+                    .inventory_upload(119615)
+                    .get()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(upload.id, 119615);
+                assert_eq!(upload.upload_type, Some(UploadType::Change));
+                assert!(upload.is_finished());
+            });
+    }
+}
@@ -13,6 +13,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use discogs::Discogs;
 use data_structures::*;
 use query::*;
 use serde_json;
@@ -25,11 +26,14 @@ pub struct Label {
     pub id: u32,
     pub resource_url: String,
     pub name: String,
+    // Only present on the labels of a release
+    pub catno: Option<String>,
     pub profile: Option<String>,
     pub releases_url: Option<String>,
     pub contact_info: Option<String>,
     pub uri: Option<String>,
     pub urls: Option<Vec<String>>,
+    pub images: Option<Vec<Image>>,
     pub data_quality: Option<DataQuality>,
     pub sublabels: Option<Vec<Label>>,
 }
@@ -51,16 +55,27 @@ impl Label {
             id: id,
             name: name,
             resource_url: resource_url,
+            catno: None,
             profile: None,
             releases_url: None,
             contact_info: None,
             uri: None,
             urls: None,
+            images: None,
             data_quality: None,
             sublabels: None,
         }
     }
 
+    /// The image that best fits `width` x `height`, see `Image::best`
+    pub fn best_image(&self, width: u32, height: u32) -> Option<&Image> {
+        self.images.as_ref().and_then(|images| Image::best(images, width, height))
+    }
+
+    /// The uri of the best image for `width` x `height`
+    pub fn best_image_uri(&self, width: u32, height: u32) -> Option<&str> {
+        self.best_image(width, height).map(|image| image.uri_for(width, height))
+    }
 }
 
 // TODO: make a more comprehensive test
@@ -70,19 +85,17 @@ impl PartialEq for Label {
     }
 }
 
+#[derive(Clone)]
 pub struct LabelQueryBuilder {
     //artist id
     id: u32,
 
-    api_endpoint: String,
-    user_agent: String,
+    // The client making the query, sharing its credentials and state
+    client: Discogs,
 
-    // Optional key and secret if necessary
-    key: Option<String>,
-    secret: Option<String>,
     page: i16,
     per_page: i16,
-    releases : bool
+    releases : bool,
 }
 
 impl LabelQueryBuilder {
@@ -91,28 +104,20 @@ impl LabelQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::LabelQueryBuilder;
     ///
     /// let lqb = LabelQueryBuilder::new(4567,
-    ///                                   discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    ///                                  &Discogs::new("USER_AGENT"));
     /// ```
     pub fn new(id: u32,
-               api_endpoint: String,
-               user_agent: String,
-               key: Option<String>,
-               secret: Option<String>) -> LabelQueryBuilder {
+               client: &Discogs) -> LabelQueryBuilder {
         LabelQueryBuilder {
             id: id,
-            api_endpoint: api_endpoint,
-            user_agent: user_agent,
-            key: key,
-            secret:secret,
+            client: client.clone(),
             page  : 1,
             per_page : 50,
-            releases : false
+            releases : false,
         }
     }
 
@@ -185,24 +190,35 @@ impl LabelQueryBuilder {
     }
 }
 
-impl QueryBuilder for LabelQueryBuilder {
-    fn get_key(&self) -> Option<String> {
-        self.key.clone()
+#[cfg(feature = "async")]
+impl LabelQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<Label> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
     }
 
-    fn get_secret(&self) -> Option<String> {
-        self.secret.clone()
+    /// `get_releases` without blocking the caller
+    pub fn get_releases_async(&self) -> QueryFuture<ArtistReleases> {
+        let mut query = self.clone();
+        self.client.spawn(move || query.get_releases())
+    }
+}
+
+impl QueryBuilder for LabelQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
     }
 
     fn get_query_url(&self) -> String {
         match self.releases {
-            false => format!("{}{}/{}", self.api_endpoint, LABEL_ENDPOINT, self.id),
-            true => format!("{}{}/{}/releases?page={}&per_page={}", self.api_endpoint, LABEL_ENDPOINT, self.id, self.page, self.per_page)
+            false => format!("{}{}/{}", self.client.get_api_endpoint(), LABEL_ENDPOINT, self.id),
+            true => format!("{}{}/{}/releases?page={}&per_page={}", self.client.get_api_endpoint(), LABEL_ENDPOINT, self.id, self.page, self.per_page)
         }
     }
 
-    fn get_user_agent(&self) -> String {
-        self.user_agent.clone()
+    fn get_cache(&self) -> Option<ResponseCache> {
+        self.client.get_response_cache()
     }
 }
 
@@ -223,10 +239,10 @@ mod tests {
         let qb = lqb(999);
 
         assert_eq!(qb.id, 999);
-        assert_eq!(qb.api_endpoint, API_URL.to_owned());
-        assert_eq!(qb.user_agent, "USER_AGENT".to_string());
-        assert!(qb.key.is_none());
-        assert!(qb.secret.is_none());
+        assert_eq!(qb.client.get_api_endpoint(), API_URL.to_owned());
+        assert_eq!(qb.client.get_user_agent(), "USER_AGENT".to_string());
+        assert!(qb.client.get_key().is_none());
+        assert!(qb.client.get_secret().is_none());
     }
 
     #[test]
@@ -0,0 +1,464 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use data_structures::*;
+use discogs::Discogs;
+use query::*;
+use serde_json;
+
+/// The endpoint for a single list
+const LIST_ENDPOINT: &'static str = "/lists";
+
+/// The endpoint for a user's resources
+const USER_ENDPOINT: &'static str = "/users";
+
+
+/// The list summary returned when enumerating a user's lists
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListSummary {
+    pub id: u32,
+    pub name: String,
+    pub resource_url: String,
+    pub uri: Option<String>,
+    pub public: Option<bool>,
+    pub description: Option<String>,
+    pub date_added: Option<String>,
+    pub date_changed: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UserLists {
+    pub pagination: Pagination,
+    pub lists: Vec<ListSummary>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct List {
+    #[serde(rename = "list_id")]
+    pub id: u32,
+    pub name: String,
+    pub resource_url: String,
+    pub url: Option<String>,
+    pub public: Option<bool>,
+    pub description: Option<String>,
+    pub created_ts: Option<String>,
+    pub modified_ts: Option<String>,
+    pub items: Vec<ListItem>,
+}
+
+// TODO: make a more comprehensive test
+impl PartialEq for List {
+    fn eq(&self, other: &List) -> bool {
+        other.id == self.id
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum ListItemType {
+    #[serde(rename = "release")]
+    Release,
+    #[serde(rename = "master")]
+    Master,
+    #[serde(rename = "artist")]
+    Artist,
+    #[serde(rename = "label")]
+    Label,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListItem {
+    pub id: u32,
+    #[serde(rename = "type")]
+    pub item_type: ListItemType,
+    pub display_title: String,
+    pub resource_url: String,
+    pub comment: Option<String>,
+    pub uri: Option<String>,
+    pub image_url: Option<String>,
+}
+
+/// The full entity a `ListItem` points to
+#[derive(Debug, Clone)]
+pub enum ListEntity {
+    Artist {
+        data: Artist
+    },
+    Master {
+        data: Master
+    },
+    Label {
+        data: Label
+    },
+    Release {
+        data: Release
+    }
+}
+
+impl ListItem {
+    /// Fetches the full entity this item points to, using the
+    /// query builder that matches its type
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let client = Discogs::new("USER_AGENT");
+    /// let list = client.list(123).get().ok().unwrap();
+    ///
+    /// for item in list.items.iter() {
+    ///     let entity = item.resolve(&client);
+    /// }
+    /// ```
+    pub fn resolve(&self, client: &Discogs) -> Result<ListEntity, QueryError> {
+        match self.item_type {
+            ListItemType::Artist => client.artist(self.id).get()
+                .map(|a| ListEntity::Artist { data: a }),
+            ListItemType::Master => client.master(self.id).get()
+                .map(|m| ListEntity::Master { data: m }),
+            ListItemType::Label => client.label(self.id).get()
+                .map(|l| ListEntity::Label { data: l }),
+            ListItemType::Release => client.release(self.id).get()
+                .map(|r| ListEntity::Release { data: r }),
+        }
+    }
+}
+
+
+#[derive(Clone)]
+pub struct ListQueryBuilder {
+    //list id
+    id: u32,
+
+    // The client making the query, sharing its credentials and state
+    client: Discogs,
+}
+
+impl ListQueryBuilder {
+    /// Creates a new instance of `ListQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::ListQueryBuilder;
+    ///
+    /// let lqb = ListQueryBuilder::new(123,
+    ///                                 &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(id: u32,
+               client: &Discogs) -> ListQueryBuilder {
+        ListQueryBuilder {
+            id: id,
+            client: client.clone()
+        }
+    }
+
+    /// Perform request
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let list = Discogs::new("USER_AGENT")
+    ///                       .list(123)
+    ///                       .get();
+    /// ```
+    pub fn get(&self) -> Result<List, QueryError> {
+        let result: Result<String, QueryError> = self.perform_request();
+
+        if let Err(error) = result {
+            return Err(error);
+        } else {
+            let result_string = result.ok().unwrap();
+            let json = serde_json::from_str(&result_string);
+
+            if let Ok(list) = json {
+                return Ok(list);
+            } else {
+                return Err(QueryError::JsonDecodeError {
+                    serde_err: json.err()
+                });
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl ListQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<List> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+}
+
+impl QueryBuilder for ListQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_query_url(&self) -> String {
+        format!("{}{}/{}", self.client.get_api_endpoint(), LIST_ENDPOINT, self.id)
+    }
+
+    fn get_cache(&self) -> Option<ResponseCache> {
+        self.client.get_response_cache()
+    }
+}
+
+
+#[derive(Clone)]
+pub struct UserListsQueryBuilder {
+    username: String,
+
+    // The client making the query, sharing its credentials and state
+    client: Discogs,
+
+    page: i16,
+    per_page: i16,
+}
+
+impl UserListsQueryBuilder {
+    /// Creates a new instance of `UserListsQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::UserListsQueryBuilder;
+    ///
+    /// let ulqb = UserListsQueryBuilder::new("username".to_string(),
+    ///                                       &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(username: String,
+               client: &Discogs) -> UserListsQueryBuilder {
+        UserListsQueryBuilder {
+            username: username,
+            client: client.clone(),
+            page: 1,
+            per_page: 50,
+        }
+    }
+
+    pub fn pagination(&mut self, page: i16, per_page: i16) -> &mut UserListsQueryBuilder {
+        self.page = page;
+        self.per_page = per_page;
+        self
+    }
+
+    /// Perform request
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let lists = Discogs::new("USER_AGENT")
+    ///                       .lists("username")
+    ///                       .get();
+    /// ```
+    pub fn get(&self) -> Result<UserLists, QueryError> {
+        let result: Result<String, QueryError> = self.perform_request();
+
+        if let Err(error) = result {
+            return Err(error);
+        } else {
+            let result_string = result.ok().unwrap();
+            let json = serde_json::from_str(&result_string);
+
+            if let Ok(lists) = json {
+                return Ok(lists);
+            } else {
+                return Err(QueryError::JsonDecodeError {
+                    serde_err: json.err()
+                });
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl UserListsQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<UserLists> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+}
+
+impl QueryBuilder for UserListsQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_query_url(&self) -> String {
+        format!("{}{}/{}/lists?page={}&per_page={}",
+                self.client.get_api_endpoint(), USER_ENDPOINT, self.username, self.page, self.per_page)
+    }
+
+    fn get_cache(&self) -> Option<ResponseCache> {
+        self.client.get_response_cache()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use discogs::*;
+    use data_structures::*;
+    use mockito::mock;
+    use serde_json;
+    use serde_json::to_string;
+
+    fn lqb(id: u32) -> ListQueryBuilder {
+        Discogs::new("USER_AGENT").list(id)
+    }
+
+    #[test]
+    fn test_request_builder() {
+        let qb = lqb(123);
+
+        assert_eq!(qb.id, 123);
+        assert_eq!(qb.client.get_api_endpoint(), API_URL.to_owned());
+        assert_eq!(qb.client.get_user_agent(), "USER_AGENT".to_string());
+        assert!(qb.client.get_key().is_none());
+        assert!(qb.client.get_secret().is_none());
+    }
+
+    #[test]
+    fn test_perform_list_request() {
+        mock("GET", "/lists/123")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "list_id": 123,
+                "name": "Staff Picks",
+                "resource_url": "https://api.discogs.com/lists/123",
+                "public": true,
+                "description": "The releases we love",
+                "items": [{
+                    "comment": "A classic",
+                    "display_title": "Silent Phase - The Rewired Mixes",
+                    "image_url": "",
+                    "resource_url": "https://api.discogs.com/releases/1",
+                    "type": "release",
+                    "id": 1
+                }, {
+                    "comment": "",
+                    "display_title": "Whirlpool Productions",
+                    "resource_url": "https://api.discogs.com/artists/4567",
+                    "type": "artist",
+                    "id": 4567
+                }]
+            })).unwrap().as_str())
+            .create_for(|| {
+                let list = Discogs::new("USER_AGENT")
+                    .list(123)
+                    .get()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(list.id, 123);
+                assert_eq!(list.name, "Staff Picks".to_string());
+                assert_eq!(list.public, Some(true));
+                assert_eq!(list.items.len(), 2);
+
+                assert_eq!(list.items[0].id, 1);
+                assert_eq!(list.items[0].item_type, ListItemType::Release);
+                assert_eq!(list.items[0].comment, Some("A classic".to_string()));
+                assert_eq!(list.items[1].item_type, ListItemType::Artist);
+                assert_eq!(list.items[1].display_title, "Whirlpool Productions".to_string());
+            });
+    }
+
+    #[test]
+    fn test_perform_user_lists_request() {
+        mock("GET", "/users/staff/lists?page=1&per_page=2")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "pagination": {
+                    "per_page": 2,
+                    "items": 3,
+                    "page": 1,
+                    "urls": {
+                        "last": "https://api.discogs.com/users/staff/lists?per_page=2&page=2",
+                        "next": "https://api.discogs.com/users/staff/lists?per_page=2&page=2"
+                    },
+                    "pages": 2
+                },
+                "lists": [{
+                    "id": 123,
+                    "name": "Staff Picks",
+                    "resource_url": "https://api.discogs.com/lists/123",
+                    "public": true,
+                    "date_added": "2017-01-01T00:00:00-08:00",
+                    "date_changed": "2017-01-02T00:00:00-08:00"
+                }, {
+                    "id": 124,
+                    "name": "Staff Picks 2016",
+                    "resource_url": "https://api.discogs.com/lists/124",
+                    "public": true
+                }]
+            })).unwrap().as_str())
+            .create_for(|| {
+                let lists = Discogs::new("USER_AGENT")
+                    .lists("staff")
+                    .pagination(1, 2)
+                    .get()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(lists.pagination.items, 3);
+                assert_eq!(lists.lists.len(), 2);
+                assert_eq!(lists.lists[0].id, 123);
+                assert_eq!(lists.lists[0].name, "Staff Picks".to_string());
+                assert_eq!(lists.lists[1].id, 124);
+            });
+    }
+
+    #[test]
+    fn test_list_item_resolve() {
+        mock("GET", "/labels/1234")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "id": 1234,
+                "resource_url": "https://api.discogs.com/labels/1234",
+                "name": "Skunkworks"
+            })).unwrap().as_str())
+            .create_for(|| {
+                let item = ListItem {
+                    id: 1234,
+                    item_type: ListItemType::Label,
+                    display_title: "Skunkworks".to_string(),
+                    resource_url: "https://api.discogs.com/labels/1234".to_string(),
+                    comment: None,
+                    uri: None,
+                    image_url: None,
+                };
+
+                let entity = item.resolve(&Discogs::new("USER_AGENT"))
+                    .ok()
+                    .unwrap();
+
+                match entity {
+                    ListEntity::Label { data } => assert_eq!(data.name, "Skunkworks".to_string()),
+                    _ => panic!("Expected a label"),
+                }
+            });
+    }
+}
@@ -0,0 +1,576 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use discogs::Discogs;
+use data_structures::*;
+use query::*;
+use serde_json;
+use hyper::method::Method;
+use std::str::FromStr;
+
+/// The marketplace listings endpoint
+const LISTING_ENDPOINT: &'static str = "/marketplace/listings";
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum ListingStatus {
+    #[serde(rename = "For Sale")]
+    ForSale,
+    Draft,
+    Expired,
+    Sold,
+}
+
+impl ListingStatus {
+    pub fn to_string(&self) -> String {
+        match *self {
+            ListingStatus::ForSale => "For Sale".to_string(),
+            ListingStatus::Draft => "Draft".to_string(),
+            ListingStatus::Expired => "Expired".to_string(),
+            ListingStatus::Sold => "Sold".to_string()
+        }
+    }
+}
+
+impl FromStr for ListingStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ListingStatus, String> {
+        match s {
+            "For Sale" => Ok(ListingStatus::ForSale),
+            "Draft" => Ok(ListingStatus::Draft),
+            "Expired" => Ok(ListingStatus::Expired),
+            "Sold" => Ok(ListingStatus::Sold),
+            _ => Err(format!("Unknown listing status {}", s))
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Seller {
+    pub username: String,
+    pub id: Option<u32>,
+    pub resource_url: Option<String>,
+}
+
+/// The release summary embedded in a listing
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListingRelease {
+    pub id: u32,
+    pub resource_url: String,
+    pub description: Option<String>,
+    pub catalog_number: Option<String>,
+    pub year: Option<u32>,
+    pub thumb: Option<String>,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub format: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Listing {
+    pub id: u64,
+    pub resource_url: String,
+    pub status: ListingStatus,
+    pub condition: MediaCondition,
+    pub sleeve_condition: Option<SleeveCondition>,
+    pub price: Price,
+    pub release: ListingRelease,
+    pub uri: Option<String>,
+    pub ships_from: Option<String>,
+    pub allow_offers: Option<bool>,
+    pub comments: Option<String>,
+    pub audio: Option<bool>,
+    pub posted: Option<String>,
+    pub seller: Option<Seller>,
+
+    // Only visible to the owner of the listing
+    pub location: Option<String>,
+    pub external_id: Option<String>,
+    pub weight: Option<f64>,
+    pub format_quantity: Option<u32>,
+}
+
+// TODO: make a more comprehensive test
+impl PartialEq for Listing {
+    fn eq(&self, other: &Listing) -> bool {
+        other.id == self.id
+    }
+}
+
+/// The statuses a listing can be created or edited with
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum NewListingStatus {
+    #[serde(rename = "For Sale")]
+    ForSale,
+    Draft,
+}
+
+/// The body sent when creating or editing a listing
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListingRequest {
+    pub release_id: u32,
+    pub condition: MediaCondition,
+    pub price: f64,
+    pub status: NewListingStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sleeve_condition: Option<SleeveCondition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comments: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_offers: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format_quantity: Option<u32>,
+}
+
+impl ListingRequest {
+    /// Creates a new instance of `ListingRequest`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::data_structures::*;
+    ///
+    /// let request = ListingRequest::new(5610049,
+    ///                                   MediaCondition::NearMint,
+    ///                                   149.0,
+    ///                                   NewListingStatus::ForSale);
+    /// ```
+    pub fn new(release_id: u32,
+               condition: MediaCondition,
+               price: f64,
+               status: NewListingStatus) -> ListingRequest {
+        ListingRequest {
+            release_id: release_id,
+            condition: condition,
+            price: price,
+            status: status,
+            sleeve_condition: None,
+            comments: None,
+            allow_offers: None,
+            external_id: None,
+            location: None,
+            weight: None,
+            format_quantity: None,
+        }
+    }
+
+    /// Checks the request before it is sent, discogs rejects
+    /// listings that are not priced above zero
+    pub fn validate(&self) -> Result<(), QueryError> {
+        if !self.price.is_finite() || self.price <= 0.0 {
+            return Err(QueryError::InvalidRequestError {
+                reason: format!("Listing price must be positive, got {}", self.price),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The response to a listing creation
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewListing {
+    pub listing_id: u64,
+    pub resource_url: String,
+}
+
+#[derive(Clone)]
+pub struct ListingQueryBuilder {
+    //listing id
+    id: u64,
+
+    // The client making the query, its token is required to edit and delete listings
+    client: Discogs,
+}
+
+impl ListingQueryBuilder {
+    /// Creates a new instance of `ListingQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::ListingQueryBuilder;
+    ///
+    /// let lqb = ListingQueryBuilder::new(150899904,
+    ///                                    &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(id: u64,
+               client: &Discogs) -> ListingQueryBuilder {
+        ListingQueryBuilder {
+            id: id,
+            client: client.clone()
+        }
+    }
+
+    /// Perform request
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let listing = Discogs::new("USER_AGENT")
+    ///                       .listing(150899904)
+    ///                       .get();
+    /// ```
+    pub fn get(&self) -> Result<Listing, QueryError> {
+        let result: Result<String, QueryError> = self.perform_request();
+
+        if let Err(error) = result {
+            return Err(error);
+        } else {
+            let result_string = result.ok().unwrap();
+            let json = serde_json::from_str(&result_string);
+
+            if let Ok(listing) = json {
+                return Ok(listing);
+            } else {
+                return Err(QueryError::JsonDecodeError {
+                    serde_err: json.err()
+                });
+            }
+        }
+    }
+
+    /// Replaces the listing with the contents of `request`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::*;
+    ///
+    /// let request = ListingRequest::new(5610049,
+    ///                                   MediaCondition::NearMint,
+    ///                                   120.0,
+    ///                                   NewListingStatus::ForSale);
+    ///
+    /// let result = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .listing(150899904)
+    ///                       .edit(&request);
+    /// ```
+    pub fn edit(&self, request: &ListingRequest) -> Result<(), QueryError> {
+        try!(request.validate());
+
+        let body = try!(serde_json::to_string(request).map_err(|e| {
+            QueryError::JsonDecodeError { serde_err: Some(e) }
+        }));
+
+        self.perform_request_with(Method::Post, Some(body)).map(|_| ())
+    }
+
+    /// Deletes the listing
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let result = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .listing(150899904)
+    ///                       .delete();
+    /// ```
+    pub fn delete(&self) -> Result<(), QueryError> {
+        self.perform_request_with(Method::Delete, None).map(|_| ())
+    }
+}
+
+#[cfg(feature = "async")]
+impl ListingQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<Listing> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+
+    /// `edit` without blocking the caller
+    pub fn edit_async(&self, request: &ListingRequest) -> QueryFuture<()> {
+        let query = self.clone();
+        let request = request.clone();
+        self.client.spawn(move || query.edit(&request))
+    }
+
+    /// `delete` without blocking the caller
+    pub fn delete_async(&self) -> QueryFuture<()> {
+        let query = self.clone();
+        self.client.spawn(move || query.delete())
+    }
+}
+
+impl QueryBuilder for ListingQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        format!("{}{}/{}", self.client.get_api_endpoint(), LISTING_ENDPOINT, self.id)
+    }
+}
+
+#[derive(Clone)]
+pub struct CreateListingQueryBuilder {
+    request: ListingRequest,
+
+    // The client making the query, its token is required to create listings
+    client: Discogs,
+}
+
+impl CreateListingQueryBuilder {
+    /// Creates a new instance of `CreateListingQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::*;
+    ///
+    /// let request = ListingRequest::new(5610049,
+    ///                                   MediaCondition::NearMint,
+    ///                                   149.0,
+    ///                                   NewListingStatus::Draft);
+    ///
+    /// let clqb = CreateListingQueryBuilder::new(request,
+    ///                                           &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(request: ListingRequest,
+               client: &Discogs) -> CreateListingQueryBuilder {
+        CreateListingQueryBuilder {
+            request: request,
+            client: client.clone()
+        }
+    }
+
+    /// Perform request
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::*;
+    ///
+    /// let request = ListingRequest::new(5610049,
+    ///                                   MediaCondition::NearMint,
+    ///                                   149.0,
+    ///                                   NewListingStatus::ForSale);
+    ///
+    /// let listing = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .create_listing(request)
+    ///                       .create();
+    /// ```
+    pub fn create(&self) -> Result<NewListing, QueryError> {
+        try!(self.request.validate());
+
+        let body = try!(serde_json::to_string(&self.request).map_err(|e| {
+            QueryError::JsonDecodeError { serde_err: Some(e) }
+        }));
+
+        let result_string = try!(self.perform_request_with(Method::Post, Some(body)));
+        let json = serde_json::from_str(&result_string);
+
+        if let Ok(listing) = json {
+            return Ok(listing);
+        } else {
+            return Err(QueryError::JsonDecodeError {
+                serde_err: json.err()
+            });
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl CreateListingQueryBuilder {
+    /// `create` without blocking the caller
+    pub fn create_async(&self) -> QueryFuture<NewListing> {
+        let query = self.clone();
+        self.client.spawn(move || query.create())
+    }
+}
+
+impl QueryBuilder for CreateListingQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        format!("{}{}", self.client.get_api_endpoint(), LISTING_ENDPOINT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use discogs::*;
+    use data_structures::*;
+    use query::QueryError;
+    use mockito::mock;
+    use serde_json;
+    use serde_json::to_string;
+
+    fn lqb(id: u64) -> ListingQueryBuilder {
+        Discogs::new("USER_AGENT").listing(id)
+    }
+
+    fn request(price: f64) -> ListingRequest {
+        ListingRequest::new(5610049,
+                            MediaCondition::NearMint,
+                            price,
+                            NewListingStatus::ForSale)
+    }
+
+    #[test]
+    fn test_request_builder() {
+        let qb = lqb(150899904);
+
+        assert_eq!(qb.id, 150899904);
+        assert_eq!(qb.client.get_api_endpoint(), API_URL.to_owned());
+        assert_eq!(qb.client.get_user_agent(), "USER_AGENT".to_string());
+        assert!(qb.client.get_key().is_none());
+        assert!(qb.client.get_secret().is_none());
+        assert!(qb.client.get_token().is_none());
+    }
+
+    #[test]
+    fn test_listing_request_serialization() {
+        let mut req = request(149.0);
+        req.sleeve_condition = Some(SleeveCondition::Generic);
+
+        let json: serde_json::Value = serde_json::from_str(&to_string(&req).unwrap()).unwrap();
+
+        assert_eq!(json, json!({
+            "release_id": 5610049,
+            "condition": "Near Mint (NM or M-)",
+            "sleeve_condition": "Generic",
+            "price": 149.0,
+            "status": "For Sale"
+        }));
+    }
+
+    #[test]
+    fn test_listing_request_validation() {
+        assert!(request(0.01).validate().is_ok());
+
+        for price in vec![0.0, -5.0] {
+            match lqb(150899904).edit(&request(price)) {
+                Err(QueryError::InvalidRequestError { .. }) => (),
+                other => panic!("Expected an InvalidRequestError, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_perform_listing_request() {
+        mock("GET", "/marketplace/listings/150899904")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "id": 150899904,
+                "resource_url": "https://api.discogs.com/marketplace/listings/150899904",
+                "status": "Draft",
+                "condition": "Mint (M)",
+                "sleeve_condition": "No Cover",
+                "price": {
+                    "currency": "EUR",
+                    "value": 20.5
+                },
+                "release": {
+                    "id": 5610049,
+                    "resource_url": "https://api.discogs.com/releases/5610049"
+                }
+            })).unwrap().as_str())
+            .create_for(|| {
+                let listing = lqb(150899904)
+                    .get()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(listing.id, 150899904);
+                assert_eq!(listing.status, ListingStatus::Draft);
+                assert_eq!(listing.condition, MediaCondition::Mint);
+                assert_eq!(listing.sleeve_condition, Some(SleeveCondition::NoCover));
+                assert_eq!(listing.price, Price::new(20.5, Currency::EUR));
+            });
+    }
+
+    #[test]
+    fn test_perform_create_listing_request() {
+        mock("POST", "/marketplace/listings")
+            .with_status(201)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "listing_id": 41578241,
+                "resource_url": "https://api.discogs.com/marketplace/listings/41578241"
+            })).unwrap().as_str())
+            .create_for(|| {
+                let listing = Discogs::new("USER_AGENT")
+                    .token("USER_TOKEN")
+                    .create_listing(request(149.0))
+                    .create()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(listing.listing_id, 41578241);
+            });
+    }
+
+    #[test]
+    fn test_perform_delete_listing_request() {
+        mock("DELETE", "/marketplace/listings/41578241")
+            .with_status(204)
+            .create_for(|| {
+                let result = Discogs::new("USER_AGENT")
+                    .token("USER_TOKEN")
+                    .listing(41578241)
+                    .delete();
+
+                assert!(result.is_ok());
+            });
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_perform_edit_listing_request_async() {
+        use futures::Future;
+
+        mock("POST", "/marketplace/listings/41578242")
+            .with_status(204)
+            .create_for(|| {
+                let mut client = Discogs::new("USER_AGENT");
+                client.token("USER_TOKEN");
+
+                let edited = client.listing(41578242).edit_async(&request(120.0));
+                let deleted = client.listing(41578243).delete_async();
+
+                assert!(edited.wait().is_ok());
+                assert!(deleted.wait().is_err());
+            });
+    }
+}
@@ -13,6 +13,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use discogs::Discogs;
 use data_structures::*;
 use query::*;
 use serde_json;
@@ -84,6 +85,16 @@ impl Master {
             lowest_price: None,
         }
     }
+
+    /// The image that best fits `width` x `height`, see `Image::best`
+    pub fn best_image(&self, width: u32, height: u32) -> Option<&Image> {
+        self.images.as_ref().and_then(|images| Image::best(images, width, height))
+    }
+
+    /// The uri of the best image for `width` x `height`
+    pub fn best_image_uri(&self, width: u32, height: u32) -> Option<&str> {
+        self.best_image(width, height).map(|image| image.uri_for(width, height))
+    }
 }
 
 // TODO: make a more comprehensive test
@@ -94,16 +105,13 @@ impl PartialEq for Master {
 }
 
 
+#[derive(Clone)]
 pub struct MasterQueryBuilder {
     //master id
     id: u32,
 
-    api_endpoint: String,
-    user_agent: String,
-
-    // Optional key and secret if necessary
-    key: Option<String>,
-    secret: Option<String>,
+    // The client making the query, sharing its credentials and state
+    client: Discogs,
 }
 
 impl MasterQueryBuilder {
@@ -112,25 +120,17 @@ impl MasterQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::MasterQueryBuilder;
     ///
     /// let mqb = MasterQueryBuilder::new(7896,
-    ///                                   discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    ///                                   &Discogs::new("USER_AGENT"));
     /// ```
     pub fn new(id: u32,
-               api_endpoint: String,
-               user_agent: String,
-               key: Option<String>,
-               secret: Option<String>) -> MasterQueryBuilder {
+               client: &Discogs) -> MasterQueryBuilder {
         MasterQueryBuilder {
             id: id,
-            api_endpoint: api_endpoint,
-            user_agent: user_agent,
-            key: key,
-            secret:secret
+            client: client.clone()
         }
     }
 
@@ -139,14 +139,12 @@ impl MasterQueryBuilder {
     /// # Examples
     ///
     /// ```rust,no_run
+    /// use discogs::Discogs;
     /// use discogs::data_structures::MasterQueryBuilder;
     ///
     /// let master = MasterQueryBuilder::new(7896,
-    ///                                   discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()))
-    ///                                  .get();
+    ///                                      &Discogs::new("USER_AGENT"))
+    ///                                          .get();
     /// ```
     pub fn get(&self) -> Result<Master, QueryError> {
         let result: Result<String, QueryError> = self.perform_request();
@@ -168,21 +166,26 @@ impl MasterQueryBuilder {
     }
 }
 
-impl QueryBuilder for MasterQueryBuilder {
-    fn get_key(&self) -> Option<String> {
-        self.key.clone()
+#[cfg(feature = "async")]
+impl MasterQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<Master> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
     }
+}
 
-    fn get_secret(&self) -> Option<String> {
-        self.secret.clone()
+impl QueryBuilder for MasterQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
     }
 
     fn get_query_url(&self) -> String {
-        format!("{}{}/{}", self.api_endpoint, MASTER_ENDPOINT, self.id)
+        format!("{}{}/{}", self.client.get_api_endpoint(), MASTER_ENDPOINT, self.id)
     }
 
-    fn get_user_agent(&self) -> String {
-        self.user_agent.clone()
+    fn get_cache(&self) -> Option<ResponseCache> {
+        self.client.get_response_cache()
     }
 }
 
@@ -203,10 +206,10 @@ mod tests {
         let qb = mqb(789);
 
         assert_eq!(qb.id, 789);
-        assert_eq!(qb.api_endpoint, API_URL.to_owned());
-        assert_eq!(qb.user_agent, "USER_AGENT".to_string());
-        assert!(qb.key.is_none());
-        assert!(qb.secret.is_none());
+        assert_eq!(qb.client.get_api_endpoint(), API_URL.to_owned());
+        assert_eq!(qb.client.get_user_agent(), "USER_AGENT".to_string());
+        assert!(qb.client.get_key().is_none());
+        assert!(qb.client.get_secret().is_none());
     }
 
     #[test]
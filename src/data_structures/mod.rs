@@ -24,6 +24,16 @@ mod master;
 mod pagination;
 mod search;
 mod others;
+mod list;
+mod condition;
+mod price;
+mod listing;
+mod inventory;
+mod order;
+mod pricing;
+mod inventory_csv;
+mod inventory_export;
+mod inventory_upload;
 
 
 
@@ -37,4 +47,14 @@ pub use self::master::*;
 pub use self::pagination::*;
 pub use self::search::*;
 pub use self::others::*;
+pub use self::list::*;
+pub use self::condition::*;
+pub use self::price::*;
+pub use self::listing::*;
+pub use self::inventory::*;
+pub use self::order::*;
+pub use self::pricing::*;
+pub use self::inventory_csv::*;
+pub use self::inventory_export::*;
+pub use self::inventory_upload::*;
 
@@ -0,0 +1,865 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use discogs::Discogs;
+use data_structures::*;
+use query::*;
+use serde_json;
+use hyper::Url;
+use hyper::method::Method;
+
+/// The marketplace orders endpoint
+const ORDER_ENDPOINT: &'static str = "/marketplace/orders";
+
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+pub enum OrderStatus {
+    #[serde(rename = "New Order")]
+    NewOrder,
+    #[serde(rename = "Buyer Contacted")]
+    BuyerContacted,
+    #[serde(rename = "Invoice Sent")]
+    InvoiceSent,
+    #[serde(rename = "Payment Pending")]
+    PaymentPending,
+    #[serde(rename = "Payment Received")]
+    PaymentReceived,
+    #[serde(rename = "In Progress")]
+    InProgress,
+    Shipped,
+    #[serde(rename = "Refund Sent")]
+    RefundSent,
+    #[serde(rename = "Cancelled (Non-Paying Buyer)")]
+    CancelledNonPayingBuyer,
+    #[serde(rename = "Cancelled (Item Unavailable)")]
+    CancelledItemUnavailable,
+    #[serde(rename = "Cancelled (Per Buyer's Request)")]
+    CancelledPerBuyersRequest,
+    Merged,
+}
+
+impl OrderStatus {
+    pub fn to_string(&self) -> String {
+        match *self {
+            OrderStatus::NewOrder => "New Order".to_string(),
+            OrderStatus::BuyerContacted => "Buyer Contacted".to_string(),
+            OrderStatus::InvoiceSent => "Invoice Sent".to_string(),
+            OrderStatus::PaymentPending => "Payment Pending".to_string(),
+            OrderStatus::PaymentReceived => "Payment Received".to_string(),
+            OrderStatus::InProgress => "In Progress".to_string(),
+            OrderStatus::Shipped => "Shipped".to_string(),
+            OrderStatus::RefundSent => "Refund Sent".to_string(),
+            OrderStatus::CancelledNonPayingBuyer => "Cancelled (Non-Paying Buyer)".to_string(),
+            OrderStatus::CancelledItemUnavailable => "Cancelled (Item Unavailable)".to_string(),
+            OrderStatus::CancelledPerBuyersRequest => "Cancelled (Per Buyer's Request)".to_string(),
+            OrderStatus::Merged => "Merged".to_string()
+        }
+    }
+
+    /// The statuses an order in this status may be moved to,
+    /// following the order status flow documented by discogs
+    pub fn next_statuses(&self) -> Vec<OrderStatus> {
+        use self::OrderStatus::*;
+
+        match *self {
+            NewOrder | BuyerContacted => vec![BuyerContacted,
+                                             InvoiceSent,
+                                             PaymentPending,
+                                             PaymentReceived,
+                                             InProgress,
+                                             Shipped,
+                                             CancelledItemUnavailable,
+                                             CancelledPerBuyersRequest],
+            InvoiceSent | PaymentPending => vec![BuyerContacted,
+                                                 InvoiceSent,
+                                                 PaymentPending,
+                                                 PaymentReceived,
+                                                 InProgress,
+                                                 Shipped,
+                                                 CancelledNonPayingBuyer,
+                                                 CancelledItemUnavailable,
+                                                 CancelledPerBuyersRequest],
+            PaymentReceived | InProgress => vec![InProgress,
+                                                 Shipped,
+                                                 RefundSent,
+                                                 CancelledItemUnavailable,
+                                                 CancelledPerBuyersRequest],
+            Shipped => vec![RefundSent],
+            RefundSent => vec![CancelledItemUnavailable,
+                               CancelledPerBuyersRequest],
+            CancelledNonPayingBuyer |
+            CancelledItemUnavailable |
+            CancelledPerBuyersRequest |
+            Merged => vec![],
+        }
+    }
+
+    pub fn can_transition_to(&self, next: &OrderStatus) -> bool {
+        self.next_statuses().contains(next)
+    }
+
+    /// Whether the buyer has paid for the order
+    pub fn is_paid(&self) -> bool {
+        match *self {
+            OrderStatus::PaymentReceived |
+            OrderStatus::InProgress |
+            OrderStatus::Shipped => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the order was cancelled, or merged into another order
+    pub fn is_cancelled(&self) -> bool {
+        match *self {
+            OrderStatus::CancelledNonPayingBuyer |
+            OrderStatus::CancelledItemUnavailable |
+            OrderStatus::CancelledPerBuyersRequest |
+            OrderStatus::Merged => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OrderSort {
+    Id,
+    Buyer,
+    Created,
+    Status,
+    LastActivity,
+}
+
+impl OrderSort {
+    pub fn to_string(&self) -> String {
+        match *self {
+            OrderSort::Id => "id".to_string(),
+            OrderSort::Buyer => "buyer".to_string(),
+            OrderSort::Created => "created".to_string(),
+            OrderSort::Status => "status".to_string(),
+            OrderSort::LastActivity => "last_activity".to_string()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderItem {
+    pub id: u64,
+    pub release: ListingRelease,
+    pub price: Price,
+    pub media_condition: Option<MediaCondition>,
+    pub sleeve_condition: Option<SleeveCondition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Order {
+    pub id: String,
+    pub resource_url: String,
+    pub status: OrderStatus,
+    pub items: Vec<OrderItem>,
+    pub total: Price,
+    pub uri: Option<String>,
+    pub messages_url: Option<String>,
+    pub next_status: Option<Vec<OrderStatus>>,
+    pub fee: Option<Price>,
+    pub shipping: Option<Price>,
+    pub created: Option<String>,
+    pub last_activity: Option<String>,
+    pub shipping_address: Option<String>,
+    pub additional_instructions: Option<String>,
+    pub archived: Option<bool>,
+    pub seller: Option<Seller>,
+    pub buyer: Option<Seller>,
+}
+
+// TODO: make a more comprehensive test
+impl PartialEq for Order {
+    fn eq(&self, other: &Order) -> bool {
+        other.id == self.id
+    }
+}
+
+impl Order {
+    /// Checks that this order can be moved to `status`
+    ///
+    /// The `next_status` list sent by discogs is used when present,
+    /// otherwise the documented status flow is followed
+    pub fn can_transition_to(&self, status: &OrderStatus) -> bool {
+        match self.next_status {
+            Some(ref next) => next.contains(status),
+            None => self.status.can_transition_to(status),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Orders {
+    pub pagination: Pagination,
+    pub orders: Vec<Order>,
+}
+
+/// The body sent when changing the status or shipping of an order
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<OrderStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shipping: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderMessage {
+    pub message: String,
+    pub subject: Option<String>,
+    pub timestamp: Option<String>,
+    #[serde(rename = "type")]
+    pub message_type: Option<String>,
+    pub from: Option<Seller>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OrderMessages {
+    pub pagination: Pagination,
+    pub messages: Vec<OrderMessage>,
+}
+
+/// The body sent when posting a message to an order
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewOrderMessage {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<OrderStatus>,
+}
+
+
+#[derive(Clone)]
+pub struct OrdersQueryBuilder {
+    // The client making the query, its token is required to list the orders
+    // of the authenticated seller
+    client: Discogs,
+
+    page: i16,
+    per_page: i16,
+    status: Option<OrderStatus>,
+    sort: Option<OrderSort>,
+    sort_order: Option<SortOrder>,
+}
+
+impl OrdersQueryBuilder {
+    /// Creates a new instance of `OrdersQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::OrdersQueryBuilder;
+    ///
+    /// let oqb = OrdersQueryBuilder::new(&Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(client: &Discogs) -> OrdersQueryBuilder {
+        OrdersQueryBuilder {
+            client: client.clone(),
+            page: 1,
+            per_page: 50,
+            status: None,
+            sort: None,
+            sort_order: None,
+        }
+    }
+
+    pub fn pagination(&mut self, page: i16, per_page: i16) -> &mut OrdersQueryBuilder {
+        self.page = page;
+        self.per_page = per_page;
+        self
+    }
+
+    /// Only return orders with the given status
+    pub fn status(&mut self, status: OrderStatus) -> &mut OrdersQueryBuilder {
+        self.status = Some(status);
+        self
+    }
+
+    /// Sort the orders by the given field
+    pub fn sort(&mut self, sort: OrderSort, sort_order: SortOrder) -> &mut OrdersQueryBuilder {
+        self.sort = Some(sort);
+        self.sort_order = Some(sort_order);
+        self
+    }
+
+    /// Perform request
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::OrderStatus;
+    ///
+    /// let orders = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .orders()
+    ///                       .status(OrderStatus::PaymentReceived)
+    ///                       .get();
+    /// ```
+    pub fn get(&self) -> Result<Orders, QueryError> {
+        let result: Result<String, QueryError> = self.perform_request();
+
+        if let Err(error) = result {
+            return Err(error);
+        } else {
+            let result_string = result.ok().unwrap();
+            let json = serde_json::from_str(&result_string);
+
+            if let Ok(orders) = json {
+                return Ok(orders);
+            } else {
+                return Err(QueryError::JsonDecodeError {
+                    serde_err: json.err()
+                });
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl OrdersQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<Orders> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+}
+
+impl QueryBuilder for OrdersQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        let mut url = Url::parse(format!("{}{}",
+                                         self.client.get_api_endpoint(),
+                                         ORDER_ENDPOINT).as_str()).unwrap();
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("page", self.page.to_string().as_str());
+            query.append_pair("per_page", self.per_page.to_string().as_str());
+
+            if let Some(ref status) = self.status {
+                query.append_pair("status", status.to_string().as_str());
+            }
+            if let Some(ref sort) = self.sort {
+                query.append_pair("sort", sort.to_string().as_str());
+            }
+            if let Some(ref sort_order) = self.sort_order {
+                query.append_pair("sort_order", sort_order.to_string().as_str());
+            }
+        }
+
+        url.into_string()
+    }
+}
+
+
+#[derive(Clone)]
+pub struct OrderQueryBuilder {
+    //order id
+    id: String,
+
+    // The client making the query, its token is required for every order request
+    client: Discogs,
+
+    page: i16,
+    per_page: i16,
+    // The part of the order the request is about, reset to the order after each one
+    resource: OrderResource,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OrderResource {
+    Order,
+    Messages,
+    // Messages are posted without pagination
+    NewMessage,
+}
+
+impl OrderQueryBuilder {
+    /// Creates a new instance of `OrderQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::OrderQueryBuilder;
+    ///
+    /// let oqb = OrderQueryBuilder::new("1-1".to_string(),
+    ///                                  &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(id: String,
+               client: &Discogs) -> OrderQueryBuilder {
+        OrderQueryBuilder {
+            id: id,
+            client: client.clone(),
+            page: 1,
+            per_page: 50,
+            resource: OrderResource::Order,
+        }
+    }
+
+    pub fn pagination(&mut self, page: i16, per_page: i16) -> &mut OrderQueryBuilder {
+        self.page = page;
+        self.per_page = per_page;
+        self
+    }
+
+    /// Perform request
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let order = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .order("1-1")
+    ///                       .get();
+    /// ```
+    pub fn get(&mut self) -> Result<Order, QueryError> {
+        let result: Result<String, QueryError> = self.perform_request();
+
+        if let Err(error) = result {
+            return Err(error);
+        } else {
+            let result_string = result.ok().unwrap();
+            let json = serde_json::from_str(&result_string);
+
+            if let Ok(order) = json {
+                return Ok(order);
+            } else {
+                return Err(QueryError::JsonDecodeError {
+                    serde_err: json.err()
+                });
+            }
+        }
+    }
+
+    /// Changes the status and/or shipping of the order
+    ///
+    /// `current` must be this order. A status change is checked against
+    /// the status flow of `current` before it is sent, an invalid change
+    /// is refused with a `QueryError::InvalidRequestError`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::*;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.token("USER_TOKEN");
+    ///
+    /// let order = client.order("1-1").get().ok().unwrap();
+    /// let update = OrderUpdate {
+    ///     status: Some(OrderStatus::Shipped),
+    ///     shipping: None,
+    /// };
+    ///
+    /// let order = client.order("1-1").update(&order, &update);
+    /// ```
+    pub fn update(&mut self, current: &Order, update: &OrderUpdate) -> Result<Order, QueryError> {
+        if current.id != self.id {
+            return Err(QueryError::InvalidRequestError {
+                reason: format!("Order {} can not be updated from order {}", self.id, current.id),
+            });
+        }
+
+        if let Some(ref status) = update.status {
+            if !current.can_transition_to(status) {
+                return Err(QueryError::InvalidRequestError {
+                    reason: format!("Order {} can not move from {} to {}",
+                                    current.id,
+                                    current.status.to_string(),
+                                    status.to_string()),
+                });
+            }
+        }
+
+        if let Some(shipping) = update.shipping {
+            if !shipping.is_finite() || shipping < 0.0 {
+                return Err(QueryError::InvalidRequestError {
+                    reason: format!("Order shipping can not be negative, got {}", shipping),
+                });
+            }
+        }
+
+        let body = try!(serde_json::to_string(update).map_err(|e| {
+            QueryError::JsonDecodeError { serde_err: Some(e) }
+        }));
+
+        let result_string = try!(self.perform_request_with(Method::Post, Some(body)));
+        let json = serde_json::from_str(&result_string);
+
+        if let Ok(order) = json {
+            return Ok(order);
+        } else {
+            return Err(QueryError::JsonDecodeError {
+                serde_err: json.err()
+            });
+        }
+    }
+
+    /// Perform request for the messages of the order
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let messages = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .order("1-1")
+    ///                       .get_messages();
+    /// ```
+    pub fn get_messages(&mut self) -> Result<OrderMessages, QueryError> {
+        self.resource = OrderResource::Messages;
+        let result: Result<String, QueryError> = self.perform_request();
+        self.resource = OrderResource::Order;
+
+        if let Err(error) = result {
+            return Err(error);
+        } else {
+            let result_string = result.ok().unwrap();
+            let json = serde_json::from_str(&result_string);
+
+            if let Ok(messages) = json {
+                return Ok(messages);
+            } else {
+                return Err(QueryError::JsonDecodeError {
+                    serde_err: json.err()
+                });
+            }
+        }
+    }
+
+    /// Posts a message to the order
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::NewOrderMessage;
+    ///
+    /// let message = NewOrderMessage {
+    ///     message: "Thanks for your order!".to_string(),
+    ///     status: None,
+    /// };
+    ///
+    /// let sent = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .order("1-1")
+    ///                       .send_message(&message);
+    /// ```
+    pub fn send_message(&mut self, message: &NewOrderMessage) -> Result<OrderMessage, QueryError> {
+        let body = try!(serde_json::to_string(message).map_err(|e| {
+            QueryError::JsonDecodeError { serde_err: Some(e) }
+        }));
+
+        self.resource = OrderResource::NewMessage;
+        let result = self.perform_request_with(Method::Post, Some(body));
+        self.resource = OrderResource::Order;
+
+        let result_string = try!(result);
+        let json = serde_json::from_str(&result_string);
+
+        if let Ok(message) = json {
+            return Ok(message);
+        } else {
+            return Err(QueryError::JsonDecodeError {
+                serde_err: json.err()
+            });
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl OrderQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<Order> {
+        let mut query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+
+    /// `update` without blocking the caller
+    pub fn update_async(&self, current: &Order, update: &OrderUpdate) -> QueryFuture<Order> {
+        let mut query = self.clone();
+        let current = current.clone();
+        let update = update.clone();
+        self.client.spawn(move || query.update(&current, &update))
+    }
+
+    /// `get_messages` without blocking the caller
+    pub fn get_messages_async(&self) -> QueryFuture<OrderMessages> {
+        let mut query = self.clone();
+        self.client.spawn(move || query.get_messages())
+    }
+
+    /// `send_message` without blocking the caller
+    pub fn send_message_async(&self, message: &NewOrderMessage) -> QueryFuture<OrderMessage> {
+        let mut query = self.clone();
+        let message = message.clone();
+        self.client.spawn(move || query.send_message(&message))
+    }
+}
+
+impl QueryBuilder for OrderQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        let order = format!("{}{}/{}", self.client.get_api_endpoint(), ORDER_ENDPOINT, self.id);
+
+        match self.resource {
+            OrderResource::Order => order,
+            OrderResource::Messages => format!("{}/messages?page={}&per_page={}", order, self.page, self.per_page),
+            OrderResource::NewMessage => format!("{}/messages", order),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use discogs::*;
+    use data_structures::*;
+    use query::{QueryBuilder, QueryError};
+    use mockito::mock;
+    use serde_json;
+    use serde_json::to_string;
+
+    fn order_json(status: &str) -> serde_json::Value {
+        json!({
+            "id": "1-1",
+            "resource_url": "https://api.discogs.com/marketplace/orders/1-1",
+            "status": status,
+            "total": {
+                "currency": "USD",
+                "value": 44.5
+            },
+            "fee": {
+                "currency": "USD",
+                "value": 2.52
+            },
+            "last_activity": "2011-10-21T09:25:17-07:00",
+            "items": [{
+                "id": 41578242,
+                "release": {
+                    "id": 1,
+                    "resource_url": "https://api.discogs.com/releases/1",
+                    "description": "Persuader, The - Stockholm (2x12\")"
+                },
+                "price": {
+                    "currency": "USD",
+                    "value": 42.0
+                }
+            }]
+        })
+    }
+
+    fn order(status: &str) -> Order {
+        serde_json::from_value(order_json(status)).unwrap()
+    }
+
+    #[test]
+    fn test_request_builder() {
+        let qb = Discogs::new("USER_AGENT").order("1-1");
+
+        assert_eq!(qb.id, "1-1".to_string());
+        assert_eq!(qb.client.get_api_endpoint(), API_URL.to_owned());
+        assert_eq!(qb.client.get_user_agent(), "USER_AGENT".to_string());
+        assert!(qb.client.get_token().is_none());
+    }
+
+    #[test]
+    fn test_order_status_flow() {
+        assert!(OrderStatus::NewOrder.can_transition_to(&OrderStatus::InvoiceSent));
+        assert!(OrderStatus::PaymentReceived.can_transition_to(&OrderStatus::Shipped));
+        assert!(OrderStatus::InvoiceSent.can_transition_to(&OrderStatus::CancelledNonPayingBuyer));
+        assert!(!OrderStatus::NewOrder.can_transition_to(&OrderStatus::RefundSent));
+        assert!(!OrderStatus::Shipped.can_transition_to(&OrderStatus::NewOrder));
+        assert!(OrderStatus::Merged.next_statuses().is_empty());
+    }
+
+    #[test]
+    fn test_order_next_status_takes_precedence() {
+        let mut o = order("New Order");
+        o.next_status = Some(vec![OrderStatus::Shipped]);
+
+        assert!(o.can_transition_to(&OrderStatus::Shipped));
+        assert!(!o.can_transition_to(&OrderStatus::InvoiceSent));
+    }
+
+    #[test]
+    fn test_invalid_status_update_is_not_sent() {
+        let update = OrderUpdate {
+            status: Some(OrderStatus::NewOrder),
+            shipping: None,
+        };
+
+        match Discogs::new("USER_AGENT").order("1-1").update(&order("Shipped"), &update) {
+            Err(QueryError::InvalidRequestError { .. }) => (),
+            other => panic!("Expected an InvalidRequestError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_update_of_another_order_is_not_sent() {
+        let update = OrderUpdate {
+            status: Some(OrderStatus::Shipped),
+            shipping: None,
+        };
+
+        match Discogs::new("USER_AGENT").order("1-2").update(&order("Payment Received"), &update) {
+            Err(QueryError::InvalidRequestError { .. }) => (),
+            other => panic!("Expected an InvalidRequestError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_perform_send_message_request() {
+        mock("POST", "/marketplace/orders/1-1/messages")
+            .with_status(201)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "message": "Shipped today",
+                "timestamp": "2011-11-18T15:32:42-07:00",
+                "type": "message"
+            })).unwrap().as_str())
+            .create_for(|| {
+                let mut client = Discogs::new("USER_AGENT");
+                client.token("USER_TOKEN");
+                let mut query = client.order("1-1");
+
+                let message = NewOrderMessage {
+                    message: "Shipped today".to_string(),
+                    status: None,
+                };
+                let sent = query.send_message(&message).ok().unwrap();
+
+                assert_eq!(sent.message, "Shipped today".to_string());
+                // The builder queries the order again afterwards
+                assert_eq!(query.get_query_url(), format!("{}/marketplace/orders/1-1", API_URL));
+            });
+    }
+
+    #[test]
+    fn test_perform_orders_request() {
+        mock("GET", "/marketplace/orders?page=1&per_page=50&status=New+Order&sort=last_activity&sort_order=desc")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "pagination": {
+                    "per_page": 50,
+                    "items": 1,
+                    "page": 1,
+                    "urls": {},
+                    "pages": 1
+                },
+                "orders": [order_json("New Order")]
+            })).unwrap().as_str())
+            .create_for(|| {
+                let orders = Discogs::new("USER_AGENT")
+                    .token("USER_TOKEN")
+                    .orders()
+                    .status(OrderStatus::NewOrder)
+                    .sort(OrderSort::LastActivity, SortOrder::Descending)
+                    .get()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(orders.orders.len(), 1);
+                assert_eq!(orders.orders[0].id, "1-1".to_string());
+                assert_eq!(orders.orders[0].status, OrderStatus::NewOrder);
+                assert_eq!(orders.orders[0].total, Price::new(44.5, Currency::USD));
+                assert_eq!(orders.orders[0].items[0].release.id, 1);
+            });
+    }
+
+    #[test]
+    fn test_perform_order_update_request() {
+        mock("POST", "/marketplace/orders/1-1")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&order_json("Shipped")).unwrap().as_str())
+            .create_for(|| {
+                let update = OrderUpdate {
+                    status: Some(OrderStatus::Shipped),
+                    shipping: Some(5.0),
+                };
+
+                let updated = Discogs::new("USER_AGENT")
+                    .token("USER_TOKEN")
+                    .order("1-1")
+                    .update(&order("Payment Received"), &update)
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(updated.status, OrderStatus::Shipped);
+            });
+    }
+
+    #[test]
+    fn test_perform_order_messages_request() {
+        mock("GET", "/marketplace/orders/1-1/messages?page=1&per_page=50")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "pagination": {
+                    "per_page": 50,
+                    "items": 1,
+                    "page": 1,
+                    "urls": {},
+                    "pages": 1
+                },
+                "messages": [{
+                    "subject": "Discogs Order #1-1, Stockholm",
+                    "message": "Hello, when will this ship?",
+                    "timestamp": "2011-11-18T15:32:42-07:00",
+                    "type": "message",
+                    "from": {
+                        "username": "example_buyer",
+                        "resource_url": "https://api.discogs.com/users/example_buyer"
+                    }
+                }]
+            })).unwrap().as_str())
+            .create_for(|| {
+                let messages = Discogs::new("USER_AGENT")
+                    .token("USER_TOKEN")
+                    .order("1-1")
+                    .get_messages()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(messages.messages.len(), 1);
+                assert_eq!(messages.messages[0].message, "Hello, when will this ship?".to_string());
+                assert_eq!(messages.messages[0].from.as_ref().unwrap().username, "example_buyer".to_string());
+            });
+    }
+}
@@ -47,6 +47,7 @@ pub struct Identifier {
     #[serde(rename = "type")]
     pub identifier_type: String,
     pub value: String,
+    pub description: Option<String>,
 }
 
 
@@ -55,8 +56,10 @@ pub struct Track {
     pub duration: String,
     pub position: String,
     pub title: String,
+    // "track", or "index" and "heading" for the rows grouping other tracks
     pub type_: String,
     pub extra_artists: Option<Vec<Artist>>,
+    pub sub_tracks: Option<Vec<Track>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -71,6 +74,9 @@ pub struct Video {
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub enum Status {
     Accepted,
+    Draft,
+    Deleted,
+    Rejected,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
@@ -80,5 +86,24 @@ pub enum DataQuality {
     NeedsVote,
     #[serde(rename="Complete and Correct")]
     CompleteAndCorrect,
+    #[serde(rename="Needs Major Changes")]
+    NeedsMajorChanges,
+    #[serde(rename="Entirely Incorrect")]
+    EntirelyIncorrect,
 }
 
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    pub fn to_string(&self) -> String {
+        match *self {
+            SortOrder::Ascending => "asc".to_string(),
+            SortOrder::Descending => "desc".to_string()
+        }
+    }
+}
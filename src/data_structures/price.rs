@@ -0,0 +1,75 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+/// The currencies accepted by the discogs marketplace
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Currency {
+    USD,
+    GBP,
+    EUR,
+    CAD,
+    AUD,
+    JPY,
+    CHF,
+    MXN,
+    BRL,
+    NZD,
+    SEK,
+    ZAR,
+}
+
+impl Currency {
+    pub fn to_string(&self) -> String {
+        match *self {
+            Currency::USD => "USD".to_string(),
+            Currency::GBP => "GBP".to_string(),
+            Currency::EUR => "EUR".to_string(),
+            Currency::CAD => "CAD".to_string(),
+            Currency::AUD => "AUD".to_string(),
+            Currency::JPY => "JPY".to_string(),
+            Currency::CHF => "CHF".to_string(),
+            Currency::MXN => "MXN".to_string(),
+            Currency::BRL => "BRL".to_string(),
+            Currency::NZD => "NZD".to_string(),
+            Currency::SEK => "SEK".to_string(),
+            Currency::ZAR => "ZAR".to_string()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Price {
+    pub value: f64,
+    pub currency: Currency,
+}
+
+impl Price {
+    /// Creates a new instance of `Price`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::data_structures::*;
+    ///
+    /// let price = Price::new(9.99, Currency::EUR);
+    /// ```
+    pub fn new(value: f64, currency: Currency) -> Price {
+        Price {
+            value: value,
+            currency: currency,
+        }
+    }
+}
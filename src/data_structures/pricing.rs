@@ -0,0 +1,324 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use discogs::Discogs;
+use data_structures::*;
+use query::*;
+use serde_json;
+use std::collections::HashMap;
+
+/// The marketplace fee endpoint
+const FEE_ENDPOINT: &'static str = "/marketplace/fee";
+
+/// The marketplace price suggestions endpoint
+const PRICE_SUGGESTIONS_ENDPOINT: &'static str = "/marketplace/price_suggestions";
+
+
+/// The fee discogs charges for selling an item at a given price
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Fee {
+    pub value: f64,
+    pub currency: Currency,
+}
+
+impl Fee {
+    /// The proceeds left to the seller after paying this fee
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::data_structures::*;
+    ///
+    /// let fee = Fee { value: 0.5, currency: Currency::USD };
+    /// assert_eq!(fee.net(10.0), 9.5);
+    /// ```
+    pub fn net(&self, price: f64) -> f64 {
+        price - self.value
+    }
+}
+
+/// The suggested price for each media condition of a release
+pub type PriceSuggestions = HashMap<MediaCondition, Price>;
+
+
+#[derive(Clone)]
+pub struct FeeQueryBuilder {
+    price: f64,
+    currency: Option<Currency>,
+
+    // The client making the query, its token is required to compute the fee
+    // of the authenticated seller
+    client: Discogs,
+}
+
+impl FeeQueryBuilder {
+    /// Creates a new instance of `FeeQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::FeeQueryBuilder;
+    ///
+    /// let fqb = FeeQueryBuilder::new(10.0,
+    ///                                &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(price: f64,
+               client: &Discogs) -> FeeQueryBuilder {
+        FeeQueryBuilder {
+            price: price,
+            currency: None,
+            client: client.clone()
+        }
+    }
+
+    /// Set the currency of the price, defaults to USD
+    pub fn currency(&mut self, currency: Currency) -> &mut FeeQueryBuilder {
+        self.currency = Some(currency);
+        self
+    }
+
+    /// Perform request
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::Currency;
+    ///
+    /// let fee = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .fee(10.0)
+    ///                       .currency(Currency::EUR)
+    ///                       .get();
+    /// ```
+    pub fn get(&self) -> Result<Fee, QueryError> {
+        if !self.price.is_finite() || self.price <= 0.0 {
+            return Err(QueryError::InvalidRequestError {
+                reason: format!("Fee price must be positive, got {}", self.price),
+            });
+        }
+
+        let result: Result<String, QueryError> = self.perform_request();
+
+        if let Err(error) = result {
+            return Err(error);
+        } else {
+            let result_string = result.ok().unwrap();
+            let json = serde_json::from_str(&result_string);
+
+            if let Ok(fee) = json {
+                return Ok(fee);
+            } else {
+                return Err(QueryError::JsonDecodeError {
+                    serde_err: json.err()
+                });
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl FeeQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<Fee> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+}
+
+impl QueryBuilder for FeeQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        match self.currency {
+            None => format!("{}{}/{}", self.client.get_api_endpoint(), FEE_ENDPOINT, self.price),
+            Some(ref currency) => format!("{}{}/{}/{}", self.client.get_api_endpoint(), FEE_ENDPOINT, self.price, currency.to_string())
+        }
+    }
+}
+
+
+#[derive(Clone)]
+pub struct PriceSuggestionsQueryBuilder {
+    //release id
+    id: u32,
+
+    // The client making the query, its token is required as price suggestions
+    // depend on the seller settings of the authenticated user
+    client: Discogs,
+}
+
+impl PriceSuggestionsQueryBuilder {
+    /// Creates a new instance of `PriceSuggestionsQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::PriceSuggestionsQueryBuilder;
+    ///
+    /// let psqb = PriceSuggestionsQueryBuilder::new(10,
+    ///                                              &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(id: u32,
+               client: &Discogs) -> PriceSuggestionsQueryBuilder {
+        PriceSuggestionsQueryBuilder {
+            id: id,
+            client: client.clone()
+        }
+    }
+
+    /// Perform request
+    ///
+    /// Conditions that discogs does not recognise are skipped
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let suggestions = Discogs::new("USER_AGENT")
+    ///                       .token("USER_TOKEN")
+    ///                       .price_suggestions(10)
+    ///                       .get();
+    /// ```
+    pub fn get(&self) -> Result<PriceSuggestions, QueryError> {
+        let result_string = try!(self.perform_request());
+        let json: Result<HashMap<String, Price>, serde_json::Error> =
+            serde_json::from_str(&result_string);
+
+        match json {
+            Ok(raw) => Ok(raw.into_iter()
+                             .filter_map(|(condition, price)| {
+                                 condition.parse::<MediaCondition>()
+                                          .ok()
+                                          .map(|c| (c, price))
+                             })
+                             .collect()),
+            Err(e) => Err(QueryError::JsonDecodeError {
+                serde_err: Some(e)
+            })
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl PriceSuggestionsQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<PriceSuggestions> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+}
+
+impl QueryBuilder for PriceSuggestionsQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        format!("{}{}/{}", self.client.get_api_endpoint(), PRICE_SUGGESTIONS_ENDPOINT, self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use discogs::*;
+    use data_structures::*;
+    use query::{QueryBuilder, QueryError};
+    use mockito::mock;
+    use serde_json;
+    use serde_json::to_string;
+
+    #[test]
+    fn test_fee_query_url() {
+        let mut qb = Discogs::new("USER_AGENT").fee(10.5);
+        assert_eq!(qb.get_query_url(), format!("{}/marketplace/fee/10.5", API_URL));
+
+        qb.currency(Currency::GBP);
+        assert_eq!(qb.get_query_url(), format!("{}/marketplace/fee/10.5/GBP", API_URL));
+    }
+
+    #[test]
+    fn test_fee_requires_positive_price() {
+        match Discogs::new("USER_AGENT").fee(-1.0).get() {
+            Err(QueryError::InvalidRequestError { .. }) => (),
+            other => panic!("Expected an InvalidRequestError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_perform_fee_request() {
+        mock("GET", "/marketplace/fee/10/EUR")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "value": 0.8,
+                "currency": "EUR"
+            })).unwrap().as_str())
+            .create_for(|| {
+                let fee = Discogs::new("USER_AGENT")
+                    .fee(10.0)
+                    .currency(Currency::EUR)
+                    .get()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(fee, Fee { value: 0.8, currency: Currency::EUR });
+                assert_eq!(fee.net(10.0), 9.2);
+            });
+    }
+
+    #[test]
+    fn test_perform_price_suggestions_request() {
+        mock("GET", "/marketplace/price_suggestions/10")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "Very Good (VG)": {
+                    "currency": "USD",
+                    "value": 6.76
+                },
+                "Mint (M)": {
+                    "currency": "USD",
+                    "value": 13.5
+                }
+            })).unwrap().as_str())
+            .create_for(|| {
+                let suggestions = Discogs::new("USER_AGENT")
+                    .token("USER_TOKEN")
+                    .price_suggestions(10)
+                    .get()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(suggestions.len(), 2);
+                assert_eq!(suggestions[&MediaCondition::Mint], Price::new(13.5, Currency::USD));
+                assert_eq!(suggestions[&MediaCondition::VeryGood], Price::new(6.76, Currency::USD));
+            });
+    }
+}
@@ -13,6 +13,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use discogs::Discogs;
 use data_structures::*;
 use query::*;
 use serde_json;
@@ -136,6 +137,32 @@ impl Release {
         }
     }
 
+    /// The image that best fits `width` x `height`, see `Image::best`
+    pub fn best_image(&self, width: u32, height: u32) -> Option<&Image> {
+        self.images.as_ref().and_then(|images| Image::best(images, width, height))
+    }
+
+    /// The uri of the best image for `width` x `height`
+    ///
+    /// Search results and release summaries come without images,
+    /// their `thumb` is used instead
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let release = Discogs::new("USER_AGENT").release(8481).get().ok().unwrap();
+    /// let cover = release.best_image_uri(300, 300);
+    /// ```
+    pub fn best_image_uri(&self, width: u32, height: u32) -> Option<&str> {
+        match self.best_image(width, height) {
+            Some(image) => Some(image.uri_for(width, height)),
+            None => self.thumb.as_ref().and_then(|thumb| {
+                if thumb.is_empty() { None } else { Some(thumb.as_str()) }
+            }),
+        }
+    }
 }
 
 // TODO: make a more comprehensive test
@@ -145,16 +172,13 @@ impl PartialEq for Release {
     }
 }
 
+#[derive(Clone)]
 pub struct ReleaseQueryBuilder {
     //artist id
     id: u32,
 
-    api_endpoint: String,
-    user_agent: String,
-
-    // Optional key and secret if necessary
-    key: Option<String>,
-    secret: Option<String>,
+    // The client making the query, sharing its credentials and state
+    client: Discogs,
 }
 
 impl ReleaseQueryBuilder {
@@ -163,25 +187,17 @@ impl ReleaseQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::ReleaseQueryBuilder;
     ///
     /// let rqb = ReleaseQueryBuilder::new(128,
-    ///                                   discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    ///                                    &Discogs::new("USER_AGENT"));
     /// ```
     pub fn new(id: u32,
-               api_endpoint: String,
-               user_agent: String,
-               key: Option<String>,
-               secret: Option<String>) -> ReleaseQueryBuilder {
+               client: &Discogs) -> ReleaseQueryBuilder {
         ReleaseQueryBuilder {
             id: id,
-            api_endpoint: api_endpoint,
-            user_agent: user_agent,
-            key: key,
-            secret:secret
+            client: client.clone()
         }
     }
 
@@ -190,14 +206,12 @@ impl ReleaseQueryBuilder {
     /// # Examples
     ///
     /// ```rust,no_run
+    /// use discogs::Discogs;
     /// use discogs::data_structures::ReleaseQueryBuilder;
     ///
     /// let rqb = ReleaseQueryBuilder::new(128,
-    ///                                   discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()))
-    ///                                    .get();
+    ///                                    &Discogs::new("USER_AGENT"))
+    ///                                        .get();
     /// ```
     pub fn get(&self) -> Result<Release, QueryError> {
         let result: Result<String, QueryError> = self.perform_request();
@@ -219,21 +233,26 @@ impl ReleaseQueryBuilder {
     }
 }
 
-impl QueryBuilder for ReleaseQueryBuilder {
-    fn get_key(&self) -> Option<String> {
-        self.key.clone()
+#[cfg(feature = "async")]
+impl ReleaseQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<Release> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
     }
+}
 
-    fn get_secret(&self) -> Option<String> {
-        self.secret.clone()
+impl QueryBuilder for ReleaseQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
     }
 
     fn get_query_url(&self) -> String {
-        format!("{}{}/{}", self.api_endpoint, RELEASE_ENDPOINT, self.id)
+        format!("{}{}/{}", self.client.get_api_endpoint(), RELEASE_ENDPOINT, self.id)
     }
 
-    fn get_user_agent(&self) -> String {
-        self.user_agent.clone()
+    fn get_cache(&self) -> Option<ResponseCache> {
+        self.client.get_response_cache()
     }
 }
 
@@ -242,6 +261,7 @@ mod tests {
     use discogs::*;
     use data_structures::*;
     use mockito::mock;
+    use query::{QueryError, ResponseCache};
     use serde_json;
     use serde_json::to_string;
 
@@ -254,10 +274,10 @@ mod tests {
         let qb = rqb(999);
 
         assert_eq!(qb.id, 999);
-        assert_eq!(qb.api_endpoint, API_URL.to_owned());
-        assert_eq!(qb.user_agent, "USER_AGENT".to_string());
-        assert!(qb.key.is_none());
-        assert!(qb.secret.is_none());
+        assert_eq!(qb.client.get_api_endpoint(), API_URL.to_owned());
+        assert_eq!(qb.client.get_user_agent(), "USER_AGENT".to_string());
+        assert!(qb.client.get_key().is_none());
+        assert!(qb.client.get_secret().is_none());
     }
 
     #[test]
@@ -314,6 +334,36 @@ mod tests {
         assert!(release == release2);
     }
 
+    #[test]
+    fn test_release_best_image_uri() {
+        let mut release = Release::new(128,
+                                       "title".to_string(),
+                                       "released".to_string(),
+                                       "released_formatted".to_string(),
+                                       "resource_url".to_string(),
+                                       "date_added".to_string(),
+                                       "date_changed".to_string(),
+                                       "uri".to_string(),
+                                       2000,
+                                       vec![],
+                                       Status::Accepted);
+        assert_eq!(release.best_image_uri(600, 600), None);
+
+        release.thumb = Some("thumb".to_string());
+        assert_eq!(release.best_image_uri(600, 600), Some("thumb"));
+
+        release.images = Some(vec![serde_json::from_value(json!({
+            "resource_url": "",
+            "type": "primary",
+            "uri": "full",
+            "uri150": "small",
+            "height": 600,
+            "width": 600
+        })).unwrap()]);
+        assert_eq!(release.best_image_uri(600, 600), Some("full"));
+        assert_eq!(release.best_image_uri(100, 100), Some("small"));
+    }
+
     #[test]
     fn test_perform_release_request() {
         mock("GET", "/releases/128")
@@ -360,4 +410,96 @@ mod tests {
                 assert_eq!(release.status, Status::Accepted);
             });
     }
+
+    #[test]
+    fn test_cached_release_request() {
+        let cache = ResponseCache::memory(10);
+        let client = || {
+            let mut client = Discogs::new("USER_AGENT");
+            client.response_cache(cache.clone());
+            client
+        };
+
+        mock("GET", "/releases/129")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_header("etag", "\"129\"")
+            .with_body(to_string(&json!({
+                "id": 129,
+                "title": "title",
+                "resource_url": "resource_url",
+                "year": 2000,
+                "status": "Accepted"
+            })).unwrap().as_str())
+            .create_for(|| {
+                assert_eq!(client().release(129).get().ok().unwrap().id, 129);
+            });
+
+        // The mock is gone, the response comes from the cache
+        assert_eq!(client().release(129).get().ok().unwrap().title, "title".to_string());
+    }
+
+    #[test]
+    fn test_offline_release_request() {
+        let cache = ResponseCache::memory(10);
+        let client = |offline: bool| {
+            let mut client = Discogs::new("USER_AGENT");
+            client.response_cache(cache.clone()).offline(offline);
+            client
+        };
+
+        mock("GET", "/releases/130")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "id": 130,
+                "title": "title",
+                "resource_url": "resource_url",
+                "year": 2000,
+                "status": "Accepted"
+            })).unwrap().as_str())
+            .create_for(|| {
+                assert_eq!(client(false).release(130).get().ok().unwrap().id, 130);
+
+                let offline = client(true);
+                assert_eq!(offline.release(130).get().ok().unwrap().id, 130);
+
+                // Neither cached nor sent
+                match offline.release(131).get() {
+                    Err(QueryError::OfflineMiss { url }) => assert_eq!(url, format!("{}/releases/131", API_URL)),
+                    _ => panic!("Expected an OfflineMiss"),
+                }
+                assert_eq!(offline.miss_report().urls(), vec![format!("{}/releases/131", API_URL)]);
+            });
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_release_request_async() {
+        use futures::Future;
+
+        mock("GET", "/releases/132")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(to_string(&json!({
+                "id": 132,
+                "title": "title",
+                "resource_url": "resource_url",
+                "year": 2000,
+                "status": "Accepted"
+            })).unwrap().as_str())
+            .create_for(|| {
+                let client = Discogs::new("USER_AGENT");
+                let releases = client.release(132).get_async()
+                    .join(client.release(132).get_async())
+                    .wait()
+                    .ok()
+                    .unwrap();
+
+                assert_eq!(releases.0.id, 132);
+                assert_eq!(releases.1.title, "title".to_string());
+
+                assert!(client.release(133).get_async().wait().is_err());
+            });
+    }
 }
@@ -13,6 +13,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use discogs::Discogs;
 use data_structures::*;
 use query::*;
 use serde_json;
@@ -39,6 +40,7 @@ pub enum SearchResult  {
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum SearchType {
     Artist,
     Master,
@@ -64,18 +66,17 @@ impl SearchType {
     }
 }
 
+#[derive(Clone)]
 pub struct SearchQueryBuilder {
-    api_endpoint: String,
-    user_agent: String,
-
-    /// The key and secret are required, however to keep
+    /// The client making the query
+    ///
+    /// Its key and secret are required, however to keep
     /// a consistent styling across multiple QueryBuilders
     /// we wont require them on `new()`
     ///
     /// If none is provided the query will fail with a
     /// `QueryError::AuthenticationMissingError`
-    key: Option<String>,
-    secret: Option<String>,
+    client: Discogs,
 
     parameters: HashMap<String, String>,
 }
@@ -87,22 +88,14 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     /// ```
-    pub fn new(api_endpoint: String,
-               user_agent: String,
-               key: Option<String>,
-               secret: Option<String>) -> SearchQueryBuilder {
+    pub fn new(client: &Discogs) -> SearchQueryBuilder {
         SearchQueryBuilder {
-            api_endpoint: api_endpoint,
-            user_agent: user_agent,
-            key: key,
-            secret: secret,
+            client: client.clone(),
             parameters: HashMap::new(),
         }
     }
@@ -112,12 +105,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.query("query".to_string());
     /// ```
@@ -131,13 +122,11 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     /// use discogs::data_structures::SearchType;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.search_type(SearchType::Artist);
     /// ```
@@ -152,12 +141,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.year(1980);
     /// ```
@@ -171,12 +158,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.title("title".to_string());
     /// ```
@@ -190,12 +175,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.release_title("release_title".to_string());
     /// ```
@@ -209,12 +192,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.credit("credit".to_string());
     /// ```
@@ -228,12 +209,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.artist("artist".to_string());
     /// ```
@@ -247,12 +226,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.anv("anv".to_string());
     /// ```
@@ -266,12 +243,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.label("label".to_string());
     /// ```
@@ -285,12 +260,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.genre("genre".to_string());
     /// ```
@@ -304,12 +277,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.style("style".to_string());
     /// ```
@@ -323,12 +294,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.country("country".to_string());
     /// ```
@@ -341,12 +310,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.format("format".to_string());
     /// ```
@@ -360,12 +327,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.catno("catno".to_string());
     /// ```
@@ -379,12 +344,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.barcode("barcode".to_string());
     /// ```
@@ -398,12 +361,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.track("track".to_string());
     /// ```
@@ -417,12 +378,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.submitter("submitter".to_string());
     /// ```
@@ -436,12 +395,10 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let mut sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()));
+    /// let mut sqb = SearchQueryBuilder::new(&Discogs::new("USER_AGENT"));
     ///
     /// sqb.contributor("contributor".to_string());
     /// ```
@@ -455,16 +412,16 @@ impl SearchQueryBuilder {
     /// # Examples
     ///
     /// ```rust,no_run
+    /// use discogs::Discogs;
     /// use discogs::data_structures::SearchQueryBuilder;
     ///
-    /// let sqb = SearchQueryBuilder::new(discogs::API_URL.to_string(),
-    ///                                   "USER_AGENT".to_string(),
-    ///                                   Some("CLIENT_KEY".to_string()),
-    ///                                   Some("CLIENT_SECRET".to_string()))
-    ///                                       .get();
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.key("CLIENT_KEY").secret("CLIENT_SECRET");
+    ///
+    /// let sqb = SearchQueryBuilder::new(&client).get();
     /// ```
     pub fn get(&self) -> Result<Vec<SearchResult>, QueryError> {
-        if self.key.is_none() || self.secret.is_none() {
+        if self.client.get_key().is_none() || self.client.get_secret().is_none() {
             return Err(QueryError::AuthenticationMissingError {
                 reason: "Missing either key or secret when perfoming search request".to_string(),
             })
@@ -492,42 +449,50 @@ impl SearchQueryBuilder {
     }
 }
 
-impl QueryBuilder for SearchQueryBuilder {
-    fn get_key(&self) -> Option<String> {
-        self.key.clone()
+#[cfg(feature = "async")]
+impl SearchQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<Vec<SearchResult>> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
     }
+}
 
-    fn get_secret(&self) -> Option<String> {
-        self.secret.clone()
+impl QueryBuilder for SearchQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
     }
 
-    //api.discogs.com/database/search?q= is a valid query, so is
-    //api.discogs.com/database/search?q=&year=1
+    // Every parameter is optional, api.discogs.com/database/search?catno=SK032
+    // is as valid as api.discogs.com/database/search?q=persuader&artist=persuader
     fn get_query_url(&self) -> String {
-        let mut url = format!("{}{}/search?q=", self.api_endpoint, SEARCH_ENDPOINT);
+        let mut url = format!("{}{}/search", self.client.get_api_endpoint(), SEARCH_ENDPOINT);
 
         if self.parameters.is_empty() {
             return url;
         }
 
-        url.push_str(self.parameters["query"].as_str());
-
-        if self.parameters.len() > 1 {
-            url.push_str("&");
-
-            url.push_str(
-                self.parameters.iter()
-                               .filter(|&(p, _)| p != "query")
-                               .map(|(p, v)| format!("{}={},", p, v))
-                               .intersperse(",".to_string())
-                               .collect::<String>()
+        // Sorted so that the same search always has the same url
+        let mut parameters: Vec<(&String, &String)> = self.parameters.iter().collect();
+        parameters.sort();
+
+        url.push('?');
+        url.push_str(parameters.into_iter()
+                               .map(|(p, v)| {
+                                   let name = match p.as_str() {
+                                       "query" => "q",
+                                       "search_type" => "type",
+                                       p => p,
+                                   };
+                                   format!("{}={}", name, encode_component(v))
+                               })
+                               .join("&")
                                .as_str());
-        }
 
         url
     }
 
-    fn get_user_agent(&self) -> String {
-        self.user_agent.clone()
+    fn get_cache(&self) -> Option<ResponseCache> {
+        self.client.get_response_cache()
     }
 }
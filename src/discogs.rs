@@ -13,7 +13,16 @@
 //
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
+use batch::BatchQueryBuilder;
 use data_structures::*;
+use hyper;
+use hyper::client::pool::{Config, Pool};
+use hyper::net::HttpsConnector;
+use hyper_native_tls::NativeTlsClient;
+use query::{Cassette, ImageCache, InFlight, MissReport, RateLimiter, RawQueryBuilder, ResponseCache};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "async")]
+use query::{CpuPool, Executor, QueryError, QueryFuture};
 
 #[cfg(test)]
 use mockito::SERVER_URL;
@@ -30,16 +39,63 @@ pub const API_URL: &'static str = "https://api.discogs.com";
 /// The default rate limit for discogs
 pub const API_RATE_LIMIT: u32 = 240;
 
+/// The default rate limit for image requests, which discogs counts separately
+pub const API_IMAGE_RATE_LIMIT: u32 = 60;
 
+
+/// A client of the API
+///
+/// `Discogs` is a cheap handle, its clones and the query builders made from
+/// it share the same connections, rate limiters, caches and miss report, and
+/// can be sent to other threads. Changing the settings of a clone does not
+/// change the others.
+///
+/// Identical GETs made at the same time, with the same credentials, are
+/// sent once and share the response. This is on by default, `deduplicate`
+/// turns it off.
+#[derive(Clone)]
 pub struct Discogs {
+    state: Arc<ClientState>,
+}
+
+#[derive(Clone)]
+struct ClientState {
     api_endpoint: String,
     user_agent: String,
 
     key: Option<String>,
     secret: Option<String>,
 
-    // Maximum number of API Queries per minute
+    // User token, required by the user and marketplace endpoints
+    token: Option<String>,
+
+    // Sends every request, keeping the connections to the API open
+    http: Arc<hyper::Client>,
+
+    // Maximum number of API Queries per minute, every request waits on the limiter
     rate_limit: u32,
+    limiter: Arc<Mutex<RateLimiter>>,
+
+    // Shared by every image request, images have a stricter limit
+    image_limiter: Arc<Mutex<RateLimiter>>,
+    image_cache: Option<ImageCache>,
+
+    // Shared by the queries of the database and lists
+    response_cache: Option<ResponseCache>,
+
+    // When offline only the cache is used, the misses are reported
+    offline: bool,
+    miss_report: MissReport,
+
+    // Records or replays the exchanges of every query
+    cassette: Option<Cassette>,
+
+    // Shares the identical GETs sent at the same time, by clones too
+    in_flight: Option<InFlight>,
+
+    // Runs the `_async` queries
+    #[cfg(feature = "async")]
+    executor: Executor,
 }
 
 impl Discogs {
@@ -53,15 +109,64 @@ impl Discogs {
     /// let client = Discogs::new("USER_AGENT");
     /// ```
     pub fn new(user_agent: &str) -> Self {
+        //TODO: Fix the unwrap()
+        let ssl = NativeTlsClient::new().unwrap();
+        let connector = Pool::with_connector(Config::default(), HttpsConnector::new(ssl));
+
         Discogs {
-            api_endpoint: API_URL.to_owned(),
-            key: None,
-            secret: None,
-            user_agent: user_agent.to_owned(),
-            rate_limit: API_RATE_LIMIT,
+            state: Arc::new(ClientState {
+                api_endpoint: API_URL.to_owned(),
+                key: None,
+                secret: None,
+                token: None,
+                user_agent: user_agent.to_owned(),
+                http: Arc::new(hyper::Client::with_connector(connector)),
+                rate_limit: API_RATE_LIMIT,
+                limiter: Arc::new(Mutex::new(RateLimiter::new(API_RATE_LIMIT))),
+                image_limiter: Arc::new(Mutex::new(RateLimiter::new(API_IMAGE_RATE_LIMIT))),
+                image_cache: None,
+                response_cache: None,
+                offline: false,
+                miss_report: MissReport::new(),
+                cassette: None,
+                in_flight: Some(InFlight::new()),
+                #[cfg(feature = "async")]
+                executor: Executor::new(),
+            }),
         }
     }
 
+    // Copies the state first when it is shared with other clones
+    fn state_mut(&mut self) -> &mut ClientState {
+        Arc::make_mut(&mut self.state)
+    }
+
+    /// The url the API is reached at
+    pub fn get_api_endpoint(&self) -> String {
+        self.state.api_endpoint.clone()
+    }
+
+    pub fn get_user_agent(&self) -> String {
+        self.state.user_agent.clone()
+    }
+
+    pub fn get_key(&self) -> Option<String> {
+        self.state.key.clone()
+    }
+
+    pub fn get_secret(&self) -> Option<String> {
+        self.state.secret.clone()
+    }
+
+    pub fn get_token(&self) -> Option<String> {
+        self.state.token.clone()
+    }
+
+    /// The HTTP client the requests are sent with
+    pub fn http_client(&self) -> &hyper::Client {
+        &self.state.http
+    }
+
     /// Sets the discogs api client key
     ///
     /// # Examples
@@ -74,7 +179,7 @@ impl Discogs {
     /// ```
     //TODO: Come back and make a better example
     pub fn key(&mut self, key: &str) -> &mut Self {
-        self.key = Some(key.to_owned());
+        self.state_mut().key = Some(key.to_owned());
         self
     }
 
@@ -90,10 +195,229 @@ impl Discogs {
     /// ```
     //TODO: Come back and make a better example
     pub fn secret(&mut self, secret: &str) -> &mut Self {
-        self.secret = Some(secret.to_owned());
+        self.state_mut().secret = Some(secret.to_owned());
+        self
+    }
+
+    /// Sets the discogs user token
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.token("USER_TOKEN");
+    /// ```
+    pub fn token(&mut self, token: &str) -> &mut Self {
+        self.state_mut().token = Some(token.to_owned());
+        self
+    }
+
+    /// Sets the url the API is reached at, such as a local fake server
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.api_endpoint("http://127.0.0.1:8080");
+    /// ```
+    pub fn api_endpoint(&mut self, api_endpoint: &str) -> &mut Self {
+        self.state_mut().api_endpoint = api_endpoint.trim_right_matches('/').to_owned();
+        self
+    }
+
+    /// Sets the maximum number of API queries per minute
+    ///
+    /// The clones made before keep sharing the previous budget.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.rate_limit(60);
+    /// ```
+    pub fn rate_limit(&mut self, rate_limit: u32) -> &mut Self {
+        let state = self.state_mut();
+        state.rate_limit = rate_limit;
+        state.limiter = Arc::new(Mutex::new(RateLimiter::new(rate_limit)));
+        self
+    }
+
+    /// Returns the `RateLimiter` every request of the client and its clones waits on
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let limiter = Discogs::new("USER_AGENT").rate_limiter();
+    /// limiter.lock().unwrap().acquire();
+    /// ```
+    pub fn rate_limiter(&self) -> Arc<Mutex<RateLimiter>> {
+        self.state.limiter.clone()
+    }
+
+    /// Sets the maximum number of image requests per minute
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.image_rate_limit(20);
+    /// ```
+    pub fn image_rate_limit(&mut self, rate_limit: u32) -> &mut Self {
+        self.state_mut().image_limiter = Arc::new(Mutex::new(RateLimiter::new(rate_limit)));
+        self
+    }
+
+    /// The `RateLimiter` image requests wait on instead
+    pub fn image_rate_limiter(&self) -> Arc<Mutex<RateLimiter>> {
+        self.state.image_limiter.clone()
+    }
+
+    /// Sets the cache downloaded images are kept in
+    pub fn image_cache(&mut self, cache: ImageCache) -> &mut Self {
+        self.state_mut().image_cache = Some(cache);
+        self
+    }
+
+    pub fn get_image_cache(&self) -> Option<ImageCache> {
+        self.state.image_cache.clone()
+    }
+
+    /// Caches the responses of the database and list queries,
+    /// see `ResponseCache` for the details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::query::ResponseCache;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.response_cache(ResponseCache::memory(1000));
+    /// ```
+    pub fn response_cache(&mut self, cache: ResponseCache) -> &mut Self {
+        self.state_mut().response_cache = Some(cache);
+        self
+    }
+
+    pub fn get_response_cache(&self) -> Option<ResponseCache> {
+        self.state.response_cache.clone()
+    }
+
+    /// Stops using the network, queries are answered from the response cache
+    /// or fail with `QueryError::OfflineMiss`.
+    ///
+    /// The misses of cached queries are listed in the `miss_report`, the
+    /// marketplace and inventory queries are never cached so they just fail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.offline(true);
+    ///
+    /// assert!(client.is_offline());
+    /// ```
+    pub fn offline(&mut self, offline: bool) -> &mut Self {
+        self.state_mut().offline = offline;
+        self
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.state.offline
+    }
+
+    /// The urls that were missing from the cache while offline
+    pub fn miss_report(&self) -> MissReport {
+        self.state.miss_report.clone()
+    }
+
+    /// Records every exchange with the API to `cassette`, or replays them from it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    /// use discogs::query::Cassette;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.cassette(Cassette::record("tests/cassettes/search.json"));
+    /// ```
+    pub fn cassette(&mut self, cassette: Cassette) -> &mut Self {
+        self.state_mut().cassette = Some(cassette);
         self
     }
 
+    pub fn get_cassette(&self) -> Option<Cassette> {
+        self.state.cassette.clone()
+    }
+
+    /// Sends identical GETs made at the same time, from any thread, only once.
+    /// Enabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.deduplicate(false);
+    ///
+    /// assert!(client.in_flight().is_none());
+    /// ```
+    pub fn deduplicate(&mut self, deduplicate: bool) -> &mut Self {
+        let in_flight = if deduplicate {
+            Some(self.in_flight().unwrap_or_else(InFlight::new))
+        } else {
+            None
+        };
+        self.state_mut().in_flight = in_flight;
+        self
+    }
+
+    /// The GETs running, shared with the identical ones
+    pub fn in_flight(&self) -> Option<InFlight> {
+        self.state.in_flight.clone()
+    }
+
+    /// Sets the threads the `_async` methods of the queries run on,
+    /// by default there are `query::future::DEFAULT_THREADS`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::query::CpuPool;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.executor(CpuPool::new(8));
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn executor(&mut self, pool: CpuPool) -> &mut Self {
+        self.state_mut().executor = Executor::with_pool(pool);
+        self
+    }
+
+    /// Runs `query` on the threads of the client
+    #[cfg(feature = "async")]
+    pub fn spawn<T, F>(&self, query: F) -> QueryFuture<T>
+        where T: Send + 'static,
+              F: FnOnce() -> Result<T, QueryError> + Send + 'static
+    {
+        self.state.executor.spawn(query)
+    }
+
     /// Returns an instance of the `ArtistQueryBuilder` structure for the specified id
     /// This allows you to pass parameters to build a request.
     ///
@@ -105,12 +429,8 @@ impl Discogs {
     /// let artist = Discogs::new("USER_AGENT")
     ///                       .artist(4567);
     /// ```
-    pub fn artist(&mut self, id: u32) -> ArtistQueryBuilder {
-        ArtistQueryBuilder::new(id,
-                                self.api_endpoint.clone(),
-                                self.user_agent.clone(),
-                                self.key.clone(),
-                                self.secret.clone())
+    pub fn artist(&self, id: u32) -> ArtistQueryBuilder {
+        ArtistQueryBuilder::new(id, self)
     }
 
     /// Returns an instance of the `LabelQueryBuilder` structure for the specified id
@@ -124,12 +444,8 @@ impl Discogs {
     /// let label = Discogs::new("USER_AGENT")
     ///                       .label(1234);
     /// ```
-    pub fn label(&mut self, id: u32) -> LabelQueryBuilder {
-        LabelQueryBuilder::new(id,
-                               self.api_endpoint.clone(),
-                               self.user_agent.clone(),
-                               self.key.clone(),
-                               self.secret.clone())
+    pub fn label(&self, id: u32) -> LabelQueryBuilder {
+        LabelQueryBuilder::new(id, self)
     }
 
     /// Returns an instance of the `ReleaseQueryBuilder` structure for the specified id
@@ -143,12 +459,8 @@ impl Discogs {
     /// let release = Discogs::new("USER_AGENT")
     ///                       .label(1234);
     /// ```
-    pub fn release(&mut self, id: u32) -> ReleaseQueryBuilder {
-        ReleaseQueryBuilder::new(id,
-                                 self.api_endpoint.clone(),
-                                 self.user_agent.clone(),
-                                 self.key.clone(),
-                                 self.secret.clone())
+    pub fn release(&self, id: u32) -> ReleaseQueryBuilder {
+        ReleaseQueryBuilder::new(id, self)
     }
 
     /// Returns an instance of the `MasterQueryBuilder` structure for the specified id
@@ -162,12 +474,222 @@ impl Discogs {
     /// let master = Discogs::new("USER_AGENT")
     ///                       .master(1234);
     /// ```
-    pub fn master(&mut self, id: u32) -> MasterQueryBuilder {
-        MasterQueryBuilder::new(id,
-                                 self.api_endpoint.clone(),
-                                 self.user_agent.clone(),
-                                 self.key.clone(),
-                                 self.secret.clone())
+    pub fn master(&self, id: u32) -> MasterQueryBuilder {
+        MasterQueryBuilder::new(id, self)
+    }
+
+    /// Returns an instance of the `ListQueryBuilder` structure for the specified id
+    /// This allows you to pass parameters to build a request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let list = Discogs::new("USER_AGENT")
+    ///                       .list(123);
+    /// ```
+    pub fn list(&self, id: u32) -> ListQueryBuilder {
+        ListQueryBuilder::new(id, self)
+    }
+
+    /// Returns an instance of the `UserListsQueryBuilder` structure for the specified user
+    /// This allows you to pass parameters to build a request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let lists = Discogs::new("USER_AGENT")
+    ///                       .lists("username");
+    /// ```
+    pub fn lists(&self, username: &str) -> UserListsQueryBuilder {
+        UserListsQueryBuilder::new(username.to_owned(), self)
+    }
+
+    /// Returns an instance of the `InventoryQueryBuilder` structure for the specified user
+    /// This allows you to pass parameters to build a request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let inventory = Discogs::new("USER_AGENT")
+    ///                       .inventory("username");
+    /// ```
+    pub fn inventory(&self, username: &str) -> InventoryQueryBuilder {
+        InventoryQueryBuilder::new(username.to_owned(), self)
+    }
+
+    /// Returns an instance of the `ListingQueryBuilder` structure for the specified id
+    /// This allows you to get, edit or delete a marketplace listing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let listing = Discogs::new("USER_AGENT")
+    ///                       .listing(150899904);
+    /// ```
+    pub fn listing(&self, id: u64) -> ListingQueryBuilder {
+        ListingQueryBuilder::new(id, self)
+    }
+
+    /// Returns an instance of the `CreateListingQueryBuilder` structure for the given request
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::*;
+    ///
+    /// let request = ListingRequest::new(5610049,
+    ///                                   MediaCondition::NearMint,
+    ///                                   149.0,
+    ///                                   NewListingStatus::Draft);
+    ///
+    /// let listing = Discogs::new("USER_AGENT")
+    ///                       .create_listing(request);
+    /// ```
+    pub fn create_listing(&self, request: ListingRequest) -> CreateListingQueryBuilder {
+        CreateListingQueryBuilder::new(request, self)
+    }
+
+    /// Returns an instance of the `OrdersQueryBuilder` structure.
+    /// This allows you to list the orders of the authenticated seller.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let orders = Discogs::new("USER_AGENT")
+    ///                       .orders();
+    /// ```
+    pub fn orders(&self) -> OrdersQueryBuilder {
+        OrdersQueryBuilder::new(self)
+    }
+
+    /// Returns an instance of the `OrderQueryBuilder` structure for the specified id
+    /// This allows you to get and update an order and its messages.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let order = Discogs::new("USER_AGENT")
+    ///                       .order("1-1");
+    /// ```
+    pub fn order(&self, id: &str) -> OrderQueryBuilder {
+        OrderQueryBuilder::new(id.to_owned(), self)
+    }
+
+    /// Returns an instance of the `FeeQueryBuilder` structure for the specified price
+    /// This allows you to pass parameters to build a request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let fee = Discogs::new("USER_AGENT")
+    ///                       .fee(10.0);
+    /// ```
+    pub fn fee(&self, price: f64) -> FeeQueryBuilder {
+        FeeQueryBuilder::new(price, self)
+    }
+
+    /// Returns an instance of the `PriceSuggestionsQueryBuilder` structure for the specified release
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let suggestions = Discogs::new("USER_AGENT")
+    ///                       .price_suggestions(10);
+    /// ```
+    pub fn price_suggestions(&self, release_id: u32) -> PriceSuggestionsQueryBuilder {
+        PriceSuggestionsQueryBuilder::new(release_id, self)
+    }
+
+    /// Returns an instance of the `InventoryExportsQueryBuilder` structure.
+    /// This allows you to request and list exports of your inventory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let exports = Discogs::new("USER_AGENT")
+    ///                       .inventory_exports();
+    /// ```
+    pub fn inventory_exports(&self) -> InventoryExportsQueryBuilder {
+        InventoryExportsQueryBuilder::new(self)
+    }
+
+    /// Returns an instance of the `InventoryExportQueryBuilder` structure for the specified id
+    /// This allows you to poll and download an export of your inventory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let export = Discogs::new("USER_AGENT")
+    ///                       .inventory_export(599632);
+    /// ```
+    pub fn inventory_export(&self, id: u32) -> InventoryExportQueryBuilder {
+        InventoryExportQueryBuilder::new(id, self)
+    }
+
+    /// Returns an instance of the `InventoryUploadsQueryBuilder` structure.
+    /// This allows you to add, change and delete listings in bulk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let uploads = Discogs::new("USER_AGENT")
+    ///                       .inventory_uploads();
+    /// ```
+    pub fn inventory_uploads(&self) -> InventoryUploadsQueryBuilder {
+        InventoryUploadsQueryBuilder::new(self)
+    }
+
+    /// Returns an instance of the `InventoryUploadQueryBuilder` structure for the specified id
+    /// This allows you to check the results of a bulk upload.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let upload = Discogs::new("USER_AGENT")
+    ///                       .inventory_upload(119615);
+    /// ```
+    pub fn inventory_upload(&self, id: u32) -> InventoryUploadQueryBuilder {
+        InventoryUploadQueryBuilder::new(id, self)
+    }
+
+    /// Returns an instance of the `ImageQueryBuilder` structure for the specified uri
+    /// This allows you to download an image, within the image rate limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let image = Discogs::new("USER_AGENT")
+    ///                       .image("https://img.discogs.com/R-8481.jpg");
+    /// ```
+    pub fn image(&self, uri: &str) -> ImageQueryBuilder {
+        ImageQueryBuilder::new(uri.to_owned(), self)
     }
 
     /// Returns an instance of the `SearchQueryBuilder` structure.
@@ -180,23 +702,97 @@ impl Discogs {
     ///
     /// let search = Discogs::new("USER_AGENT").search();
     /// ```
-    pub fn search(&mut self) -> SearchQueryBuilder {
-        SearchQueryBuilder::new(self.api_endpoint.clone(),
-                                 self.user_agent.clone(),
-                                 self.key.clone(),
-                                 self.secret.clone())
+    pub fn search(&self) -> SearchQueryBuilder {
+        SearchQueryBuilder::new(self)
+    }
+
+    /// Returns an instance of the `RawQueryBuilder` structure for the specified path,
+    /// along with its query. The response is returned untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let raw = Discogs::new("USER_AGENT")
+    ///                   .raw("/releases/1?curr_abbr=EUR");
+    /// ```
+    pub fn raw(&self, path: &str) -> RawQueryBuilder {
+        RawQueryBuilder::new(path.to_owned(), self)
+    }
+
+    /// Returns an instance of the `BatchQueryBuilder` structure fetching the releases
+    /// of `ids` concurrently, see `BatchQueryBuilder` for the details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let releases = Discogs::new("USER_AGENT")
+    ///                   .releases(&[1, 2, 3]);
+    /// ```
+    pub fn releases(&self, ids: &[u32]) -> BatchQueryBuilder<Release> {
+        BatchQueryBuilder::new(ids, |client: &Discogs, id| client.release(id).get(), self)
+    }
+
+    /// Returns an instance of the `BatchQueryBuilder` structure fetching the artists
+    /// of `ids` concurrently, see `BatchQueryBuilder` for the details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let artists = Discogs::new("USER_AGENT")
+    ///                   .artists(&[1, 2, 3]);
+    /// ```
+    pub fn artists(&self, ids: &[u32]) -> BatchQueryBuilder<Artist> {
+        BatchQueryBuilder::new(ids, |client: &Discogs, id| client.artist(id).get(), self)
+    }
+
+    /// Returns an instance of the `BatchQueryBuilder` structure fetching the masters
+    /// of `ids` concurrently, see `BatchQueryBuilder` for the details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let masters = Discogs::new("USER_AGENT")
+    ///                   .masters(&[1, 2, 3]);
+    /// ```
+    pub fn masters(&self, ids: &[u32]) -> BatchQueryBuilder<Master> {
+        BatchQueryBuilder::new(ids, |client: &Discogs, id| client.master(id).get(), self)
+    }
+
+    /// Returns an instance of the `BatchQueryBuilder` structure fetching the labels
+    /// of `ids` concurrently, see `BatchQueryBuilder` for the details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    ///
+    /// let labels = Discogs::new("USER_AGENT")
+    ///                   .labels(&[1, 2, 3]);
+    /// ```
+    pub fn labels(&self, ids: &[u32]) -> BatchQueryBuilder<Label> {
+        BatchQueryBuilder::new(ids, |client: &Discogs, id| client.label(id).get(), self)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use discogs::Discogs;
+    use discogs::{Discogs, API_RATE_LIMIT};
+    use query::QueryBuilder;
+    use std::thread;
 
     #[test]
     fn user_agent_test() {
         let client = Discogs::new("USER_AGENT");
 
-        assert_eq!(client.user_agent, "USER_AGENT".to_string());
+        assert_eq!(client.get_user_agent(), "USER_AGENT".to_string());
     }
 
     #[test]
@@ -204,8 +800,8 @@ mod tests {
         let mut client = Discogs::new("USER_AGENT");
         client.key("CLIENT_KEY");
 
-        assert_eq!(client.user_agent, "USER_AGENT".to_string());
-        assert_eq!(client.key, Some("CLIENT_KEY".to_string()));
+        assert_eq!(client.get_user_agent(), "USER_AGENT".to_string());
+        assert_eq!(client.get_key(), Some("CLIENT_KEY".to_string()));
     }
 
     #[test]
@@ -213,7 +809,70 @@ mod tests {
         let mut client = Discogs::new("USER_AGENT");
         client.secret("CLIENT_STRING");
 
-        assert_eq!(client.user_agent, "USER_AGENT".to_string());
-        assert_eq!(client.secret, Some("CLIENT_STRING".to_string()));
+        assert_eq!(client.get_user_agent(), "USER_AGENT".to_string());
+        assert_eq!(client.get_secret(), Some("CLIENT_STRING".to_string()));
+    }
+
+    #[test]
+    fn token_test() {
+        let mut client = Discogs::new("USER_AGENT");
+        client.token("USER_TOKEN");
+
+        assert_eq!(client.get_user_agent(), "USER_AGENT".to_string());
+        assert_eq!(client.get_token(), Some("USER_TOKEN".to_string()));
+    }
+
+    #[test]
+    fn rate_limit_test() {
+        let mut client = Discogs::new("USER_AGENT");
+        assert_eq!(client.state.rate_limit, API_RATE_LIMIT);
+
+        client.rate_limit(60);
+        assert_eq!(client.state.rate_limit, 60);
+    }
+
+    #[test]
+    fn shared_rate_limiter_test() {
+        let mut client = Discogs::new("USER_AGENT");
+        let other = client.clone();
+
+        assert!(&*client.rate_limiter() as *const _ == &*other.rate_limiter() as *const _);
+        assert!(&*client.release(1).get_rate_limiter() as *const _ == &*other.rate_limiter() as *const _);
+
+        client.rate_limit(60);
+        assert!(&*client.rate_limiter() as *const _ != &*other.rate_limiter() as *const _);
+    }
+
+    #[test]
+    fn clone_settings_test() {
+        let mut client = Discogs::new("USER_AGENT");
+        client.key("CLIENT_KEY");
+
+        let mut other = client.clone();
+        other.key("OTHER_KEY");
+
+        assert_eq!(client.get_key(), Some("CLIENT_KEY".to_string()));
+        assert_eq!(other.get_key(), Some("OTHER_KEY".to_string()));
+        assert_eq!(other.get_user_agent(), "USER_AGENT".to_string());
+    }
+
+    #[test]
+    fn shared_between_threads_test() {
+        fn assert_shareable<T: Clone + Send + Sync>() {}
+        assert_shareable::<Discogs>();
+
+        let client = Discogs::new("USER_AGENT");
+
+        let handles: Vec<_> = (1..5)
+            .map(|id| {
+                let client = client.clone();
+                thread::spawn(move || client.release(id).get_query_url())
+            })
+            .collect();
+
+        for (id, handle) in (1..5).zip(handles) {
+            assert_eq!(handle.join().unwrap(),
+                       format!("{}/releases/{}", client.get_api_endpoint(), id));
+        }
     }
 }
@@ -0,0 +1,388 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Compares two data dumps of the same kind, like two monthly release dumps
+//!
+//! Dumps are sorted by id, so both are streamed side by side and only
+//! one entity of each is held in memory at a time.
+
+use data_structures::*;
+use dump::{self, DumpEntity, GzDump};
+use query::QueryError;
+use serde::Serialize;
+use serde_json::{self, Value};
+use std::path::Path;
+
+/// A field that differs between the two dumps
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub before: Value,
+    pub after: Value,
+}
+
+/// An entity that was added, removed or changed in the newer dump
+#[derive(Debug, Clone)]
+pub enum Change<T> {
+    Added(T),
+    Removed(T),
+    Changed {
+        id: u32,
+        changes: Vec<FieldChange>,
+    },
+}
+
+/// An entity whose fields can be compared
+pub trait Diffable: DumpEntity {
+    /// The fields that are compared, by name
+    fn fields(&self) -> Vec<(&'static str, Value)>;
+}
+
+fn field<T: Serialize>(name: &'static str, value: &T) -> (&'static str, Value) {
+    (name, serde_json::to_value(value).unwrap_or(Value::Null))
+}
+
+/// The fields that differ between `before` and `after`
+pub fn field_changes<T: Diffable>(before: &T, after: &T) -> Vec<FieldChange> {
+    before.fields()
+          .into_iter()
+          .zip(after.fields().into_iter())
+          .filter(|&((_, ref b), (_, ref a))| b != a)
+          .map(|((name, b), (_, a))| FieldChange {
+              field: name,
+              before: b,
+              after: a,
+          })
+          .collect()
+}
+
+impl Diffable for Artist {
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        vec![field("name", &self.name),
+             field("realname", &self.realname),
+             field("profile", &self.profile),
+             field("namevariations", &self.namevariations),
+             field("aliases", &self.aliases),
+             field("members", &self.members),
+             field("urls", &self.urls),
+             field("images", &self.images),
+             field("data_quality", &self.data_quality)]
+    }
+}
+
+impl Diffable for Label {
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        vec![field("name", &self.name),
+             field("profile", &self.profile),
+             field("contact_info", &self.contact_info),
+             field("urls", &self.urls),
+             field("sublabels", &self.sublabels),
+             field("images", &self.images),
+             field("data_quality", &self.data_quality)]
+    }
+}
+
+impl Diffable for Master {
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        vec![field("title", &self.title),
+             field("main_release", &self.main_release),
+             field("year", &self.year),
+             field("artists", &self.artists),
+             field("genres", &self.genres),
+             field("styles", &self.styles),
+             field("notes", &self.notes),
+             field("videos", &self.videos),
+             field("images", &self.images),
+             field("data_quality", &self.data_quality)]
+    }
+}
+
+impl Diffable for Release {
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        vec![field("title", &self.title),
+             field("status", &self.status),
+             field("artists", &self.artists),
+             field("credits", &self.extra_artists),
+             field("labels", &self.labels),
+             field("formats", &self.formats),
+             field("genres", &self.genres),
+             field("styles", &self.styles),
+             field("country", &self.country),
+             field("released", &self.released),
+             field("notes", &self.notes),
+             field("master_id", &self.master_id),
+             field("tracklist", &self.tracklist),
+             field("identifiers", &self.identifiers),
+             field("companies", &self.companies),
+             field("videos", &self.videos),
+             field("images", &self.images),
+             field("data_quality", &self.data_quality)]
+    }
+}
+
+/// One side of the comparison
+struct Side<I> {
+    entities: I,
+    last_id: Option<u32>,
+    done: bool,
+    // Unsorted or malformed, nothing more can be read
+    failed: bool,
+}
+
+impl<T: DumpEntity, I: Iterator<Item = Result<T, QueryError>>> Side<I> {
+    /// Reads the next entity, checking that the dump is sorted
+    fn read(&mut self) -> Result<Option<T>, QueryError> {
+        match self.entities.next() {
+            Some(Ok(entity)) => {
+                let id = entity.entity_id();
+
+                if self.last_id.map_or(false, |last| id <= last) {
+                    self.failed = true;
+                    return Err(QueryError::DumpDecodeError {
+                        reason: format!("The dump is not sorted by id, {} comes after {:?}", id, self.last_id)
+                    });
+                }
+                self.last_id = Some(id);
+                Ok(Some(entity))
+            },
+            Some(Err(error)) => {
+                // Unlike a malformed entity, malformed xml ends the dump
+                if self.entities.size_hint().1 == Some(0) {
+                    self.failed = true;
+                }
+                Err(error)
+            },
+            None => {
+                self.done = true;
+                Ok(None)
+            },
+        }
+    }
+}
+
+/// Iterates over the changes between two dumps, in id order
+///
+/// Entities that did not change are not returned. Entities that fail
+/// to decode are returned as errors and comparison carries on. A dump
+/// that is not sorted or not well formed ends the comparison after its
+/// error, as the rest of the other dump can not be compared to it.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use discogs::data_structures::Release;
+/// use discogs::dump::diff::{self, Change};
+///
+/// let changes = diff::open::<Release, _>("discogs_20170201_releases.xml.gz",
+///                                        "discogs_20170301_releases.xml.gz").ok().unwrap();
+///
+/// for change in changes {
+///     match change {
+///         Ok(Change::Added(release)) => println!("+ {}", release.id),
+///         Ok(Change::Removed(release)) => println!("- {}", release.id),
+///         Ok(Change::Changed { id, changes }) => {
+///             for change in changes {
+///                 println!("~ {} {}", id, change.field);
+///             }
+///         },
+///         Err(e) => println!("{:?}", e),
+///     }
+/// }
+/// ```
+pub struct DumpDiff<T, A, B> {
+    old: Side<A>,
+    new: Side<B>,
+    old_entity: Option<T>,
+    new_entity: Option<T>,
+}
+
+impl<T, A, B> DumpDiff<T, A, B>
+    where T: Diffable, A: Iterator<Item = Result<T, QueryError>>, B: Iterator<Item = Result<T, QueryError>>
+{
+    pub fn new(old: A, new: B) -> DumpDiff<T, A, B> {
+        DumpDiff {
+            old: Side { entities: old, last_id: None, done: false, failed: false },
+            new: Side { entities: new, last_id: None, done: false, failed: false },
+            old_entity: None,
+            new_entity: None,
+        }
+    }
+}
+
+impl<T, A, B> Iterator for DumpDiff<T, A, B>
+    where T: Diffable, A: Iterator<Item = Result<T, QueryError>>, B: Iterator<Item = Result<T, QueryError>>
+{
+    type Item = Result<Change<T>, QueryError>;
+
+    fn next(&mut self) -> Option<Result<Change<T>, QueryError>> {
+        loop {
+            if self.old.failed || self.new.failed {
+                return None;
+            }
+            if self.old_entity.is_none() && !self.old.done {
+                match self.old.read() {
+                    Ok(entity) => self.old_entity = entity,
+                    Err(error) => return Some(Err(error)),
+                }
+            }
+            if self.new_entity.is_none() && !self.new.done {
+                match self.new.read() {
+                    Ok(entity) => self.new_entity = entity,
+                    Err(error) => return Some(Err(error)),
+                }
+            }
+
+            let change = match (self.old_entity.take(), self.new_entity.take()) {
+                (None, None) => return None,
+                (Some(old), None) => Change::Removed(old),
+                (None, Some(new)) => Change::Added(new),
+                (Some(old), Some(new)) => {
+                    if old.entity_id() < new.entity_id() {
+                        self.new_entity = Some(new);
+                        Change::Removed(old)
+                    } else if old.entity_id() > new.entity_id() {
+                        self.old_entity = Some(old);
+                        Change::Added(new)
+                    } else {
+                        let changes = field_changes(&old, &new);
+
+                        if changes.is_empty() {
+                            continue;
+                        }
+                        Change::Changed {
+                            id: new.entity_id(),
+                            changes: changes,
+                        }
+                    }
+                },
+            };
+
+            return Some(Ok(change));
+        }
+    }
+}
+
+/// Compares two gzip'd dumps of entities of type `T`
+pub fn open<T, P>(old: P, new: P) -> Result<DumpDiff<T, GzDump<T>, GzDump<T>>, QueryError>
+    where T: Diffable, P: AsRef<Path>
+{
+    let old = try!(dump::open(old));
+    let new = try!(dump::open(new));
+
+    Ok(DumpDiff::new(old, new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_structures::*;
+    use dump::DumpReader;
+    use serde_json::Value;
+
+    const OLD: &'static str = r#"<releases>
+<release id="1" status="Accepted"><title>Stockholm</title>
+  <tracklist><track><position>A</position><title>Östermalm</title><duration>4:45</duration></track></tracklist>
+</release>
+<release id="2" status="Accepted"><title>Unchanged</title></release>
+<release id="3" status="Accepted"><title>Removed</title></release>
+</releases>"#;
+
+    const NEW: &'static str = r#"<releases>
+<release id="1" status="Accepted"><title>Stockholm (Remastered)</title>
+  <tracklist><track><position>A</position><title>Östermalm</title><duration>4:46</duration></track></tracklist>
+</release>
+<release id="2" status="Accepted"><title>Unchanged</title></release>
+<release id="4" status="Accepted"><title>Added</title></release>
+</releases>"#;
+
+    fn diff(old: &'static str, new: &'static str) -> Vec<Result<Change<Release>, QueryError>> {
+        DumpDiff::new(DumpReader::new(old.as_bytes()), DumpReader::new(new.as_bytes())).collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        let changes = diff(OLD, NEW);
+        assert_eq!(changes.len(), 3);
+
+        match changes[0] {
+            Ok(Change::Changed { id, ref changes }) => {
+                assert_eq!(id, 1);
+                assert_eq!(changes.iter().map(|c| c.field).collect::<Vec<&str>>(), vec!["title", "tracklist"]);
+                assert_eq!(changes[0].after, Value::String("Stockholm (Remastered)".to_string()));
+            },
+            ref other => panic!("Expected a change, got {:?}", other),
+        }
+        match changes[1] {
+            Ok(Change::Removed(ref release)) => assert_eq!(release.id, 3),
+            ref other => panic!("Expected a removal, got {:?}", other),
+        }
+        match changes[2] {
+            Ok(Change::Added(ref release)) => assert_eq!(release.id, 4),
+            ref other => panic!("Expected an addition, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_identical_dumps() {
+        assert!(diff(OLD, OLD).is_empty());
+    }
+
+    #[test]
+    fn test_unsorted_dump() {
+        let unsorted = r#"<releases>
+<release id="2" status="Accepted"><title>Unchanged</title></release>
+<release id="1" status="Accepted"><title>Stockholm</title></release>
+</releases>"#;
+
+        let changes = diff(OLD, unsorted);
+        assert!(changes.last().unwrap().is_err());
+        // Release 3 is not reported as removed once the new dump is unusable
+        assert!(!changes.iter().any(|c| match *c {
+            Ok(Change::Removed(_)) => true,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_truncated_dump() {
+        let truncated = r#"<releases>
+<release id="1" status="Accepted"><title>Stockholm</title>
+  <tracklist><track><position>A</position><title>Östermalm</title><duration>4:45</duration></track></tracklist>
+</release>
+<release id="2" status="Accepted"><ti"#;
+
+        for changes in vec![diff(OLD, truncated), diff(truncated, OLD)] {
+            assert_eq!(changes.len(), 1);
+            assert!(changes[0].is_err());
+        }
+    }
+
+    #[test]
+    fn test_malformed_entities_are_skipped() {
+        let malformed = r#"<releases>
+<release status="Accepted"><title>Without an id</title></release>
+<release id="1" status="Accepted"><title>Stockholm (Remastered)</title>
+  <tracklist><track><position>A</position><title>Östermalm</title><duration>4:46</duration></track></tracklist>
+</release>
+<release id="2" status="Accepted"><title>Unchanged</title></release>
+<release id="4" status="Accepted"><title>Added</title></release>
+</releases>"#;
+
+        let changes = diff(OLD, malformed);
+        assert!(changes[0].is_err());
+        assert_eq!(changes.len(), 4);
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use query::QueryError;
+use std::io::Read;
+use std::str::FromStr;
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+/// An xml element of a dump, along with everything nested in it
+///
+/// Only a single entity is held in memory at a time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<Element>,
+}
+
+impl Element {
+    /// Reads the element that was just opened, up to its closing tag
+    pub fn read<R: Read>(events: &mut EventReader<R>,
+                         name: String,
+                         attributes: Vec<OwnedAttribute>) -> Result<Element, QueryError> {
+        let mut element = Element {
+            name: name,
+            attributes: attributes.into_iter()
+                                  .map(|a| (a.name.local_name, a.value))
+                                  .collect(),
+            text: String::new(),
+            children: Vec::new(),
+        };
+
+        loop {
+            match try!(events.next().map_err(xml_error)) {
+                XmlEvent::StartElement { name, attributes, .. } => {
+                    element.children.push(try!(Element::read(events, name.local_name, attributes)));
+                },
+                XmlEvent::EndElement { .. } => return Ok(element),
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => element.text.push_str(&text),
+                XmlEvent::EndDocument => return Err(QueryError::DumpDecodeError {
+                    reason: format!("Unexpected end of file in <{}>", element.name)
+                }),
+                _ => (),
+            }
+        }
+    }
+
+    /// The first child called `name`
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// The children called `name`
+    pub fn children_named(&self, name: &str) -> Vec<&Element> {
+        self.children.iter().filter(|c| c.name == name).collect()
+    }
+
+    /// The value of the attribute `name`, if not empty
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter()
+                       .find(|a| a.0 == name)
+                       .map(|a| a.1.trim())
+                       .and_then(non_empty)
+    }
+
+    /// The text of this element, if not empty
+    pub fn text(&self) -> Option<&str> {
+        non_empty(self.text.trim())
+    }
+
+    /// The text of the child `name`, if present and not empty
+    pub fn child_text(&self, name: &str) -> Option<String> {
+        self.child(name).and_then(|c| c.text()).map(|t| t.to_string())
+    }
+
+    /// The texts of the `item` children of the child `list`,
+    /// as in `<genres><genre>Rock</genre></genres>`
+    pub fn child_list(&self, list: &str, item: &str) -> Option<Vec<String>> {
+        self.child(list).map(|l| {
+            l.children_named(item)
+             .into_iter()
+             .filter_map(|i| i.text())
+             .map(|t| t.to_string())
+             .collect()
+        })
+    }
+
+    /// Parses the text of the child `name`
+    pub fn child_parse<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.child(name).and_then(|c| c.text()).and_then(|t| t.parse().ok())
+    }
+
+    /// Parses the attribute `name`
+    pub fn attribute_parse<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.attribute(name).and_then(|a| a.parse().ok())
+    }
+
+    /// The id of the entity, from an `id` attribute or child
+    pub fn id(&self) -> Result<u32, QueryError> {
+        self.attribute_parse("id")
+            .or_else(|| self.child_parse("id"))
+            .ok_or_else(|| self.error("without a valid id"))
+    }
+
+    pub fn error(&self, reason: &str) -> QueryError {
+        QueryError::DumpDecodeError {
+            reason: format!("<{}> {}", self.name, reason)
+        }
+    }
+}
+
+fn non_empty(text: &str) -> Option<&str> {
+    if text.is_empty() { None } else { Some(text) }
+}
+
+pub fn xml_error<E: ::std::fmt::Display>(error: E) -> QueryError {
+    QueryError::DumpDecodeError {
+        reason: error.to_string()
+    }
+}
@@ -0,0 +1,349 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use data_structures::*;
+use dump::DumpEntity;
+use dump::element::Element;
+use query::QueryError;
+use serde::Deserialize;
+use serde_json;
+
+/// Dumps hold no urls, these are the ones the API would return
+const RESOURCE_URL: &'static str = "https://api.discogs.com";
+
+fn resource_url(kind: &str, id: u32) -> String {
+    format!("{}/{}/{}", RESOURCE_URL, kind, id)
+}
+
+/// Parses the enums that are serialized as their text, like `DataQuality`
+fn from_text<T: Deserialize>(text: &str) -> Option<T> {
+    serde_json::from_value(serde_json::Value::String(text.to_string())).ok()
+}
+
+fn data_quality(e: &Element) -> Option<DataQuality> {
+    e.child_text("data_quality").and_then(|q| from_text(&q))
+}
+
+fn images(e: &Element) -> Option<Vec<Image>> {
+    e.child("images").map(|images| {
+        images.children_named("image")
+              .into_iter()
+              .map(|i| {
+                  let uri = i.attribute("uri").unwrap_or("").to_string();
+
+                  Image {
+                      resource_url: uri.clone(),
+                      image_type: i.attribute("type").unwrap_or("").parse().unwrap(),
+                      uri: uri,
+                      uri150: i.attribute("uri150").unwrap_or("").to_string(),
+                      height: i.attribute_parse("height").unwrap_or(0),
+                      width: i.attribute_parse("width").unwrap_or(0),
+                  }
+              })
+              .collect()
+    })
+}
+
+fn videos(e: &Element) -> Option<Vec<Video>> {
+    e.child("videos").map(|videos| {
+        videos.children_named("video")
+              .into_iter()
+              .map(|v| Video {
+                  description: v.child_text("description").unwrap_or_default(),
+                  duration: v.attribute_parse("duration").unwrap_or(0),
+                  embed: v.attribute("embed") == Some("true"),
+                  title: v.child_text("title").unwrap_or_default(),
+                  uri: v.attribute("src").unwrap_or("").to_string(),
+              })
+              .collect()
+    })
+}
+
+/// Entities referenced by id, as in `<aliases><name id="239">Dick Track</name></aliases>`
+fn references(e: &Element, list: &str, item: &str) -> Option<Vec<(u32, String)>> {
+    e.child(list).map(|l| {
+        l.children_named(item)
+         .into_iter()
+         .filter_map(|i| i.attribute_parse("id").map(|id| (id, i.text().unwrap_or("").to_string())))
+         .collect()
+    })
+}
+
+/// A track of a tracklist, along with its sub-tracks
+///
+/// Older dumps have no `type_`, it is then told from the track the way the
+/// API does: an index has sub-tracks and a heading neither position nor duration.
+fn track(t: &Element) -> Track {
+    let sub_tracks = t.child("sub_tracks").map(|s| {
+        s.children_named("track")
+         .into_iter()
+         .map(track)
+         .collect::<Vec<Track>>()
+    });
+    let position = t.child_text("position").unwrap_or_default();
+    let duration = t.child_text("duration").unwrap_or_default();
+
+    let type_ = match t.child_text("type_") {
+        Some(type_) => type_,
+        None if sub_tracks.as_ref().map_or(false, |s| !s.is_empty()) => "index".to_string(),
+        None if position.is_empty() && duration.is_empty() => "heading".to_string(),
+        None => "track".to_string(),
+    };
+
+    Track {
+        duration: duration,
+        position: position,
+        title: t.child_text("title").unwrap_or_default(),
+        type_: type_,
+        extra_artists: credits(t, "extraartists"),
+        sub_tracks: sub_tracks,
+    }
+}
+
+/// The artists credited on a release, master or track
+fn credits(e: &Element, list: &str) -> Option<Vec<Artist>> {
+    e.child(list).map(|l| {
+        l.children_named("artist")
+         .into_iter()
+         .filter_map(|a| {
+             a.child_parse("id").map(|id| {
+                 let mut artist = Artist::new(id,
+                                              a.child_text("name").unwrap_or_default(),
+                                              resource_url("artists", id));
+                 artist.anv = a.child_text("anv");
+                 artist.join = a.child_text("join");
+                 artist.role = a.child_text("role");
+                 artist.tracks = a.child_text("tracks");
+                 artist
+             })
+         })
+         .collect()
+    })
+}
+
+impl DumpEntity for Artist {
+    fn element_name() -> &'static str {
+        "artist"
+    }
+
+    fn entity_id(&self) -> u32 {
+        self.id
+    }
+
+    fn from_element(e: &Element) -> Result<Artist, QueryError> {
+        let id = try!(e.id());
+        let mut artist = Artist::new(id,
+                                     e.child_text("name").unwrap_or_default(),
+                                     resource_url("artists", id));
+
+        artist.realname = e.child_text("realname");
+        artist.profile = e.child_text("profile");
+        artist.data_quality = data_quality(e);
+        artist.urls = e.child_list("urls", "url");
+        artist.namevariations = e.child_list("namevariations", "name");
+        artist.aliases = references(e, "aliases", "name").map(|aliases| {
+            aliases.into_iter()
+                   .map(|(id, name)| Artist::new(id, name, resource_url("artists", id)))
+                   .collect()
+        });
+        artist.members = references(e, "members", "name").map(|members| {
+            members.into_iter()
+                   .map(|(id, name)| Artist::new(id, name, resource_url("artists", id)))
+                   .collect()
+        });
+        artist.images = images(e);
+
+        Ok(artist)
+    }
+}
+
+impl DumpEntity for Label {
+    fn element_name() -> &'static str {
+        "label"
+    }
+
+    fn entity_id(&self) -> u32 {
+        self.id
+    }
+
+    fn from_element(e: &Element) -> Result<Label, QueryError> {
+        let id = try!(e.id());
+        let mut label = Label::new(id,
+                                   e.child_text("name").unwrap_or_default(),
+                                   resource_url("labels", id));
+
+        label.releases_url = Some(format!("{}/releases", label.resource_url));
+        label.contact_info = e.child_text("contactinfo");
+        label.profile = e.child_text("profile");
+        label.data_quality = data_quality(e);
+        label.urls = e.child_list("urls", "url");
+        label.images = images(e);
+        label.sublabels = references(e, "sublabels", "label").map(|sublabels| {
+            sublabels.into_iter()
+                     .map(|(id, name)| Label::new(id, name, resource_url("labels", id)))
+                     .collect()
+        });
+
+        Ok(label)
+    }
+}
+
+impl DumpEntity for Master {
+    fn element_name() -> &'static str {
+        "master"
+    }
+
+    fn entity_id(&self) -> u32 {
+        self.id
+    }
+
+    fn from_element(e: &Element) -> Result<Master, QueryError> {
+        let id = try!(e.id());
+        let main_release = try!(e.child_parse("main_release")
+                                 .ok_or_else(|| e.error("without a main release")));
+        let mut master = Master::new(id,
+                                     resource_url("masters", id),
+                                     main_release,
+                                     resource_url("releases", main_release));
+
+        master.versions_url = Some(format!("{}/versions", master.resource_url));
+        master.title = e.child_text("title");
+        master.year = e.child_parse("year");
+        master.images = images(e);
+        master.artists = credits(e, "artists");
+        master.genres = e.child_list("genres", "genre");
+        master.styles = e.child_list("styles", "style");
+        master.notes = e.child_text("notes");
+        master.data_quality = data_quality(e);
+        master.videos = videos(e);
+
+        Ok(master)
+    }
+}
+
+impl DumpEntity for Release {
+    fn element_name() -> &'static str {
+        "release"
+    }
+
+    fn entity_id(&self) -> u32 {
+        self.id
+    }
+
+    fn from_element(e: &Element) -> Result<Release, QueryError> {
+        let id = try!(e.id());
+        let status = try!(e.attribute("status")
+                           .and_then(from_text)
+                           .ok_or_else(|| e.error("without a valid status")));
+
+        let released = e.child_text("released");
+        let year = released.as_ref()
+                           .and_then(|r| r.chars().take(4).collect::<String>().parse().ok())
+                           .unwrap_or(0);
+        let master_id: Option<u32> = e.child_parse("master_id");
+
+        Ok(Release {
+            title: e.child_text("title").unwrap_or_default(),
+            id: id,
+            status: status,
+            resource_url: resource_url("releases", id),
+            year: year,
+            artists: credits(e, "artists"),
+            artist: None,
+            uri: None,
+            released: released,
+            released_formatted: None,
+            date_added: None,
+            date_changed: None,
+            data_quality: data_quality(e),
+            thumb: None,
+            community: None,
+            companies: e.child("companies").map(|companies| {
+                companies.children_named("company")
+                         .into_iter()
+                         .map(|c| {
+                             let id = c.child_parse("id").unwrap_or(0);
+
+                             Company {
+                                 catno: c.child_text("catno").unwrap_or_default(),
+                                 entity_type: c.child_text("entity_type").unwrap_or_default(),
+                                 entity_type_name: c.child_text("entity_type_name").unwrap_or_default(),
+                                 id: id,
+                                 name: c.child_text("name").unwrap_or_default(),
+                                 resource_url: c.child_text("resource_url")
+                                                .unwrap_or_else(|| resource_url("labels", id)),
+                             }
+                         })
+                         .collect()
+            }),
+            country: e.child_text("country"),
+            estimated_weight: None,
+            extra_artists: credits(e, "extraartists"),
+            format_quantity: None,
+            formats: e.child("formats").map(|formats| {
+                formats.children_named("format")
+                       .into_iter()
+                       .map(|f| ReleaseFormat {
+                           descriptions: f.child_list("descriptions", "description").unwrap_or_default(),
+                           name: f.attribute("name").unwrap_or("").to_string(),
+                           qty: f.attribute("qty").unwrap_or("").to_string(),
+                       })
+                       .collect()
+            }),
+            genres: e.child_list("genres", "genre"),
+            identifiers: e.child("identifiers").map(|identifiers| {
+                identifiers.children_named("identifier")
+                           .into_iter()
+                           .map(|i| Identifier {
+                               identifier_type: i.attribute("type").unwrap_or("").to_string(),
+                               value: i.attribute("value").unwrap_or("").to_string(),
+                               description: i.attribute("description").map(|d| d.to_string()),
+                           })
+                           .collect()
+            }),
+            images: images(e),
+            labels: e.child("labels").map(|labels| {
+                labels.children_named("label")
+                      .into_iter()
+                      .filter_map(|l| {
+                          l.attribute_parse("id").map(|id| {
+                              let mut label = Label::new(id,
+                                                         l.attribute("name").unwrap_or("").to_string(),
+                                                         resource_url("labels", id));
+                              label.catno = l.attribute("catno").map(|c| c.to_string());
+                              label
+                          })
+                      })
+                      .collect()
+            }),
+            label: None,
+            lowest_price: None,
+            master_id: master_id,
+            master_url: master_id.map(|m| resource_url("masters", m)),
+            notes: e.child_text("notes"),
+            num_for_sale: None,
+            series: None,
+            styles: e.child_list("styles", "style"),
+            tracklist: e.child("tracklist").map(|tracklist| {
+                tracklist.children_named("track")
+                         .into_iter()
+                         .map(track)
+                         .collect()
+            }),
+            videos: videos(e),
+        })
+    }
+}
@@ -0,0 +1,297 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Reads the monthly data dumps published at <https://data.discogs.com>
+//!
+//! The dumps are streamed one entity at a time into the same structures
+//! the API returns, so the whole catalog can be processed in bounded
+//! memory. Only the fields present in the dumps are filled in.
+//!
+//! This module requires the `dump` feature, the `sqlite` submodule
+//! requires the `sqlite` feature.
+
+mod element;
+mod entities;
+pub mod diff;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+pub use self::element::Element;
+
+use data_structures::*;
+use flate2::read::GzDecoder;
+use query::QueryError;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::marker::PhantomData;
+use std::path::Path;
+use xml::reader::{EventReader, XmlEvent};
+
+use self::element::xml_error;
+
+/// An entity that can be read from a data dump
+pub trait DumpEntity: Sized {
+    /// The name of the element holding a single entity
+    fn element_name() -> &'static str;
+
+    /// The id the entities of a dump are sorted by
+    fn entity_id(&self) -> u32;
+
+    fn from_element(element: &Element) -> Result<Self, QueryError>;
+}
+
+/// The reader of a gzip'd dump file
+pub type GzDump<T> = DumpReader<BufReader<GzDecoder<BufReader<File>>>, T>;
+
+/// Iterates over the entities of a dump
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use discogs::dump;
+///
+/// for release in dump::releases("discogs_20170301_releases.xml.gz").ok().unwrap() {
+///     match release {
+///         Ok(release) => println!("{} {}", release.id, release.title),
+///         Err(e) => println!("Skipping a release: {:?}", e),
+///     }
+/// }
+/// ```
+pub struct DumpReader<R: Read, T> {
+    events: EventReader<R>,
+    finished: bool,
+    entity: PhantomData<T>,
+}
+
+impl<R: Read, T: DumpEntity> DumpReader<R, T> {
+    /// Reads an uncompressed dump from `reader`
+    pub fn new(reader: R) -> DumpReader<R, T> {
+        DumpReader {
+            events: EventReader::new(reader),
+            finished: false,
+            entity: PhantomData,
+        }
+    }
+}
+
+impl<R: Read, T: DumpEntity> Iterator for DumpReader<R, T> {
+    type Item = Result<T, QueryError>;
+
+    /// A malformed entity is returned as an error and reading carries on,
+    /// malformed xml ends the iteration
+    fn next(&mut self) -> Option<Result<T, QueryError>> {
+        while !self.finished {
+            match self.events.next() {
+                Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+                    // Anything else, like the root element, is stepped into
+                    if name.local_name == T::element_name() {
+                        let element = Element::read(&mut self.events, name.local_name, attributes);
+
+                        if element.is_err() {
+                            self.finished = true;
+                        }
+                        return Some(element.and_then(|e| T::from_element(&e)));
+                    }
+                },
+                Ok(XmlEvent::EndDocument) => self.finished = true,
+                Ok(_) => (),
+                Err(error) => {
+                    self.finished = true;
+                    return Some(Err(xml_error(error)));
+                },
+            }
+        }
+        None
+    }
+
+    // Nothing comes after malformed xml, so that an error can be told fatal
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.finished { (0, Some(0)) } else { (0, None) }
+    }
+}
+
+/// Opens a gzip'd dump of entities of type `T`
+pub fn open<T: DumpEntity, P: AsRef<Path>>(path: P) -> Result<GzDump<T>, QueryError> {
+    let file = try!(File::open(path).map_err(|e| QueryError::TextReadError { error: e }));
+    let gz = try!(GzDecoder::new(BufReader::new(file)).map_err(|e| QueryError::TextReadError { error: e }));
+
+    // The xml parser reads a byte at a time
+    Ok(DumpReader::new(BufReader::new(gz)))
+}
+
+/// Opens an `artists.xml.gz` dump
+pub fn artists<P: AsRef<Path>>(path: P) -> Result<GzDump<Artist>, QueryError> {
+    open(path)
+}
+
+/// Opens a `labels.xml.gz` dump
+pub fn labels<P: AsRef<Path>>(path: P) -> Result<GzDump<Label>, QueryError> {
+    open(path)
+}
+
+/// Opens a `masters.xml.gz` dump
+pub fn masters<P: AsRef<Path>>(path: P) -> Result<GzDump<Master>, QueryError> {
+    open(path)
+}
+
+/// Opens a `releases.xml.gz` dump
+pub fn releases<P: AsRef<Path>>(path: P) -> Result<GzDump<Release>, QueryError> {
+    open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_structures::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::env;
+    use std::fs::File;
+    use std::io::Write;
+
+    const ARTISTS: &'static str = r#"<artists>
+<artist>
+  <images><image height="450" type="primary" uri="" uri150="" width="600"/></images>
+  <id>1</id>
+  <name>The Persuader</name>
+  <realname>Jesper Dahlb&#228;ck</realname>
+  <profile/>
+  <data_quality>Needs Vote</data_quality>
+  <urls><url>https://en.wikipedia.org/wiki/Jesper_Dahlb%C3%A4ck</url></urls>
+  <namevariations><name>Persuader</name><name>The Presuader</name></namevariations>
+  <aliases><name id="239">Jesper Dahlback</name><name id="16055">Groove Machine</name></aliases>
+</artist>
+<artist>
+  <id>2</id>
+  <name>Mr. James Barth &amp; A.D.</name>
+  <members><id>26</id><name id="26">Alexi Delano</name></members>
+</artist>
+</artists>"#;
+
+    const RELEASES: &'static str = r#"<releases>
+<release id="1" status="Accepted">
+  <artists><artist><id>1</id><name>The Persuader</name><anv/><join/><role/><tracks/></artist></artists>
+  <title>Stockholm</title>
+  <labels><label catno="SK032" id="5" name="Svek"/></labels>
+  <extraartists><artist><id>239</id><name>Jesper Dahlback</name><anv/><join/><role>Music By [All Tracks By]</role><tracks/></artist></extraartists>
+  <formats><format name="Vinyl" qty="2" text=""><descriptions><description>12"</description><description>33 &#8531; RPM</description></descriptions></format></formats>
+  <genres><genre>Electronic</genre></genres>
+  <styles><style>Deep House</style></styles>
+  <country>Sweden</country>
+  <released>1999-03-00</released>
+  <data_quality>Needs Vote</data_quality>
+  <master_id is_main_release="true">5427</master_id>
+  <tracklist>
+    <track><position>A</position><title>&#214;stermalm</title><duration>4:45</duration></track>
+    <track><position>B1</position><title>Vasastaden</title><duration>6:11</duration></track>
+    <track><position/><title>Bonus</title><duration/></track>
+    <track><position>C</position><title>Suite</title><duration/><sub_tracks>
+      <track><position>C.a</position><title>Part One</title><duration>3:00</duration></track>
+      <track><position>C.b</position><title>Part Two</title><duration>2:30</duration></track>
+    </sub_tracks></track>
+  </tracklist>
+  <identifiers><identifier description="A-Side Runout" type="Matrix / Runout" value="MPO SK 032 A1"/></identifiers>
+  <companies><company><id>271046</id><name>The Globe Studios</name><catno/><entity_type>23</entity_type><entity_type_name>Recorded At</entity_type_name><resource_url>https://api.discogs.com/labels/271046</resource_url></company></companies>
+</release>
+<release id="2" status="Unheard Of"><title>Broken</title></release>
+</releases>"#;
+
+    #[test]
+    fn test_read_artists() {
+        let artists: Vec<Artist> = DumpReader::new(ARTISTS.as_bytes()).map(|a| a.ok().unwrap()).collect();
+
+        assert_eq!(artists.len(), 2);
+        assert_eq!(artists[0].id, 1);
+        assert_eq!(artists[0].name, "The Persuader".to_string());
+        assert_eq!(artists[0].realname, Some("Jesper Dahlbäck".to_string()));
+        assert_eq!(artists[0].profile, None);
+        assert_eq!(artists[0].data_quality, Some(DataQuality::NeedsVote));
+        assert_eq!(artists[0].namevariations.as_ref().unwrap().len(), 2);
+        assert_eq!(artists[0].aliases.as_ref().unwrap()[1].id, 16055);
+        assert_eq!(artists[0].images.as_ref().unwrap()[0].image_type, ImageType::Primary);
+        assert_eq!(artists[1].members.as_ref().unwrap()[0].name, "Alexi Delano".to_string());
+        assert_eq!(artists[1].resource_url, "https://api.discogs.com/artists/2".to_string());
+    }
+
+    #[test]
+    fn test_read_releases() {
+        let mut releases = DumpReader::<_, Release>::new(RELEASES.as_bytes());
+        let release = releases.next().unwrap().ok().unwrap();
+
+        assert_eq!(release.id, 1);
+        assert_eq!(release.status, Status::Accepted);
+        assert_eq!(release.title, "Stockholm".to_string());
+        assert_eq!(release.year, 1999);
+        assert_eq!(release.master_id, Some(5427));
+        assert_eq!(release.artists.as_ref().unwrap()[0].name, "The Persuader".to_string());
+        assert_eq!(release.extra_artists.as_ref().unwrap()[0].role, Some("Music By [All Tracks By]".to_string()));
+        assert_eq!(release.labels.as_ref().unwrap()[0].catno, Some("SK032".to_string()));
+        assert_eq!(release.formats.as_ref().unwrap()[0].descriptions[1], "33 ⅓ RPM".to_string());
+        assert_eq!(release.tracklist.as_ref().unwrap()[0].title, "Östermalm".to_string());
+        assert_eq!(release.tracklist.as_ref().unwrap()[0].type_, "track".to_string());
+        assert_eq!(release.tracklist.as_ref().unwrap()[2].type_, "heading".to_string());
+        let index = &release.tracklist.as_ref().unwrap()[3];
+        assert_eq!(index.type_, "index".to_string());
+        assert_eq!(index.sub_tracks.as_ref().unwrap()[1].title, "Part Two".to_string());
+        assert_eq!(release.tracklist.as_ref().unwrap().len(), 4);
+        assert_eq!(release.identifiers.as_ref().unwrap()[0].value, "MPO SK 032 A1".to_string());
+        assert_eq!(release.companies.as_ref().unwrap()[0].entity_type_name, "Recorded At".to_string());
+
+        // A bad entity does not stop the dump
+        assert!(releases.next().unwrap().is_err());
+        assert!(releases.next().is_none());
+    }
+
+    #[test]
+    fn test_read_masters_and_labels() {
+        let masters = r#"<masters><master id="5427"><main_release>1</main_release><title>Stockholm</title><year>1999</year>
+            <videos><video duration="290" embed="true" src="https://www.youtube.com/watch?v=AHuQWcylaU4"><title>Östermalm</title><description/></video></videos>
+            </master></masters>"#;
+        let labels = r#"<labels><label><id>5</id><name>Svek</name><contactinfo>Box 3</contactinfo>
+            <sublabels><label id="1961">Svek Classics</label></sublabels></label></labels>"#;
+
+        let master = DumpReader::<_, Master>::new(masters.as_bytes()).next().unwrap().ok().unwrap();
+        assert_eq!(master.main_release, 1);
+        assert_eq!(master.year, Some(1999));
+        assert_eq!(master.videos.as_ref().unwrap()[0].duration, 290);
+        assert!(master.videos.as_ref().unwrap()[0].embed);
+
+        let label = DumpReader::<_, Label>::new(labels.as_bytes()).next().unwrap().ok().unwrap();
+        assert_eq!(label.id, 5);
+        assert_eq!(label.contact_info, Some("Box 3".to_string()));
+        assert_eq!(label.sublabels.as_ref().unwrap()[0].id, 1961);
+    }
+
+    #[test]
+    fn test_open_gzip() {
+        let path = env::temp_dir().join("discogs-rs-artists.xml.gz");
+        {
+            let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::Default);
+            encoder.write_all(ARTISTS.as_bytes()).unwrap();
+            encoder.finish().unwrap();
+        }
+
+        assert_eq!(artists(&path).ok().unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_truncated_dump() {
+        let mut artists = DumpReader::<_, Artist>::new(&ARTISTS.as_bytes()[..200]);
+
+        assert!(artists.next().unwrap().is_err());
+        assert!(artists.next().is_none());
+    }
+}
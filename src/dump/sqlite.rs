@@ -0,0 +1,608 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Loads the data dumps into a relational SQLite database
+//!
+//! Every entity is imported in the same transaction as its tracks,
+//! credits and other rows, and entities already in the database are
+//! skipped, so an interrupted import can simply be run again.
+
+use data_structures::*;
+use dump::{self, DumpEntity};
+use query::QueryError;
+use rusqlite::{Connection, Transaction};
+use serde::Serialize;
+use serde_json;
+use std::path::Path;
+
+const SCHEMA: &'static str = "
+CREATE TABLE IF NOT EXISTS artists (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    realname TEXT,
+    profile TEXT,
+    data_quality TEXT
+);
+CREATE TABLE IF NOT EXISTS artist_aliases (
+    artist_id INTEGER NOT NULL,
+    alias_id INTEGER NOT NULL,
+    name TEXT
+);
+CREATE TABLE IF NOT EXISTS artist_members (
+    artist_id INTEGER NOT NULL,
+    member_id INTEGER NOT NULL,
+    name TEXT
+);
+CREATE TABLE IF NOT EXISTS labels (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    profile TEXT,
+    contact_info TEXT,
+    data_quality TEXT
+);
+CREATE TABLE IF NOT EXISTS sublabels (
+    label_id INTEGER NOT NULL,
+    sublabel_id INTEGER NOT NULL,
+    name TEXT
+);
+CREATE TABLE IF NOT EXISTS masters (
+    id INTEGER PRIMARY KEY,
+    main_release INTEGER NOT NULL,
+    title TEXT,
+    year INTEGER,
+    data_quality TEXT
+);
+CREATE TABLE IF NOT EXISTS master_artists (
+    master_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    artist_id INTEGER NOT NULL,
+    name TEXT,
+    anv TEXT,
+    join_phrase TEXT
+);
+CREATE TABLE IF NOT EXISTS releases (
+    id INTEGER PRIMARY KEY,
+    title TEXT NOT NULL,
+    status TEXT,
+    country TEXT,
+    released TEXT,
+    year INTEGER,
+    notes TEXT,
+    master_id INTEGER,
+    data_quality TEXT
+);
+CREATE TABLE IF NOT EXISTS release_artists (
+    release_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    artist_id INTEGER NOT NULL,
+    name TEXT,
+    anv TEXT,
+    join_phrase TEXT
+);
+CREATE TABLE IF NOT EXISTS release_labels (
+    release_id INTEGER NOT NULL,
+    label_id INTEGER NOT NULL,
+    name TEXT,
+    catno TEXT
+);
+CREATE TABLE IF NOT EXISTS release_genres (
+    release_id INTEGER NOT NULL,
+    genre TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS release_styles (
+    release_id INTEGER NOT NULL,
+    style TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS formats (
+    release_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    name TEXT,
+    qty TEXT,
+    descriptions TEXT
+);
+CREATE TABLE IF NOT EXISTS tracks (
+    release_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    track_position TEXT,
+    title TEXT,
+    duration TEXT,
+    PRIMARY KEY (release_id, position)
+);
+-- track_position is NULL for the credits of the whole release
+CREATE TABLE IF NOT EXISTS credits (
+    release_id INTEGER NOT NULL,
+    track_position INTEGER,
+    artist_id INTEGER NOT NULL,
+    name TEXT,
+    anv TEXT,
+    role TEXT,
+    tracks TEXT
+);
+CREATE TABLE IF NOT EXISTS identifiers (
+    release_id INTEGER NOT NULL,
+    type TEXT NOT NULL,
+    value TEXT NOT NULL,
+    description TEXT
+);
+
+CREATE INDEX IF NOT EXISTS artists_name ON artists (name);
+CREATE INDEX IF NOT EXISTS labels_name ON labels (name);
+CREATE INDEX IF NOT EXISTS artist_aliases_artist ON artist_aliases (artist_id);
+CREATE INDEX IF NOT EXISTS artist_members_artist ON artist_members (artist_id);
+CREATE INDEX IF NOT EXISTS sublabels_label ON sublabels (label_id);
+CREATE INDEX IF NOT EXISTS master_artists_master ON master_artists (master_id);
+CREATE INDEX IF NOT EXISTS master_artists_artist ON master_artists (artist_id);
+CREATE INDEX IF NOT EXISTS releases_master ON releases (master_id);
+CREATE INDEX IF NOT EXISTS release_artists_release ON release_artists (release_id);
+CREATE INDEX IF NOT EXISTS release_artists_artist ON release_artists (artist_id);
+CREATE INDEX IF NOT EXISTS release_artists_name ON release_artists (name);
+CREATE INDEX IF NOT EXISTS release_labels_release ON release_labels (release_id);
+CREATE INDEX IF NOT EXISTS release_labels_label ON release_labels (label_id);
+CREATE INDEX IF NOT EXISTS release_labels_catno ON release_labels (catno);
+CREATE INDEX IF NOT EXISTS release_genres_release ON release_genres (release_id);
+CREATE INDEX IF NOT EXISTS release_styles_release ON release_styles (release_id);
+CREATE INDEX IF NOT EXISTS formats_release ON formats (release_id);
+CREATE INDEX IF NOT EXISTS credits_release ON credits (release_id);
+CREATE INDEX IF NOT EXISTS credits_artist ON credits (artist_id);
+CREATE INDEX IF NOT EXISTS identifiers_release ON identifiers (release_id);
+CREATE INDEX IF NOT EXISTS identifiers_barcode ON identifiers (value) WHERE type = 'Barcode';
+";
+
+fn sql_error(error: ::rusqlite::Error) -> QueryError {
+    QueryError::SqliteError {
+        error: error
+    }
+}
+
+/// The text of the enums serialized as strings, like `DataQuality`
+fn text<T: Serialize>(value: &Option<T>) -> Option<String> {
+    value.as_ref()
+         .and_then(|v| serde_json::to_value(v).ok())
+         .and_then(|v| v.as_str().map(|s| s.to_string()))
+}
+
+/// An entity the `Importer` can store
+pub trait Importable: DumpEntity {
+    /// The table holding the entity, with an `id` primary key
+    fn table() -> &'static str;
+
+    /// Inserts the entity and the rows that belong to it
+    fn insert(&self, tx: &Transaction) -> Result<(), QueryError>;
+}
+
+/// The counters passed to the progress callback of an import
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportStats {
+    /// Entities read from the dump
+    pub read: u64,
+    pub imported: u64,
+    /// Entities that were already in the database
+    pub skipped: u64,
+    /// Entities that could not be decoded
+    pub failed: u64,
+}
+
+/// Imports dumps into a SQLite database
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use discogs::dump::sqlite::Importer;
+///
+/// let mut importer = Importer::open("discogs.db").ok().unwrap();
+///
+/// let stats = importer.import_releases("discogs_20170301_releases.xml.gz", |stats| {
+///     println!("{} releases read", stats.read);
+/// });
+/// ```
+pub struct Importer {
+    conn: Connection,
+    batch_size: u64,
+}
+
+impl Importer {
+    /// Opens the database at `path`, creating the schema if needed
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Importer, QueryError> {
+        Importer::new(try!(Connection::open(path).map_err(sql_error)))
+    }
+
+    /// Uses an existing connection, creating the schema if needed
+    pub fn new(conn: Connection) -> Result<Importer, QueryError> {
+        try!(conn.execute_batch(SCHEMA).map_err(sql_error));
+
+        Ok(Importer {
+            conn: conn,
+            batch_size: 1000,
+        })
+    }
+
+    /// How many entities are committed at once, and how often progress is reported
+    pub fn batch_size(&mut self, batch_size: u64) -> &mut Importer {
+        self.batch_size = if batch_size == 0 { 1 } else { batch_size };
+        self
+    }
+
+    /// The connection to the database, to query it
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub fn import_artists<P, F>(&mut self, path: P, progress: F) -> Result<ImportStats, QueryError>
+        where P: AsRef<Path>, F: FnMut(&ImportStats)
+    {
+        let artists = try!(dump::artists(path));
+        self.import(artists, progress)
+    }
+
+    pub fn import_labels<P, F>(&mut self, path: P, progress: F) -> Result<ImportStats, QueryError>
+        where P: AsRef<Path>, F: FnMut(&ImportStats)
+    {
+        let labels = try!(dump::labels(path));
+        self.import(labels, progress)
+    }
+
+    pub fn import_masters<P, F>(&mut self, path: P, progress: F) -> Result<ImportStats, QueryError>
+        where P: AsRef<Path>, F: FnMut(&ImportStats)
+    {
+        let masters = try!(dump::masters(path));
+        self.import(masters, progress)
+    }
+
+    pub fn import_releases<P, F>(&mut self, path: P, progress: F) -> Result<ImportStats, QueryError>
+        where P: AsRef<Path>, F: FnMut(&ImportStats)
+    {
+        let releases = try!(dump::releases(path));
+        self.import(releases, progress)
+    }
+
+    /// Imports `entities`, calling `progress` after every batch and at the end
+    ///
+    /// Entities that fail to decode are counted and skipped. Database
+    /// errors and malformed xml stop the import, the batch being read is
+    /// not committed.
+    pub fn import<T, I, F>(&mut self, entities: I, mut progress: F) -> Result<ImportStats, QueryError>
+        where T: Importable, I: Iterator<Item = Result<T, QueryError>>, F: FnMut(&ImportStats)
+    {
+        let exists = format!("SELECT COUNT(*) FROM {} WHERE id = ?", T::table());
+        let mut stats = ImportStats::default();
+        let mut entities = entities.peekable();
+
+        while entities.peek().is_some() {
+            {
+                let tx = try!(self.conn.transaction().map_err(sql_error));
+
+                for _ in 0..self.batch_size {
+                    let entity = match entities.next() {
+                        Some(entity) => entity,
+                        None => break,
+                    };
+                    stats.read += 1;
+
+                    let entity = match entity {
+                        Ok(entity) => entity,
+                        // Nothing follows an error ending the dump, like truncated xml
+                        Err(error) => {
+                            if entities.size_hint().1 == Some(0) {
+                                return Err(error);
+                            }
+                            stats.failed += 1;
+                            continue;
+                        },
+                    };
+
+                    let count: i64 = try!(tx.query_row(&exists, &[&(entity.entity_id() as i64)], |row| row.get(0))
+                                            .map_err(sql_error));
+                    if count > 0 {
+                        stats.skipped += 1;
+                        continue;
+                    }
+
+                    try!(entity.insert(&tx));
+                    stats.imported += 1;
+                }
+
+                try!(tx.commit().map_err(sql_error));
+            }
+
+            progress(&stats);
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Inserts the credits of a release or track
+fn insert_credits(tx: &Transaction,
+                  release_id: u32,
+                  track_position: Option<i64>,
+                  artists: &Option<Vec<Artist>>) -> Result<(), QueryError> {
+    if let Some(ref artists) = *artists {
+        let mut stmt = try!(tx.prepare_cached("INSERT INTO credits (release_id, track_position, artist_id, name, anv, role, tracks)
+                                               VALUES (?, ?, ?, ?, ?, ?, ?)")
+                              .map_err(sql_error));
+        for artist in artists.iter() {
+            try!(stmt.execute(&[&(release_id as i64), &track_position, &(artist.id as i64), &artist.name,
+                                &artist.anv, &artist.role, &artist.tracks])
+                     .map_err(sql_error));
+        }
+    }
+    Ok(())
+}
+
+/// Inserts the main artists of a release or master into `table`
+fn insert_artist_credits(tx: &Transaction,
+                         table: &str,
+                         owner: &str,
+                         owner_id: u32,
+                         artists: &Option<Vec<Artist>>) -> Result<(), QueryError> {
+    if let Some(ref artists) = *artists {
+        let sql = format!("INSERT INTO {} ({}, position, artist_id, name, anv, join_phrase) VALUES (?, ?, ?, ?, ?, ?)",
+                          table, owner);
+        let mut stmt = try!(tx.prepare_cached(&sql).map_err(sql_error));
+
+        for (position, artist) in artists.iter().enumerate() {
+            try!(stmt.execute(&[&(owner_id as i64), &(position as i64), &(artist.id as i64), &artist.name,
+                                &artist.anv, &artist.join])
+                     .map_err(sql_error));
+        }
+    }
+    Ok(())
+}
+
+/// Inserts `(owner, id, name)` rows, like the aliases of an artist
+fn insert_references(tx: &Transaction, sql: &str, owner_id: u32, references: &[(u32, &String)]) -> Result<(), QueryError> {
+    let mut stmt = try!(tx.prepare_cached(sql).map_err(sql_error));
+
+    for &(id, name) in references.iter() {
+        try!(stmt.execute(&[&(owner_id as i64), &(id as i64), name]).map_err(sql_error));
+    }
+    Ok(())
+}
+
+/// Inserts `(release, value)` rows, like the genres of a release
+fn insert_values(tx: &Transaction, sql: &str, release_id: u32, values: &Option<Vec<String>>) -> Result<(), QueryError> {
+    if let Some(ref values) = *values {
+        let mut stmt = try!(tx.prepare_cached(sql).map_err(sql_error));
+
+        for value in values.iter() {
+            try!(stmt.execute(&[&(release_id as i64), value]).map_err(sql_error));
+        }
+    }
+    Ok(())
+}
+
+fn artist_references(artists: &Option<Vec<Artist>>) -> Vec<(u32, &String)> {
+    artists.as_ref().map_or(Vec::new(), |a| a.iter().map(|a| (a.id, &a.name)).collect())
+}
+
+impl Importable for Artist {
+    fn table() -> &'static str {
+        "artists"
+    }
+
+    fn insert(&self, tx: &Transaction) -> Result<(), QueryError> {
+        try!(tx.execute("INSERT INTO artists (id, name, realname, profile, data_quality) VALUES (?, ?, ?, ?, ?)",
+                        &[&(self.id as i64), &self.name, &self.realname, &self.profile, &text(&self.data_quality)])
+               .map_err(sql_error));
+
+        try!(insert_references(tx,
+                               "INSERT INTO artist_aliases (artist_id, alias_id, name) VALUES (?, ?, ?)",
+                               self.id,
+                               &artist_references(&self.aliases)));
+        insert_references(tx,
+                          "INSERT INTO artist_members (artist_id, member_id, name) VALUES (?, ?, ?)",
+                          self.id,
+                          &artist_references(&self.members))
+    }
+}
+
+impl Importable for Label {
+    fn table() -> &'static str {
+        "labels"
+    }
+
+    fn insert(&self, tx: &Transaction) -> Result<(), QueryError> {
+        try!(tx.execute("INSERT INTO labels (id, name, profile, contact_info, data_quality) VALUES (?, ?, ?, ?, ?)",
+                        &[&(self.id as i64), &self.name, &self.profile, &self.contact_info, &text(&self.data_quality)])
+               .map_err(sql_error));
+
+        let sublabels = self.sublabels.as_ref().map_or(Vec::new(), |s| s.iter().map(|l| (l.id, &l.name)).collect());
+        insert_references(tx,
+                          "INSERT INTO sublabels (label_id, sublabel_id, name) VALUES (?, ?, ?)",
+                          self.id,
+                          &sublabels)
+    }
+}
+
+impl Importable for Master {
+    fn table() -> &'static str {
+        "masters"
+    }
+
+    fn insert(&self, tx: &Transaction) -> Result<(), QueryError> {
+        try!(tx.execute("INSERT INTO masters (id, main_release, title, year, data_quality) VALUES (?, ?, ?, ?, ?)",
+                        &[&(self.id as i64), &(self.main_release as i64), &self.title,
+                          &self.year.map(|y| y as i64), &text(&self.data_quality)])
+               .map_err(sql_error));
+
+        insert_artist_credits(tx, "master_artists", "master_id", self.id, &self.artists)
+    }
+}
+
+impl Importable for Release {
+    fn table() -> &'static str {
+        "releases"
+    }
+
+    fn insert(&self, tx: &Transaction) -> Result<(), QueryError> {
+        let id = self.id as i64;
+        let year = if self.year == 0 { None } else { Some(self.year as i64) };
+
+        try!(tx.execute("INSERT INTO releases (id, title, status, country, released, year, notes, master_id, data_quality)
+                         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                        &[&id, &self.title, &text(&Some(self.status.clone())), &self.country, &self.released,
+                          &year, &self.notes, &self.master_id.map(|m| m as i64), &text(&self.data_quality)])
+               .map_err(sql_error));
+
+        try!(insert_artist_credits(tx, "release_artists", "release_id", self.id, &self.artists));
+        try!(insert_credits(tx, self.id, None, &self.extra_artists));
+        try!(insert_values(tx, "INSERT INTO release_genres (release_id, genre) VALUES (?, ?)", self.id, &self.genres));
+        try!(insert_values(tx, "INSERT INTO release_styles (release_id, style) VALUES (?, ?)", self.id, &self.styles));
+
+        if let Some(ref labels) = self.labels {
+            let mut stmt = try!(tx.prepare_cached("INSERT INTO release_labels (release_id, label_id, name, catno)
+                                                   VALUES (?, ?, ?, ?)")
+                                  .map_err(sql_error));
+            for label in labels.iter() {
+                try!(stmt.execute(&[&id, &(label.id as i64), &label.name, &label.catno]).map_err(sql_error));
+            }
+        }
+
+        if let Some(ref formats) = self.formats {
+            let mut stmt = try!(tx.prepare_cached("INSERT INTO formats (release_id, position, name, qty, descriptions)
+                                                   VALUES (?, ?, ?, ?, ?)")
+                                  .map_err(sql_error));
+            for (position, format) in formats.iter().enumerate() {
+                try!(stmt.execute(&[&id, &(position as i64), &format.name, &format.qty, &format.descriptions.join(", ")])
+                         .map_err(sql_error));
+            }
+        }
+
+        if let Some(ref tracklist) = self.tracklist {
+            for (position, track) in tracklist.iter().enumerate() {
+                try!(tx.prepare_cached("INSERT INTO tracks (release_id, position, track_position, title, duration)
+                                        VALUES (?, ?, ?, ?, ?)")
+                       .and_then(|mut stmt| stmt.execute(&[&id, &(position as i64), &track.position,
+                                                           &track.title, &track.duration]))
+                       .map_err(sql_error));
+                try!(insert_credits(tx, self.id, Some(position as i64), &track.extra_artists));
+            }
+        }
+
+        if let Some(ref identifiers) = self.identifiers {
+            let mut stmt = try!(tx.prepare_cached("INSERT INTO identifiers (release_id, type, value, description)
+                                                   VALUES (?, ?, ?, ?)")
+                                  .map_err(sql_error));
+            for identifier in identifiers.iter() {
+                try!(stmt.execute(&[&id, &identifier.identifier_type, &identifier.value, &identifier.description])
+                         .map_err(sql_error));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dump::DumpReader;
+    use data_structures::*;
+    use rusqlite::Connection;
+
+    const RELEASES: &'static str = r#"<releases>
+<release id="1" status="Accepted">
+  <artists><artist><id>1</id><name>The Persuader</name><anv/><join/><role/><tracks/></artist></artists>
+  <title>Stockholm</title>
+  <labels><label catno="SK032" id="5" name="Svek"/></labels>
+  <extraartists><artist><id>239</id><name>Jesper Dahlback</name><anv/><join/><role>Music By</role><tracks/></artist></extraartists>
+  <formats><format name="Vinyl" qty="2" text=""><descriptions><description>12"</description></descriptions></format></formats>
+  <genres><genre>Electronic</genre></genres>
+  <released>1999-03-00</released>
+  <tracklist>
+    <track><position>A</position><title>Östermalm</title><duration>4:45</duration></track>
+    <track><position>B1</position><title>Vasastaden</title><duration>6:11</duration>
+      <extraartists><artist><id>2</id><name>Remixer</name><anv/><join/><role>Remix</role><tracks/></artist></extraartists>
+    </track>
+  </tracklist>
+  <identifiers><identifier type="Barcode" value="7 332334 432436"/></identifiers>
+</release>
+<release id="2" status="Accepted"><title>Svek</title><labels><label catno="SK033" id="5" name="Svek"/></labels></release>
+<release id="3"><title>No status</title></release>
+</releases>"#;
+
+    fn importer() -> Importer {
+        let mut importer = Importer::new(Connection::open_in_memory().unwrap()).ok().unwrap();
+        importer.batch_size(2);
+        importer
+    }
+
+    fn count(importer: &Importer, sql: &str) -> i64 {
+        importer.connection().query_row(sql, &[], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_import_releases() {
+        let mut importer = importer();
+        let mut reports = Vec::new();
+
+        let stats = importer.import(DumpReader::<_, Release>::new(RELEASES.as_bytes()),
+                                    |stats| reports.push(stats.clone()))
+                            .ok()
+                            .unwrap();
+
+        assert_eq!(stats, ImportStats { read: 3, imported: 2, skipped: 0, failed: 1 });
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].read, 2);
+
+        assert_eq!(count(&importer, "SELECT COUNT(*) FROM tracks WHERE release_id = 1"), 2);
+        assert_eq!(count(&importer, "SELECT COUNT(*) FROM credits WHERE track_position IS NULL"), 1);
+        assert_eq!(count(&importer, "SELECT track_position FROM credits WHERE role = 'Remix'"), 1);
+        assert_eq!(count(&importer, "SELECT release_id FROM release_labels WHERE catno = 'SK033'"), 2);
+        assert_eq!(count(&importer, "SELECT release_id FROM identifiers
+                                     WHERE type = 'Barcode' AND value = '7 332334 432436'"), 1);
+        assert_eq!(count(&importer, "SELECT year FROM releases WHERE id = 1"), 1999);
+    }
+
+    #[test]
+    fn test_import_is_resumable() {
+        let mut importer = importer();
+
+        importer.import(DumpReader::<_, Release>::new(RELEASES.as_bytes()).take(1), |_| ()).ok().unwrap();
+        let stats = importer.import(DumpReader::<_, Release>::new(RELEASES.as_bytes()), |_| ()).ok().unwrap();
+
+        assert_eq!(stats, ImportStats { read: 3, imported: 1, skipped: 1, failed: 1 });
+        assert_eq!(count(&importer, "SELECT COUNT(*) FROM tracks"), 2);
+    }
+
+    #[test]
+    fn test_import_of_truncated_dump_fails() {
+        let mut importer = importer();
+        let truncated = &RELEASES[..RELEASES.find("<release id=\"3\">").unwrap() + 10];
+
+        match importer.import(DumpReader::<_, Release>::new(truncated.as_bytes()), |_| ()) {
+            Err(QueryError::DumpDecodeError { .. }) => (),
+            other => panic!("Expected a DumpDecodeError, got {:?}", other),
+        }
+
+        // The batches before the error are kept, so the import can be resumed
+        assert_eq!(count(&importer, "SELECT COUNT(*) FROM releases"), 2);
+    }
+
+    #[test]
+    fn test_import_artists() {
+        let artists = r#"<artists><artist><id>1</id><name>The Persuader</name>
+            <data_quality>Needs Vote</data_quality>
+            <aliases><name id="239">Jesper Dahlback</name></aliases></artist></artists>"#;
+        let mut importer = importer();
+
+        importer.import(DumpReader::<_, Artist>::new(artists.as_bytes()), |_| ()).ok().unwrap();
+
+        assert_eq!(count(&importer, "SELECT id FROM artists WHERE name = 'The Persuader'
+                                     AND data_quality = 'Needs Vote'"), 1);
+        assert_eq!(count(&importer, "SELECT alias_id FROM artist_aliases WHERE artist_id = 1"), 239);
+    }
+}
@@ -0,0 +1,313 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! A stateful stand-in for the Discogs API, to test against without network
+//!
+//! The server listens on a free local port and keeps everything it is told
+//! in memory: the database entities come from a fixture document, and the
+//! marketplace, inventory, wantlist and collection writes change its state
+//! the way the API would, so a listing created with one request shows up in
+//! the inventory fetched by the next.
+//!
+//! Tokens are mapped to usernames with `add_user` (or the `users` fixture),
+//! writes are only allowed for the user owning the resource.
+//! Rate limiting and errors can be injected to test retries and backoff.
+//!
+//! The wantlist and collection endpoints are served even though this crate
+//! has no query builders for them yet, other clients can still use them.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[macro_use] extern crate serde_json;
+//! # extern crate discogs;
+//! # #[cfg(feature = "fake_server")]
+//! # fn main() {
+//! use discogs::fake_server::FakeServer;
+//!
+//! let server = FakeServer::with_fixtures(&json!({
+//!     "releases": [{ "id": 1, "title": "Stockholm", "status": "Accepted",
+//!                    "resource_url": "", "year": 1999 }]
+//! })).unwrap();
+//!
+//! let release = server.client("USER_AGENT").release(1).get().unwrap();
+//! assert_eq!(release.title, "Stockholm");
+//! # }
+//! # #[cfg(not(feature = "fake_server"))]
+//! # fn main() {}
+//! ```
+
+mod state;
+mod routes;
+
+pub use self::state::InjectedError;
+
+use discogs::Discogs;
+use fake_server::routes::{respond, FakeRequest};
+use fake_server::state::State;
+use hyper::server::{Listening, Request, Response, Server};
+use hyper::uri::RequestUri;
+use query::QueryError;
+use serde_json;
+use serde_json::Value;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+pub struct FakeServer {
+    listening: Listening,
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeServer {
+    /// Starts an empty server on a free local port
+    pub fn start() -> Result<FakeServer, QueryError> {
+        let state = Arc::new(Mutex::new(State::new()));
+        let handler_state = state.clone();
+
+        let mut server = try!(Server::http("127.0.0.1:0").map_err(|e| QueryError::HyperSendError { hyper_err: e }));
+        // Every connection holds a thread while kept alive
+        server.keep_alive(None);
+
+        let listening = try!(server.handle(move |request: Request, response: Response| {
+                                        serve(&handler_state, request, response)
+                                    })
+                                   .map_err(|e| QueryError::HyperSendError { hyper_err: e }));
+
+        let url = format!("http://{}", listening.socket);
+        state.lock().unwrap().base_url = url.clone();
+
+        Ok(FakeServer {
+            listening: listening,
+            url: url,
+            state: state,
+        })
+    }
+
+    /// Starts a server holding the entities of `fixtures`,
+    /// see `seed` for their format
+    pub fn with_fixtures(fixtures: &Value) -> Result<FakeServer, QueryError> {
+        let server = try!(FakeServer::start());
+        try!(server.seed(fixtures));
+        Ok(server)
+    }
+
+    /// Starts a server holding the entities of the fixture file at `path`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<FakeServer, QueryError> {
+        let mut text = String::new();
+        try!(File::open(path).and_then(|mut f| f.read_to_string(&mut text))
+                             .map_err(|e| QueryError::TextReadError { error: e }));
+
+        let fixtures: Value = try!(serde_json::from_str(&text)
+                                       .map_err(|e| QueryError::JsonDecodeError { serde_err: Some(e) }));
+        FakeServer::with_fixtures(&fixtures)
+    }
+
+    /// The url the server is reached at
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// A client sending its queries to this server
+    pub fn client(&self, user_agent: &str) -> Discogs {
+        let mut client = Discogs::new(user_agent);
+        client.api_endpoint(&self.url);
+        client
+    }
+
+    /// Adds the entities of a fixture document
+    ///
+    /// Every key is optional: `artists`, `labels`, `masters`, `releases`,
+    /// `lists`, `listings` and `orders` are arrays of the objects the API
+    /// returns, `messages`, `wantlists` and `collections` map an order id or
+    /// a username to an array, `price_suggestions` maps a release id to its
+    /// suggestions and `users` maps a token to a username.
+    pub fn seed(&self, fixtures: &Value) -> Result<(), QueryError> {
+        self.state.lock().unwrap().seed(fixtures)
+    }
+
+    /// Authenticates the requests sending `token` as `username`
+    pub fn add_user(&self, token: &str, username: &str) -> &FakeServer {
+        self.state.lock().unwrap().users.insert(token.to_owned(), username.to_owned());
+        self
+    }
+
+    /// Answers 429 once more than `per_minute` requests came in the last minute,
+    /// `None` lifts the limit
+    pub fn rate_limit(&self, per_minute: Option<u32>) -> &FakeServer {
+        let mut state = self.state.lock().unwrap();
+        state.rate_limit = per_minute;
+        state.recent_requests.clear();
+        self
+    }
+
+    /// Answers `status` to the next `times` requests whose path starts with `path_prefix`
+    pub fn fail(&self, path_prefix: &str, status: u16, times: u32) -> &FakeServer {
+        self.state.lock().unwrap().errors.push(InjectedError {
+            path_prefix: path_prefix.to_owned(),
+            status: status,
+            remaining: times,
+        });
+        self
+    }
+
+    /// The method and path, with the query, of every request received so far
+    pub fn requests(&self) -> Vec<(String, String)> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Everything the server holds, in the fixture format
+    pub fn snapshot(&self) -> Value {
+        self.state.lock().unwrap().snapshot()
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        let _ = self.listening.close();
+    }
+}
+
+fn serve(state: &Mutex<State>, mut request: Request, mut response: Response) {
+    let uri = match request.uri {
+        RequestUri::AbsolutePath(ref path) => path.clone(),
+        RequestUri::AbsoluteUri(ref url) => {
+            match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_owned(),
+            }
+        },
+        _ => "/".to_owned(),
+    };
+    let (path, query) = FakeRequest::parse_uri(&uri);
+
+    let authorization = request.headers
+                               .get_raw("Authorization")
+                               .and_then(|values| values.first())
+                               .map(|value| String::from_utf8_lossy(value).into_owned())
+                               .unwrap_or_default();
+    let token = authorization.split(|c: char| c == ' ' || c == ',')
+                             .find(|part| part.starts_with("token="))
+                             .map(|part| part["token=".len()..].to_owned());
+    let content_type = request.headers
+                              .get_raw("Content-Type")
+                              .and_then(|values| values.first())
+                              .map(|value| String::from_utf8_lossy(value).into_owned());
+
+    let mut body = Vec::new();
+    let _ = request.read_to_end(&mut body);
+
+    let fake = FakeRequest {
+        method: request.method.clone(),
+        path: path,
+        query: query,
+        authorized: authorization.contains("token=") || authorization.contains("key="),
+        token: token,
+        content_type: content_type,
+        body: body,
+    };
+    let reply = respond(&mut state.lock().unwrap(), &fake);
+
+    *response.status_mut() = reply.status;
+    for (name, value) in reply.headers {
+        response.headers_mut().set_raw(name, vec![value.into_bytes()]);
+    }
+    let _ = response.send(&reply.body);
+}
+
+#[cfg(test)]
+mod tests {
+    use data_structures::*;
+    use fake_server::*;
+
+    fn fixtures() -> Value {
+        json!({
+            "artists": [{ "id": 1, "name": "The Persuader", "resource_url": "" }],
+            "releases": [{
+                "id": 1,
+                "title": "Stockholm",
+                "status": "Accepted",
+                "resource_url": "",
+                "year": 1999,
+                "artists": [{ "id": 1, "name": "The Persuader" }]
+            }],
+            "users": { "TOKEN": "seller" }
+        })
+    }
+
+    #[test]
+    fn test_database_lookup() {
+        let server = FakeServer::with_fixtures(&fixtures()).unwrap();
+        let client = server.client("USER_AGENT");
+
+        assert_eq!(client.release(1).get().unwrap().title, "Stockholm".to_string());
+        assert!(client.release(2).get().is_err());
+        assert_eq!(client.artist(1).get().unwrap().name, "The Persuader".to_string());
+
+        assert_eq!(server.requests(),
+                   vec![("GET".to_string(), "/releases/1".to_string()),
+                        ("GET".to_string(), "/releases/2".to_string()),
+                        ("GET".to_string(), "/artists/1".to_string())]);
+    }
+
+    #[test]
+    fn test_listing_lifecycle() {
+        let server = FakeServer::with_fixtures(&fixtures()).unwrap();
+        let mut client = server.client("USER_AGENT");
+        client.token("TOKEN");
+
+        let request = ListingRequest::new(1, MediaCondition::NearMint, 10.0, NewListingStatus::ForSale);
+        let listing = client.create_listing(request).create().unwrap();
+
+        let inventory = client.inventory("seller").get().unwrap();
+        assert_eq!(inventory.listings.len(), 1);
+        assert_eq!(inventory.listings[0].id, listing.listing_id);
+        assert_eq!(inventory.listings[0].release.title, Some("Stockholm".to_string()));
+
+        client.listing(listing.listing_id).delete().unwrap();
+        assert!(client.inventory("seller").get().unwrap().listings.is_empty());
+        assert_eq!(server.snapshot()["listings"], json!([]));
+    }
+
+    #[test]
+    fn test_writes_require_a_known_token() {
+        let server = FakeServer::with_fixtures(&fixtures()).unwrap();
+        let mut client = server.client("USER_AGENT");
+        client.token("UNKNOWN");
+
+        let request = ListingRequest::new(1, MediaCondition::NearMint, 10.0, NewListingStatus::ForSale);
+        assert!(client.create_listing(request).create().is_err());
+        assert_eq!(server.snapshot()["listings"], json!([]));
+    }
+
+    #[test]
+    fn test_injected_errors() {
+        let server = FakeServer::with_fixtures(&fixtures()).unwrap();
+        let client = server.client("USER_AGENT");
+
+        server.fail("/releases", 500, 1);
+        assert!(client.release(1).get().is_err());
+        assert!(client.release(1).get().is_ok());
+
+        server.rate_limit(Some(1));
+        assert!(client.release(1).get().is_ok());
+        assert!(client.release(1).get().is_err());
+        server.rate_limit(None);
+        assert!(client.release(1).get().is_ok());
+    }
+}
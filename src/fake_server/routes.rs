@@ -0,0 +1,1323 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use data_structures::{parse_csv, write_csv_record, MediaCondition, Order, OrderStatus};
+use fake_server::state::{timestamp, State};
+use hyper::Url;
+use hyper::method::Method;
+use hyper::status::StatusCode;
+use serde_json;
+use serde_json::Value;
+use std::cmp::{max, min, Ordering};
+use std::time::Instant;
+
+/// A request, as seen by the routes
+#[derive(Debug, Clone)]
+pub struct FakeRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    // Any credentials were sent, key and secret or token
+    pub authorized: bool,
+    pub token: Option<String>,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl FakeRequest {
+    /// Splits `uri` into its path and query parameters
+    ///
+    /// The search query builder separates its parameters with commas,
+    /// those are treated like ampersands. Encoded commas are kept.
+    pub fn parse_uri(uri: &str) -> (String, Vec<(String, String)>) {
+        let (path, query) = match uri.find('?') {
+            Some(i) => (&uri[..i], &uri[i + 1..]),
+            None => (uri, ""),
+        };
+
+        let query = match Url::parse(&format!("http://fake/?{}", query.replace(',', "&"))) {
+            Ok(url) => url.query_pairs().into_owned().filter(|&(ref k, _)| !k.is_empty()).collect(),
+            Err(_) => Vec::new(),
+        };
+
+        (path.to_owned(), query)
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query.iter().find(|&&(ref k, _)| k == name).map(|&(_, ref v)| v.as_str())
+    }
+
+    fn json_body(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+
+    /// A parameter from the query, or else from a json body
+    fn field(&self, name: &str) -> Option<Value> {
+        match self.param(name) {
+            Some(value) => Some(Value::String(value.to_owned())),
+            None => {
+                let body = self.json_body();
+                if body[name].is_null() { None } else { Some(body[name].clone()) }
+            },
+        }
+    }
+}
+
+/// A response, before it is written
+#[derive(Debug, Clone)]
+pub struct FakeResponse {
+    pub status: StatusCode,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl FakeResponse {
+    fn json(status: StatusCode, body: &Value) -> FakeResponse {
+        FakeResponse {
+            status: status,
+            headers: vec![("Content-Type".to_owned(), "application/json".to_owned())],
+            body: serde_json::to_vec(body).unwrap(),
+        }
+    }
+
+    fn ok(body: &Value) -> FakeResponse {
+        FakeResponse::json(StatusCode::Ok, body)
+    }
+
+    fn no_content() -> FakeResponse {
+        FakeResponse {
+            status: StatusCode::NoContent,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Errors look like the ones of the API, a json object with a message
+    fn error(status: StatusCode, message: &str) -> FakeResponse {
+        FakeResponse::json(status, &json!({ "message": message }))
+    }
+
+    fn not_found(what: &str) -> FakeResponse {
+        FakeResponse::error(StatusCode::NotFound, &format!("{} not found.", what))
+    }
+
+    fn invalid(message: &str) -> FakeResponse {
+        FakeResponse::error(StatusCode::UnprocessableEntity, message)
+    }
+
+    fn header(mut self, name: &str, value: &str) -> FakeResponse {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+}
+
+/// Answers `request`, changing `state` for the writes
+pub fn respond(state: &mut State, request: &FakeRequest) -> FakeResponse {
+    let full_path = match request.query.is_empty() {
+        true => request.path.clone(),
+        false => format!("{}?{}",
+                         request.path,
+                         request.query.iter().map(|&(ref k, ref v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("&")),
+    };
+    state.requests.push((request.method.to_string(), full_path));
+
+    if !state.within_rate_limit(Instant::now()) {
+        let limit = state.rate_limit.unwrap_or(0).to_string();
+        return FakeResponse::error(StatusCode::TooManyRequests, "You are making requests too quickly.")
+                   .header("X-Discogs-Ratelimit", &limit)
+                   .header("X-Discogs-Ratelimit-Used", &limit)
+                   .header("X-Discogs-Ratelimit-Remaining", "0");
+    }
+
+    let mut response = match state.injected_error(&request.path) {
+        Some(status) => FakeResponse::error(StatusCode::from_u16(status), "Injected error"),
+        None => route(state, request),
+    };
+
+    if let Some(limit) = state.rate_limit {
+        let used = state.recent_requests.len() as u32;
+        response = response.header("X-Discogs-Ratelimit", &limit.to_string())
+                           .header("X-Discogs-Ratelimit-Used", &used.to_string())
+                           .header("X-Discogs-Ratelimit-Remaining", &(limit.saturating_sub(used)).to_string());
+    }
+    response
+}
+
+/// The values of the `:name` segments of `pattern`, if `path` matches it
+fn matches(path: &str, pattern: &str) -> Option<Vec<String>> {
+    let path: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let pattern: Vec<&str> = pattern.trim_matches('/').split('/').collect();
+
+    if path.len() != pattern.len() {
+        return None;
+    }
+
+    let mut params = Vec::new();
+    for (segment, expected) in path.iter().zip(pattern.iter()) {
+        if expected.starts_with(':') {
+            params.push(segment.to_string());
+        } else if segment != expected {
+            return None;
+        }
+    }
+    Some(params)
+}
+
+fn route(state: &mut State, request: &FakeRequest) -> FakeResponse {
+    let path = request.path.as_str();
+    let user = request.token.as_ref().and_then(|t| state.users.get(t).cloned());
+
+    let get = request.method == Method::Get;
+    let post = request.method == Method::Post;
+    let put = request.method == Method::Put;
+    let delete = request.method == Method::Delete;
+
+    // Database
+    if let (true, Some(p)) = (get, matches(path, "/artists/:id")) {
+        return entity(&state.artists, &p[0], "Artist");
+    }
+    if let (true, Some(p)) = (get, matches(path, "/artists/:id/releases")) {
+        return artist_releases(state, request, &p[0]);
+    }
+    if let (true, Some(p)) = (get, matches(path, "/labels/:id")) {
+        return entity(&state.labels, &p[0], "Label");
+    }
+    if let (true, Some(p)) = (get, matches(path, "/labels/:id/releases")) {
+        return label_releases(state, request, &p[0]);
+    }
+    if let (true, Some(p)) = (get, matches(path, "/masters/:id")) {
+        return entity(&state.masters, &p[0], "Master");
+    }
+    if let (true, Some(p)) = (get, matches(path, "/releases/:id")) {
+        return entity(&state.releases, &p[0], "Release");
+    }
+    if let (true, Some(_)) = (get, matches(path, "/database/search")) {
+        return search(state, request);
+    }
+    if let (true, Some(p)) = (get, matches(path, "/lists/:id")) {
+        return entity(&state.lists, &p[0], "List");
+    }
+    if let (true, Some(p)) = (get, matches(path, "/users/:username/lists")) {
+        return user_lists(state, request, &p[0]);
+    }
+
+    // Marketplace
+    if let (true, Some(p)) = (get, matches(path, "/users/:username/inventory")) {
+        return inventory(state, request, user, &p[0]);
+    }
+    if let (true, Some(_)) = (post, matches(path, "/marketplace/listings")) {
+        return create_listing(state, request, user);
+    }
+    if let Some(p) = matches(path, "/marketplace/listings/:id") {
+        if get {
+            return get_listing(state, user, &p[0]);
+        } else if post {
+            return edit_listing(state, request, user, &p[0]);
+        } else if delete {
+            return delete_listing(state, user, &p[0]);
+        }
+    }
+    if let (true, Some(_)) = (get, matches(path, "/marketplace/orders")) {
+        return orders(state, request, user);
+    }
+    if let Some(p) = matches(path, "/marketplace/orders/:id") {
+        if get {
+            return get_order(state, user, &p[0]);
+        } else if post {
+            return update_order(state, request, user, &p[0]);
+        }
+    }
+    if let Some(p) = matches(path, "/marketplace/orders/:id/messages") {
+        if get {
+            return order_messages(state, request, user, &p[0]);
+        } else if post {
+            return send_order_message(state, request, user, &p[0]);
+        }
+    }
+    if let (true, Some(p)) = (get, matches(path, "/marketplace/fee/:price")) {
+        return fee(&p[0], "USD");
+    }
+    if let (true, Some(p)) = (get, matches(path, "/marketplace/fee/:price/:currency")) {
+        return fee(&p[0], &p[1]);
+    }
+    if let (true, Some(p)) = (get, matches(path, "/marketplace/price_suggestions/:id")) {
+        return price_suggestions(state, user, &p[0]);
+    }
+
+    // Inventory exports and uploads
+    if let Some(_) = matches(path, "/inventory/export") {
+        if post {
+            return create_export(state, user);
+        } else if get {
+            return exports(state, request, user);
+        }
+    }
+    if let (true, Some(p)) = (get, matches(path, "/inventory/export/:id")) {
+        return get_export(state, user, &p[0]);
+    }
+    if let (true, Some(p)) = (get, matches(path, "/inventory/export/:id/download")) {
+        return download_export(state, user, &p[0]);
+    }
+    if let (true, Some(_)) = (get, matches(path, "/inventory/upload")) {
+        return uploads(state, request, user);
+    }
+    if let (true, Some(p)) = (post, matches(path, "/inventory/upload/:type")) {
+        return create_upload(state, request, user, &p[0]);
+    }
+    if let (true, Some(p)) = (get, matches(path, "/inventory/upload/:id")) {
+        return get_upload(state, user, &p[0]);
+    }
+
+    // Wantlist and collection
+    if let (true, Some(p)) = (get, matches(path, "/users/:username/wants")) {
+        return wants(state, request, &p[0]);
+    }
+    if let Some(p) = matches(path, "/users/:username/wants/:release") {
+        if put || post {
+            return put_want(state, request, user, &p[0], &p[1], put);
+        } else if delete {
+            return delete_want(state, user, &p[0], &p[1]);
+        }
+    }
+    if let (true, Some(p)) = (get, matches(path, "/users/:username/collection/folders")) {
+        return folders(state, &p[0]);
+    }
+    if let (true, Some(p)) = (get, matches(path, "/users/:username/collection/folders/:folder/releases")) {
+        return collection_releases(state, request, &p[0], &p[1]);
+    }
+    if let (true, Some(p)) = (post, matches(path, "/users/:username/collection/folders/:folder/releases/:release")) {
+        return add_to_collection(state, user, &p[0], &p[1], &p[2]);
+    }
+    if let (true, Some(p)) = (delete,
+                              matches(path, "/users/:username/collection/folders/:folder/releases/:release/instances/:instance")) {
+        return remove_from_collection(state, user, &p[0], &p[1], &p[2], &p[3]);
+    }
+
+    FakeResponse::error(StatusCode::NotFound, "The requested resource was not found.")
+}
+
+fn parse_id<T: ::std::str::FromStr>(id: &str, what: &str) -> Result<T, FakeResponse> {
+    id.parse().map_err(|_| FakeResponse::not_found(what))
+}
+
+fn authenticated(user: Option<String>) -> Result<String, FakeResponse> {
+    user.ok_or_else(|| FakeResponse::error(StatusCode::Unauthorized, "You must authenticate to access this resource."))
+}
+
+fn owner(user: Option<String>, username: &str) -> Result<String, FakeResponse> {
+    let user = try!(authenticated(user));
+
+    if user != username {
+        return Err(FakeResponse::error(StatusCode::Forbidden, "You don't have permission to access this resource."));
+    }
+    Ok(user)
+}
+
+macro_rules! respond_with {
+    ($result:expr) => {
+        match $result {
+            Ok(value) => value,
+            Err(response) => return response,
+        }
+    }
+}
+
+fn entity<K: Ord + ::std::str::FromStr>(map: &::std::collections::BTreeMap<K, Value>, id: &str, what: &str) -> FakeResponse {
+    let id: K = respond_with!(parse_id(id, what));
+
+    match map.get(&id) {
+        Some(entity) => FakeResponse::ok(entity),
+        None => FakeResponse::not_found(what),
+    }
+}
+
+/// A page of `items` under `key`, along with the pagination of the API
+fn paginate(state: &State, request: &FakeRequest, items: Vec<Value>, key: &str) -> Value {
+    let page = max(request.param("page").and_then(|p| p.parse().ok()).unwrap_or(1usize), 1);
+    let per_page = min(max(request.param("per_page").and_then(|p| p.parse().ok()).unwrap_or(50usize), 1), 100);
+    let count = items.len();
+    let pages = max((count + per_page - 1) / per_page, 1);
+
+    let url = |page: usize| format!("{}{}?page={}&per_page={}", state.base_url, request.path, page, per_page);
+    let urls = if page < pages {
+        json!({ "next": (url(page + 1)), "last": (url(pages)) })
+    } else {
+        json!({})
+    };
+
+    let items: Vec<Value> = items.into_iter().skip((page - 1) * per_page).take(per_page).collect();
+
+    let mut result = json!({
+        "pagination": {
+            "page": page,
+            "pages": pages,
+            "per_page": per_page,
+            "items": count,
+            "urls": urls
+        }
+    });
+    if let Value::Object(ref mut map) = result {
+        map.insert(key.to_owned(), Value::Array(items));
+    }
+    result
+}
+
+/// Sets a field of an object
+fn set(object: &mut Value, key: &str, value: Value) {
+    if let Value::Object(ref mut map) = *object {
+        map.insert(key.to_owned(), value);
+    }
+}
+
+fn contains(text: &str, pattern: &str) -> bool {
+    text.to_lowercase().contains(&pattern.to_lowercase())
+}
+
+fn names(entities: &Value, field: &str) -> Vec<String> {
+    entities.as_array()
+            .map_or(Vec::new(), |a| a.iter().filter_map(|e| e[field].as_str().map(|s| s.to_owned())).collect())
+}
+
+fn digits(text: &str) -> String {
+    text.chars().filter(|c| c.is_digit(10)).collect()
+}
+
+fn text(value: &Value) -> String {
+    match *value {
+        Value::String(ref s) => s.clone(),
+        Value::Null => String::new(),
+        ref other => other.to_string(),
+    }
+}
+
+fn display_title(release: &Value) -> String {
+    let artists = names(&release["artists"], "name");
+
+    match artists.is_empty() {
+        true => text(&release["title"]),
+        false => format!("{} - {}", artists.join(", "), text(&release["title"])),
+    }
+}
+
+/// The summary of a release, as embedded in wants, collection and release lists
+fn basic_information(release: &Value) -> Value {
+    json!({
+        "id": (release["id"]),
+        "title": (release["title"]),
+        "year": (release["year"]),
+        "resource_url": (release["resource_url"]),
+        "thumb": (release["thumb"]),
+        "artists": (release["artists"]),
+        "labels": (release["labels"]),
+        "formats": (release["formats"]),
+        "genres": (release["genres"]),
+        "styles": (release["styles"])
+    })
+}
+
+fn release_summary(release: &Value) -> Value {
+    let labels = &release["labels"];
+
+    json!({
+        "id": (release["id"]),
+        "title": (release["title"]),
+        "status": (if release["status"].is_null() { json!("Accepted") } else { release["status"].clone() }),
+        "resource_url": (release["resource_url"]),
+        "year": (if release["year"].is_null() { json!(0) } else { release["year"].clone() }),
+        "thumb": (release["thumb"]),
+        "artist": (names(&release["artists"], "name").join(", ")),
+        "label": (names(labels, "name").join(", ")),
+        "catno": (names(labels, "catno").join(", ")),
+        "format": (names(&release["formats"], "name").join(", ")),
+        "type": "release"
+    })
+}
+
+fn artist_releases(state: &State, request: &FakeRequest, id: &str) -> FakeResponse {
+    let id: u64 = respond_with!(parse_id(id, "Artist"));
+    if !state.artists.contains_key(&(id as u32)) {
+        return FakeResponse::not_found("Artist");
+    }
+
+    let releases = state.releases
+                        .values()
+                        .filter(|r| r["artists"].as_array().map_or(false, |a| a.iter().any(|a| a["id"].as_u64() == Some(id))))
+                        .map(release_summary)
+                        .collect();
+    FakeResponse::ok(&paginate(state, request, releases, "releases"))
+}
+
+fn label_releases(state: &State, request: &FakeRequest, id: &str) -> FakeResponse {
+    let id: u64 = respond_with!(parse_id(id, "Label"));
+    if !state.labels.contains_key(&(id as u32)) {
+        return FakeResponse::not_found("Label");
+    }
+
+    let releases = state.releases
+                        .values()
+                        .filter(|r| r["labels"].as_array().map_or(false, |l| l.iter().any(|l| l["id"].as_u64() == Some(id))))
+                        .map(release_summary)
+                        .collect();
+    FakeResponse::ok(&paginate(state, request, releases, "releases"))
+}
+
+fn search(state: &State, request: &FakeRequest) -> FakeResponse {
+    if !request.authorized {
+        return FakeResponse::error(StatusCode::Unauthorized, "You must authenticate to access this resource.");
+    }
+
+    let param = |name: &str| request.param(name).and_then(|v| if v.is_empty() { None } else { Some(v) });
+    let wants = |search_type: &str| param("type").map_or(true, |t| t == search_type);
+    let only_releases = param("release_title").is_some() || param("label").is_some() ||
+                        param("catno").is_some() || param("barcode").is_some();
+    let only_music = only_releases || param("title").is_some() || param("artist").is_some() || param("year").is_some();
+
+    let mut results = Vec::new();
+
+    if wants("artist") && !only_music {
+        for artist in state.artists.values() {
+            if param("q").map_or(true, |q| contains(&text(&artist["name"]), q)) {
+                results.push(json!({
+                    "id": (artist["id"]),
+                    "type": "artist",
+                    "title": (artist["name"]),
+                    "resource_url": (artist["resource_url"]),
+                    "uri": (artist["uri"])
+                }));
+            }
+        }
+    }
+    if wants("label") && !only_music {
+        for label in state.labels.values() {
+            if param("q").map_or(true, |q| contains(&text(&label["name"]), q)) {
+                results.push(json!({
+                    "id": (label["id"]),
+                    "type": "label",
+                    "title": (label["name"]),
+                    "resource_url": (label["resource_url"]),
+                    "uri": (label["uri"])
+                }));
+            }
+        }
+    }
+
+    let music_matches = |entity: &Value| {
+        let title = display_title(entity);
+        param("q").map_or(true, |q| contains(&title, q)) &&
+        param("title").map_or(true, |t| contains(&title, t)) &&
+        param("artist").map_or(true, |a| names(&entity["artists"], "name").iter().any(|n| contains(n, a))) &&
+        param("year").map_or(true, |y| text(&entity["year"]) == y)
+    };
+
+    if wants("master") && !only_releases {
+        for master in state.masters.values().filter(|m| music_matches(m)) {
+            results.push(json!({
+                "id": (master["id"]),
+                "type": "master",
+                "title": (display_title(master)),
+                "year": (text(&master["year"])),
+                "resource_url": (master["resource_url"]),
+                "uri": (master["uri"])
+            }));
+        }
+    }
+    if wants("release") {
+        let release_matches = |release: &Value| {
+            let barcodes: Vec<String> = release["identifiers"].as_array()
+                                                              .map_or(Vec::new(), |i| {
+                                                                  i.iter()
+                                                                   .filter(|i| i["type"] == json!("Barcode"))
+                                                                   .map(|i| text(&i["value"]))
+                                                                   .collect()
+                                                              });
+            music_matches(release) &&
+            param("release_title").map_or(true, |t| contains(&text(&release["title"]), t)) &&
+            param("label").map_or(true, |l| names(&release["labels"], "name").iter().any(|n| contains(n, l))) &&
+            param("catno").map_or(true, |c| names(&release["labels"], "catno").iter().any(|n| contains(n, c))) &&
+            param("barcode").map_or(true, |b| barcodes.iter().any(|code| digits(code) == digits(b)))
+        };
+
+        for release in state.releases.values().filter(|r| release_matches(r)) {
+            let barcodes: Vec<Value> = release["identifiers"].as_array()
+                                                             .map_or(Vec::new(), |i| {
+                                                                 i.iter()
+                                                                  .filter(|i| i["type"] == json!("Barcode"))
+                                                                  .map(|i| i["value"].clone())
+                                                                  .collect()
+                                                             });
+            results.push(json!({
+                "id": (release["id"]),
+                "type": "release",
+                "title": (display_title(release)),
+                "year": (text(&release["year"])),
+                "country": (release["country"]),
+                "resource_url": (release["resource_url"]),
+                "uri": (release["uri"]),
+                "thumb": (release["thumb"]),
+                "catno": (names(&release["labels"], "catno").join(", ")),
+                "label": (names(&release["labels"], "name")),
+                "format": (names(&release["formats"], "name")),
+                "genre": (release["genres"]),
+                "style": (release["styles"]),
+                "barcode": barcodes
+            }));
+        }
+    }
+
+    FakeResponse::ok(&paginate(state, request, results, "results"))
+}
+
+fn user_lists(state: &State, request: &FakeRequest, username: &str) -> FakeResponse {
+    let lists = state.lists
+                     .values()
+                     .filter(|l| l["user"]["username"].as_str() == Some(username))
+                     .map(|l| json!({
+                         "id": (l["list_id"]),
+                         "name": (l["name"]),
+                         "resource_url": (l["resource_url"]),
+                         "uri": (l["url"]),
+                         "public": (l["public"]),
+                         "description": (l["description"]),
+                         "date_added": (l["created_ts"]),
+                         "date_changed": (l["modified_ts"])
+                     }))
+                     .collect();
+    FakeResponse::ok(&paginate(state, request, lists, "lists"))
+}
+
+// The fields of a listing only its seller sees
+const PRIVATE_LISTING_FIELDS: [&'static str; 4] = ["location", "external_id", "weight", "format_quantity"];
+
+fn seller_of(listing: &Value) -> Option<&str> {
+    listing["seller"]["username"].as_str()
+}
+
+fn public_listing(listing: &Value, user: &Option<String>) -> Value {
+    let mut listing = listing.clone();
+
+    if user.as_ref().map(|u| u.as_str()) != seller_of(&listing) {
+        if let Value::Object(ref mut map) = listing {
+            for field in PRIVATE_LISTING_FIELDS.iter() {
+                map.remove(*field);
+            }
+        }
+    }
+    listing
+}
+
+/// Orders values of the same kind, numbers by value and the rest by their text
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => text(a).cmp(&text(b)),
+    }
+}
+
+fn inventory(state: &State, request: &FakeRequest, user: Option<String>, username: &str) -> FakeResponse {
+    let is_owner = user.as_ref().map(|u| u.as_str()) == Some(username);
+
+    let mut listings: Vec<Value> = state.listings
+                                        .values()
+                                        .filter(|l| seller_of(l) == Some(username))
+                                        // Others only see what is for sale
+                                        .filter(|l| is_owner || l["status"] == json!("For Sale"))
+                                        .filter(|l| request.param("status").map_or(true, |s| text(&l["status"]) == s))
+                                        .map(|l| public_listing(l, &user))
+                                        .collect();
+
+    let key = |listing: &Value| -> Value {
+        match request.param("sort").unwrap_or("listed") {
+            "price" => listing["price"]["value"].clone(),
+            "item" => listing["release"]["description"].clone(),
+            "artist" => listing["release"]["artist"].clone(),
+            "catno" => listing["release"]["catalog_number"].clone(),
+            "status" => listing["status"].clone(),
+            "location" => listing["location"].clone(),
+            _ => listing["posted"].clone(),
+        }
+    };
+    listings.sort_by(|a, b| compare(&key(a), &key(b)));
+    if request.param("sort_order") == Some("desc") {
+        listings.reverse();
+    }
+
+    FakeResponse::ok(&paginate(state, request, listings, "listings"))
+}
+
+fn get_listing(state: &State, user: Option<String>, id: &str) -> FakeResponse {
+    let id: u64 = respond_with!(parse_id(id, "Listing"));
+
+    match state.listings.get(&id) {
+        Some(listing) if listing["status"] == json!("For Sale") ||
+                         user.as_ref().map(|u| u.as_str()) == seller_of(listing) => {
+            FakeResponse::ok(&public_listing(listing, &user))
+        },
+        _ => FakeResponse::not_found("Listing"),
+    }
+}
+
+/// Sets the fields of `listing` from the fields of a listing request
+fn apply_listing_fields(state: &State, listing: &mut Value, fields: &Value) -> Result<(), FakeResponse> {
+    if !fields["release_id"].is_null() {
+        let release_id = try!(fields["release_id"].as_u64().ok_or_else(|| FakeResponse::invalid("Invalid release_id")));
+        let release = try!(state.releases
+                                .get(&(release_id as u32))
+                                .ok_or_else(|| FakeResponse::invalid("Invalid release_id")));
+        let labels = &release["labels"];
+
+        let release = json!({
+            "id": release_id,
+            "resource_url": (format!("{}/releases/{}", state.base_url, release_id)),
+            "description": (display_title(release)),
+            "catalog_number": (names(labels, "catno").join(", ")),
+            "year": (release["year"]),
+            "thumb": (release["thumb"]),
+            "artist": (names(&release["artists"], "name").join(", ")),
+            "title": (release["title"]),
+            "format": (names(&release["formats"], "name").join(", "))
+        });
+        set(listing, "release", release);
+    }
+    if !fields["condition"].is_null() {
+        if serde_json::from_value::<MediaCondition>(fields["condition"].clone()).is_err() {
+            return Err(FakeResponse::invalid("Invalid condition"));
+        }
+        set(listing, "condition", fields["condition"].clone());
+    }
+    if !fields["price"].is_null() {
+        match fields["price"].as_f64() {
+            Some(price) if price > 0.0 => {
+                let currency = if listing["price"]["currency"].is_null() {
+                    json!("USD")
+                } else {
+                    listing["price"]["currency"].clone()
+                };
+                set(listing, "price", json!({ "value": price, "currency": currency }));
+            },
+            _ => return Err(FakeResponse::invalid("Price must be positive")),
+        }
+    }
+    if !fields["status"].is_null() {
+        match fields["status"].as_str() {
+            Some("For Sale") | Some("Draft") => set(listing, "status", fields["status"].clone()),
+            _ => return Err(FakeResponse::invalid("Invalid status")),
+        }
+    }
+    for field in ["sleeve_condition", "comments", "allow_offers", "external_id", "location", "weight", "format_quantity"].iter() {
+        if !fields[*field].is_null() {
+            set(listing, *field, fields[*field].clone());
+        }
+    }
+    Ok(())
+}
+
+/// Creates a listing of `username` from the fields of a listing request
+fn new_listing(state: &mut State, username: &str, fields: &Value) -> Result<u64, FakeResponse> {
+    for field in ["release_id", "condition", "price", "status"].iter() {
+        if fields[*field].is_null() {
+            return Err(FakeResponse::invalid(&format!("Missing {}", field)));
+        }
+    }
+
+    let id = state.next_id();
+    let mut listing = json!({
+        "id": id,
+        "resource_url": (format!("{}/marketplace/listings/{}", state.base_url, id)),
+        "uri": (format!("https://www.discogs.com/sell/item/{}", id)),
+        "posted": (timestamp()),
+        "ships_from": "Fakeland",
+        "seller": {
+            "username": username,
+            "resource_url": (format!("{}/users/{}", state.base_url, username))
+        }
+    });
+    try!(apply_listing_fields(state, &mut listing, fields));
+
+    state.listings.insert(id, listing);
+    Ok(id)
+}
+
+fn create_listing(state: &mut State, request: &FakeRequest, user: Option<String>) -> FakeResponse {
+    let user = respond_with!(authenticated(user));
+    let id = respond_with!(new_listing(state, &user, &request.json_body()));
+
+    FakeResponse::json(StatusCode::Created, &json!({
+        "listing_id": id,
+        "resource_url": (format!("{}/marketplace/listings/{}", state.base_url, id))
+    }))
+}
+
+/// The id of a listing of the user, for the writes
+fn own_listing(state: &State, user: Option<String>, id: &str) -> Result<u64, FakeResponse> {
+    let user = try!(authenticated(user));
+    let id: u64 = try!(parse_id(id, "Listing"));
+
+    match state.listings.get(&id) {
+        Some(listing) if seller_of(listing) == Some(user.as_str()) => Ok(id),
+        Some(_) => Err(FakeResponse::error(StatusCode::Forbidden, "You don't have permission to access this resource.")),
+        None => Err(FakeResponse::not_found("Listing")),
+    }
+}
+
+fn edit_listing(state: &mut State, request: &FakeRequest, user: Option<String>, id: &str) -> FakeResponse {
+    let id = respond_with!(own_listing(state, user, id));
+
+    let mut listing = state.listings[&id].clone();
+    respond_with!(apply_listing_fields(state, &mut listing, &request.json_body()));
+    state.listings.insert(id, listing);
+
+    FakeResponse::no_content()
+}
+
+fn delete_listing(state: &mut State, user: Option<String>, id: &str) -> FakeResponse {
+    let id = respond_with!(own_listing(state, user, id));
+    state.listings.remove(&id);
+
+    FakeResponse::no_content()
+}
+
+fn orders(state: &State, request: &FakeRequest, user: Option<String>) -> FakeResponse {
+    let user = respond_with!(authenticated(user));
+
+    let mut orders: Vec<Value> = state.orders
+                                      .values()
+                                      .filter(|o| o["seller"]["username"].as_str() == Some(user.as_str()))
+                                      .filter(|o| request.param("status").map_or(true, |s| text(&o["status"]) == s))
+                                      .cloned()
+                                      .collect();
+
+    let field = match request.param("sort") {
+        Some("created") => "created",
+        Some("status") => "status",
+        Some("last_activity") => "last_activity",
+        _ => "id",
+    };
+    orders.sort_by(|a, b| compare(&a[field], &b[field]));
+    if request.param("sort_order") == Some("desc") {
+        orders.reverse();
+    }
+
+    FakeResponse::ok(&paginate(state, request, orders, "orders"))
+}
+
+/// The id of an order the user sold or bought
+fn own_order(state: &State, user: Option<String>, id: &str) -> Result<String, FakeResponse> {
+    let user = try!(authenticated(user));
+
+    match state.orders.get(id) {
+        Some(order) if order["seller"]["username"].as_str() == Some(user.as_str()) ||
+                       order["buyer"]["username"].as_str() == Some(user.as_str()) => Ok(id.to_owned()),
+        Some(_) => Err(FakeResponse::error(StatusCode::Forbidden, "You don't have permission to access this resource.")),
+        None => Err(FakeResponse::not_found("Order")),
+    }
+}
+
+fn get_order(state: &State, user: Option<String>, id: &str) -> FakeResponse {
+    let id = respond_with!(own_order(state, user, id));
+    FakeResponse::ok(&state.orders[&id])
+}
+
+/// Moves `order` to `status`, following the status flow of the crate
+fn set_order_status(order: &mut Value, status: &Value) -> Result<(), FakeResponse> {
+    let next: OrderStatus = try!(serde_json::from_value(status.clone())
+                                     .map_err(|_| FakeResponse::invalid("Invalid status")));
+
+    if let Ok(current) = serde_json::from_value::<Order>(order.clone()) {
+        if !current.can_transition_to(&next) {
+            return Err(FakeResponse::invalid(&format!("Can not go from {} to {}",
+                                                      current.status.to_string(),
+                                                      next.to_string())));
+        }
+    }
+
+    set(order, "status", status.clone());
+    // The next statuses changed along, the documented flow applies from now on
+    if let Value::Object(ref mut map) = *order {
+        map.remove("next_status");
+    }
+    Ok(())
+}
+
+fn update_order(state: &mut State, request: &FakeRequest, user: Option<String>, id: &str) -> FakeResponse {
+    let id = respond_with!(own_order(state, user, id));
+    let update = request.json_body();
+    let mut order = state.orders[&id].clone();
+
+    if !update["status"].is_null() {
+        respond_with!(set_order_status(&mut order, &update["status"]));
+    }
+    if !update["shipping"].is_null() {
+        let shipping = match update["shipping"].as_f64() {
+            Some(shipping) if shipping >= 0.0 => shipping,
+            _ => return FakeResponse::invalid("Invalid shipping"),
+        };
+        let items: f64 = order["items"].as_array()
+                                       .map_or(0.0, |i| i.iter().filter_map(|i| i["price"]["value"].as_f64()).sum());
+        let currency = order["total"]["currency"].clone();
+
+        set(&mut order, "shipping", json!({ "value": shipping, "currency": (currency.clone()) }));
+        set(&mut order, "total", json!({ "value": (items + shipping), "currency": currency }));
+    }
+    set(&mut order, "last_activity", json!(timestamp()));
+
+    state.orders.insert(id, order.clone());
+    FakeResponse::ok(&order)
+}
+
+fn order_messages(state: &State, request: &FakeRequest, user: Option<String>, id: &str) -> FakeResponse {
+    let id = respond_with!(own_order(state, user, id));
+    let messages = state.messages.get(&id).cloned().unwrap_or_default();
+
+    FakeResponse::ok(&paginate(state, request, messages, "messages"))
+}
+
+fn send_order_message(state: &mut State, request: &FakeRequest, user: Option<String>, id: &str) -> FakeResponse {
+    let username = respond_with!(authenticated(user.clone()));
+    let id = respond_with!(own_order(state, user, id));
+    let body = request.json_body();
+
+    let text = match body["message"].as_str() {
+        Some(text) => text.to_owned(),
+        None => return FakeResponse::invalid("Missing message"),
+    };
+
+    let mut order = state.orders[&id].clone();
+    if !body["status"].is_null() {
+        respond_with!(set_order_status(&mut order, &body["status"]));
+    }
+    let now = timestamp();
+    set(&mut order, "last_activity", json!(now.clone()));
+    state.orders.insert(id.clone(), order);
+
+    let message = json!({
+        "message": text,
+        "subject": (format!("Discogs Order #{}", id)),
+        "timestamp": now,
+        "type": "message",
+        "from": {
+            "username": username,
+            "resource_url": (format!("{}/users/{}", state.base_url, username))
+        }
+    });
+    state.messages.entry(id).or_insert_with(Vec::new).push(message.clone());
+
+    FakeResponse::json(StatusCode::Created, &message)
+}
+
+/// A flat 8% of the price
+fn fee(price: &str, currency: &str) -> FakeResponse {
+    let price: f64 = match price.parse() {
+        Ok(price) if price > 0.0 => price,
+        _ => return FakeResponse::invalid("Invalid price"),
+    };
+
+    FakeResponse::ok(&json!({
+        "value": ((price * 8.0).round() / 100.0),
+        "currency": currency
+    }))
+}
+
+fn price_suggestions(state: &State, user: Option<String>, id: &str) -> FakeResponse {
+    respond_with!(authenticated(user));
+    let id: u32 = respond_with!(parse_id(id, "Release"));
+
+    if !state.releases.contains_key(&id) {
+        return FakeResponse::not_found("Release");
+    }
+    FakeResponse::ok(&state.price_suggestions.get(&id).cloned().unwrap_or(json!({})))
+}
+
+const CSV_COLUMNS: [&'static str; 17] = ["listing_id", "artist", "title", "label", "catno", "format", "release_id",
+                                         "status", "price", "listed", "comments", "media_condition",
+                                         "sleeve_condition", "accept_offer", "external_id", "weight", "format_quantity"];
+
+fn inventory_csv(state: &State, username: &str) -> String {
+    let header: Vec<String> = CSV_COLUMNS.iter().map(|c| c.to_string()).collect();
+    let mut csv = write_csv_record(&header);
+
+    for listing in state.listings.values().filter(|l| seller_of(l) == Some(username)) {
+        let release = &listing["release"];
+        let fields: Vec<String> = CSV_COLUMNS.iter()
+                                             .map(|column| match *column {
+                                                 "listing_id" => text(&listing["id"]),
+                                                 "artist" => text(&release["artist"]),
+                                                 "title" => text(&release["title"]),
+                                                 "label" => String::new(),
+                                                 "catno" => text(&release["catalog_number"]),
+                                                 "format" => text(&release["format"]),
+                                                 "release_id" => text(&release["id"]),
+                                                 "price" => listing["price"]["value"].as_f64()
+                                                                                       .map(|p| format!("{:.2}", p))
+                                                                                       .unwrap_or_default(),
+                                                 "listed" => text(&listing["posted"]),
+                                                 "media_condition" => text(&listing["condition"]),
+                                                 "accept_offer" => match listing["allow_offers"].as_bool() {
+                                                     Some(true) => "Y".to_owned(),
+                                                     Some(false) => "N".to_owned(),
+                                                     None => String::new(),
+                                                 },
+                                                 other => text(&listing[other]),
+                                             })
+                                             .collect();
+        csv.push_str(&write_csv_record(&fields));
+    }
+    csv
+}
+
+fn create_export(state: &mut State, user: Option<String>) -> FakeResponse {
+    let user = respond_with!(authenticated(user));
+    let id = state.next_id() as u32;
+    let now = timestamp();
+
+    let export = json!({
+        "id": id,
+        "status": "success",
+        "url": (format!("{}/inventory/export/{}", state.base_url, id)),
+        "download_url": (format!("{}/inventory/export/{}/download", state.base_url, id)),
+        "filename": (format!("{}-inventory-{}-{}.csv", user, &now[..10], id)),
+        "created_ts": (now.clone()),
+        "finished_ts": now
+    });
+    state.exports.insert(id, (user, export));
+
+    FakeResponse::json(StatusCode::Created, &json!({}))
+        .header("Location", &format!("{}/inventory/export/{}", state.base_url, id))
+}
+
+fn exports(state: &State, request: &FakeRequest, user: Option<String>) -> FakeResponse {
+    let user = respond_with!(authenticated(user));
+    let items = state.exports.values().filter(|&&(ref u, _)| *u == user).map(|&(_, ref e)| e.clone()).collect();
+
+    FakeResponse::ok(&paginate(state, request, items, "items"))
+}
+
+fn own_export(state: &State, user: Option<String>, id: &str) -> Result<(String, Value), FakeResponse> {
+    let user = try!(authenticated(user));
+    let id: u32 = try!(parse_id(id, "Export"));
+
+    match state.exports.get(&id) {
+        Some(&(ref owner, ref export)) if *owner == user => Ok((user, export.clone())),
+        _ => Err(FakeResponse::not_found("Export")),
+    }
+}
+
+fn get_export(state: &State, user: Option<String>, id: &str) -> FakeResponse {
+    let (_, export) = respond_with!(own_export(state, user, id));
+    FakeResponse::ok(&export)
+}
+
+fn download_export(state: &State, user: Option<String>, id: &str) -> FakeResponse {
+    let (user, _) = respond_with!(own_export(state, user, id));
+
+    FakeResponse {
+        status: StatusCode::Ok,
+        headers: vec![("Content-Type".to_owned(), "text/csv; charset=utf-8".to_owned())],
+        body: inventory_csv(state, &user).into_bytes(),
+    }
+}
+
+/// The first file of a multipart body
+fn multipart_file(request: &FakeRequest) -> Option<String> {
+    let boundary = match request.content_type.as_ref().and_then(|c| c.split("boundary=").nth(1)) {
+        Some(boundary) => boundary.trim_matches('"').to_owned(),
+        None => return None,
+    };
+    let body = String::from_utf8_lossy(&request.body).into_owned();
+
+    let part = match body.split(&format!("--{}", boundary)).nth(1) {
+        Some(part) => part.to_owned(),
+        None => return None,
+    };
+    part.find("\r\n\r\n").map(|i| {
+        let content = &part[i + 4..];
+        content.trim_right_matches("\r\n").to_owned()
+    })
+}
+
+/// Turns a csv row into the fields of a listing request
+fn listing_fields(row: &[(String, String)]) -> Value {
+    let mut fields = json!({});
+
+    for &(ref column, ref value) in row {
+        if value.is_empty() {
+            continue;
+        }
+        let field = match column.as_str() {
+            "listing_id" | "release_id" | "format_quantity" => {
+                value.parse::<u64>().map(|n| json!(n)).unwrap_or(json!(value))
+            },
+            "price" | "weight" => value.parse::<f64>().map(|n| json!(n)).unwrap_or(json!(value)),
+            "accept_offer" => json!(value == "Y"),
+            _ => json!(value),
+        };
+        let name = match column.as_str() {
+            "media_condition" => "condition",
+            "accept_offer" => "allow_offers",
+            other => other,
+        };
+        set(&mut fields, name, field);
+    }
+    fields
+}
+
+fn create_upload(state: &mut State, request: &FakeRequest, user: Option<String>, upload_type: &str) -> FakeResponse {
+    let user = respond_with!(authenticated(user));
+    if upload_type != "add" && upload_type != "change" && upload_type != "delete" {
+        return FakeResponse::not_found("Upload type");
+    }
+
+    let csv = match multipart_file(request) {
+        Some(csv) => csv,
+        None => return FakeResponse::invalid("Missing upload file"),
+    };
+    let records = match parse_csv(&csv) {
+        Ok(records) => records,
+        Err(_) => return FakeResponse::invalid("Invalid csv"),
+    };
+
+    let mut processed = 0;
+    let mut errors = Vec::new();
+
+    if let Some((header, rows)) = records.split_first() {
+        for (line, record) in rows.iter().enumerate() {
+            let row: Vec<(String, String)> = header.iter().cloned().zip(record.iter().cloned()).collect();
+            let fields = listing_fields(&row);
+            let listing_id = text(&fields["listing_id"]);
+
+            let result = match upload_type {
+                "add" => new_listing(state, &user, &fields).map(|_| ()),
+                "change" => own_listing(state, Some(user.clone()), &listing_id).and_then(|id| {
+                    let mut listing = state.listings[&id].clone();
+                    try!(apply_listing_fields(state, &mut listing, &fields));
+                    state.listings.insert(id, listing);
+                    Ok(())
+                }),
+                _ => own_listing(state, Some(user.clone()), &listing_id).map(|id| {
+                    state.listings.remove(&id);
+                }),
+            };
+
+            match result {
+                Ok(()) => processed += 1,
+                Err(response) => errors.push(format!("Line {}: {}",
+                                                     line + 2,
+                                                     serde_json::from_slice::<Value>(&response.body)
+                                                         .map(|m| text(&m["message"]))
+                                                         .unwrap_or_default())),
+            }
+        }
+    }
+
+    let id = state.next_id() as u32;
+    let now = timestamp();
+    let mut results = format!("{} listings processed.", processed);
+    for error in &errors {
+        results.push_str(&format!("\n{}", error));
+    }
+
+    let upload = json!({
+        "id": id,
+        "status": (if errors.is_empty() || processed > 0 { "success" } else { "failed" }),
+        "type": upload_type,
+        "results": results,
+        "filename": "inventory.csv",
+        "created_ts": (now.clone()),
+        "finished_ts": now
+    });
+    state.uploads.insert(id, (user, upload));
+
+    FakeResponse::json(StatusCode::Created, &json!({}))
+        .header("Location", &format!("{}/inventory/upload/{}", state.base_url, id))
+}
+
+fn uploads(state: &State, request: &FakeRequest, user: Option<String>) -> FakeResponse {
+    let user = respond_with!(authenticated(user));
+    let items = state.uploads.values().filter(|&&(ref u, _)| *u == user).map(|&(_, ref u)| u.clone()).collect();
+
+    FakeResponse::ok(&paginate(state, request, items, "items"))
+}
+
+fn get_upload(state: &State, user: Option<String>, id: &str) -> FakeResponse {
+    let user = respond_with!(authenticated(user));
+    let id: u32 = respond_with!(parse_id(id, "Upload"));
+
+    match state.uploads.get(&id) {
+        Some(&(ref owner, ref upload)) if *owner == user => FakeResponse::ok(upload),
+        _ => FakeResponse::not_found("Upload"),
+    }
+}
+
+fn wants(state: &State, request: &FakeRequest, username: &str) -> FakeResponse {
+    let wants = state.wantlists.get(username).cloned().unwrap_or_default();
+    FakeResponse::ok(&paginate(state, request, wants, "wants"))
+}
+
+fn put_want(state: &mut State,
+            request: &FakeRequest,
+            user: Option<String>,
+            username: &str,
+            release: &str,
+            create: bool)
+            -> FakeResponse {
+    respond_with!(owner(user, username));
+    let id: u32 = respond_with!(parse_id(release, "Release"));
+
+    let release = match state.releases.get(&id) {
+        Some(release) => release.clone(),
+        None => return FakeResponse::not_found("Release"),
+    };
+
+    let wants = state.wantlists.entry(username.to_owned()).or_insert_with(Vec::new);
+    let position = wants.iter().position(|w| w["id"].as_u64() == Some(id as u64));
+
+    let mut want = match position {
+        Some(i) => wants[i].clone(),
+        None if create => json!({
+            "id": id,
+            "rating": 0,
+            "notes": "",
+            "date_added": (timestamp()),
+            "basic_information": (basic_information(&release))
+        }),
+        None => return FakeResponse::not_found("Want"),
+    };
+
+    if let Some(notes) = request.field("notes") {
+        set(&mut want, "notes", json!(text(&notes)));
+    }
+    if let Some(rating) = request.field("rating") {
+        match text(&rating).parse::<u8>() {
+            Ok(rating) if rating <= 5 => set(&mut want, "rating", json!(rating)),
+            _ => return FakeResponse::invalid("Rating must be between 0 and 5"),
+        }
+    }
+
+    match position {
+        Some(i) => wants[i] = want.clone(),
+        None => wants.push(want.clone()),
+    }
+
+    let status = if position.is_none() { StatusCode::Created } else { StatusCode::Ok };
+    FakeResponse::json(status, &want)
+}
+
+fn delete_want(state: &mut State, user: Option<String>, username: &str, release: &str) -> FakeResponse {
+    respond_with!(owner(user, username));
+    let id: u64 = respond_with!(parse_id(release, "Release"));
+
+    let wants = state.wantlists.entry(username.to_owned()).or_insert_with(Vec::new);
+    let before = wants.len();
+    wants.retain(|w| w["id"].as_u64() != Some(id));
+
+    if wants.len() == before {
+        return FakeResponse::not_found("Want");
+    }
+    FakeResponse::no_content()
+}
+
+fn folders(state: &State, username: &str) -> FakeResponse {
+    let instances = state.collections.get(username).cloned().unwrap_or_default();
+
+    let mut ids: Vec<u64> = instances.iter().filter_map(|i| i["folder_id"].as_u64()).filter(|&f| f > 1).collect();
+    ids.sort();
+    ids.dedup();
+
+    let folder = |id: u64, name: String, count: usize| json!({
+        "id": id,
+        "name": name,
+        "count": count,
+        "resource_url": (format!("{}/users/{}/collection/folders/{}", state.base_url, username, id))
+    });
+
+    let in_folder = |id: u64| instances.iter().filter(|i| i["folder_id"].as_u64() == Some(id)).count();
+    let mut folders = vec![folder(0, "All".to_owned(), instances.len()),
+                           folder(1, "Uncategorized".to_owned(), in_folder(1))];
+    for id in ids {
+        folders.push(folder(id, format!("Folder {}", id), in_folder(id)));
+    }
+
+    FakeResponse::ok(&json!({ "folders": folders }))
+}
+
+fn collection_releases(state: &State, request: &FakeRequest, username: &str, folder: &str) -> FakeResponse {
+    let folder: u64 = respond_with!(parse_id(folder, "Folder"));
+
+    let releases = state.collections
+                        .get(username)
+                        .cloned()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|i| folder == 0 || i["folder_id"].as_u64() == Some(folder))
+                        .collect();
+    FakeResponse::ok(&paginate(state, request, releases, "releases"))
+}
+
+fn add_to_collection(state: &mut State, user: Option<String>, username: &str, folder: &str, release: &str) -> FakeResponse {
+    respond_with!(owner(user, username));
+    let folder: u64 = respond_with!(parse_id(folder, "Folder"));
+    let id: u32 = respond_with!(parse_id(release, "Release"));
+
+    if folder == 0 {
+        return FakeResponse::invalid("Releases can not be added to the All folder");
+    }
+    let release = match state.releases.get(&id) {
+        Some(release) => basic_information(release),
+        None => return FakeResponse::not_found("Release"),
+    };
+
+    let instance_id = state.next_id();
+    let resource_url = format!("{}/users/{}/collection/folders/{}/releases/{}/instances/{}",
+                               state.base_url, username, folder, id, instance_id);
+
+    state.collections.entry(username.to_owned()).or_insert_with(Vec::new).push(json!({
+        "id": id,
+        "instance_id": instance_id,
+        "folder_id": folder,
+        "rating": 0,
+        "date_added": (timestamp()),
+        "basic_information": release
+    }));
+
+    FakeResponse::json(StatusCode::Created, &json!({
+        "instance_id": instance_id,
+        "resource_url": resource_url
+    }))
+}
+
+fn remove_from_collection(state: &mut State,
+                          user: Option<String>,
+                          username: &str,
+                          folder: &str,
+                          release: &str,
+                          instance: &str)
+                          -> FakeResponse {
+    respond_with!(owner(user, username));
+    let folder: u64 = respond_with!(parse_id(folder, "Folder"));
+    let id: u64 = respond_with!(parse_id(release, "Release"));
+    let instance: u64 = respond_with!(parse_id(instance, "Instance"));
+
+    let instances = state.collections.entry(username.to_owned()).or_insert_with(Vec::new);
+    let before = instances.len();
+    instances.retain(|i| {
+        !(i["id"].as_u64() == Some(id) && i["instance_id"].as_u64() == Some(instance) &&
+          (folder == 0 || i["folder_id"].as_u64() == Some(folder)))
+    });
+
+    if instances.len() == before {
+        return FakeResponse::not_found("Instance");
+    }
+    FakeResponse::no_content()
+}
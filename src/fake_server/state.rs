@@ -0,0 +1,305 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use query::QueryError;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// An error to answer instead of handling the request
+#[derive(Debug, Clone)]
+pub struct InjectedError {
+    pub path_prefix: String,
+    pub status: u16,
+    // How many more requests fail
+    pub remaining: u32,
+}
+
+/// Everything the fake server knows, changed by the write requests
+#[derive(Debug, Default)]
+pub struct State {
+    // The url the server is reached at, used to build resource urls
+    pub base_url: String,
+
+    pub artists: BTreeMap<u32, Value>,
+    pub labels: BTreeMap<u32, Value>,
+    pub masters: BTreeMap<u32, Value>,
+    pub releases: BTreeMap<u32, Value>,
+    pub lists: BTreeMap<u32, Value>,
+    pub listings: BTreeMap<u64, Value>,
+    pub orders: BTreeMap<String, Value>,
+    pub messages: HashMap<String, Vec<Value>>,
+    pub price_suggestions: HashMap<u32, Value>,
+    pub wantlists: BTreeMap<String, Vec<Value>>,
+    pub collections: BTreeMap<String, Vec<Value>>,
+    pub exports: BTreeMap<u32, (String, Value)>,
+    pub uploads: BTreeMap<u32, (String, Value)>,
+
+    // Tokens and the user they authenticate
+    pub users: HashMap<String, String>,
+
+    pub next_id: u64,
+
+    pub rate_limit: Option<u32>,
+    pub recent_requests: VecDeque<Instant>,
+    pub errors: Vec<InjectedError>,
+
+    // Method and path with query of every request, in order
+    pub requests: Vec<(String, String)>,
+}
+
+impl State {
+    pub fn new() -> State {
+        State {
+            next_id: 1000000,
+            ..State::default()
+        }
+    }
+
+    /// Adds the entities of a fixture document
+    ///
+    /// Every key is optional: `artists`, `labels`, `masters`, `releases`,
+    /// `lists`, `listings` and `orders` are arrays of the objects the API
+    /// returns, `messages`, `wantlists` and `collections` map an order id or
+    /// a username to an array, `price_suggestions` maps a release id to its
+    /// suggestions and `users` maps a token to a username.
+    pub fn seed(&mut self, fixtures: &Value) -> Result<(), QueryError> {
+        for artist in array(fixtures, "artists") {
+            let id = try!(id_of(artist));
+            self.artists.insert(id, artist.clone());
+        }
+        for label in array(fixtures, "labels") {
+            let id = try!(id_of(label));
+            self.labels.insert(id, label.clone());
+        }
+        for master in array(fixtures, "masters") {
+            let id = try!(id_of(master));
+            self.masters.insert(id, master.clone());
+        }
+        for release in array(fixtures, "releases") {
+            let id = try!(id_of(release));
+            self.releases.insert(id, release.clone());
+        }
+        for list in array(fixtures, "lists") {
+            let id = try!(list["list_id"].as_u64().ok_or_else(|| invalid("A list without a list_id")));
+            self.lists.insert(id as u32, list.clone());
+        }
+        for listing in array(fixtures, "listings") {
+            let id = try!(id_of(listing));
+            self.listings.insert(id as u64, listing.clone());
+        }
+        for order in array(fixtures, "orders") {
+            let id = try!(order["id"].as_str().ok_or_else(|| invalid("An order without an id")));
+            self.orders.insert(id.to_owned(), order.clone());
+        }
+
+        if let Some(messages) = fixtures["messages"].as_object() {
+            for (order, messages) in messages {
+                self.messages.insert(order.clone(), messages.as_array().cloned().unwrap_or_default());
+            }
+        }
+        if let Some(wantlists) = fixtures["wantlists"].as_object() {
+            for (username, wants) in wantlists {
+                self.wantlists.insert(username.clone(), wants.as_array().cloned().unwrap_or_default());
+            }
+        }
+        if let Some(collections) = fixtures["collections"].as_object() {
+            for (username, releases) in collections {
+                self.collections.insert(username.clone(), releases.as_array().cloned().unwrap_or_default());
+            }
+        }
+        if let Some(suggestions) = fixtures["price_suggestions"].as_object() {
+            for (release, suggestion) in suggestions {
+                let id = try!(release.parse().map_err(|_| invalid("A price suggestion for an invalid release id")));
+                self.price_suggestions.insert(id, suggestion.clone());
+            }
+        }
+        if let Some(users) = fixtures["users"].as_object() {
+            for (token, username) in users {
+                let username = try!(username.as_str().ok_or_else(|| invalid("A user that is not a string")));
+                self.users.insert(token.clone(), username.to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    /// A fresh id for the created listings, exports, uploads and collection instances
+    pub fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Whether one more request fits in the rate limit, counting it if so
+    pub fn within_rate_limit(&mut self, now: Instant) -> bool {
+        let limit = match self.rate_limit {
+            Some(limit) => limit as usize,
+            None => return true,
+        };
+
+        while self.recent_requests.front().map_or(false, |&t| now.duration_since(t) >= Duration::from_secs(60)) {
+            self.recent_requests.pop_front();
+        }
+
+        if self.recent_requests.len() >= limit {
+            return false;
+        }
+        self.recent_requests.push_back(now);
+        true
+    }
+
+    /// The status of the injected error for `path`, if any is left
+    pub fn injected_error(&mut self, path: &str) -> Option<u16> {
+        let status = match self.errors.iter_mut().find(|e| e.remaining > 0 && path.starts_with(e.path_prefix.as_str())) {
+            Some(error) => {
+                error.remaining -= 1;
+                Some(error.status)
+            },
+            None => None,
+        };
+
+        self.errors.retain(|e| e.remaining > 0);
+        status
+    }
+
+    /// Everything in the server, in the fixture format
+    pub fn snapshot(&self) -> Value {
+        let values = |map: &BTreeMap<u32, Value>| Value::Array(map.values().cloned().collect());
+        let artists = values(&self.artists);
+        let labels = values(&self.labels);
+        let masters = values(&self.masters);
+        let releases = values(&self.releases);
+        let lists = values(&self.lists);
+        let listings = Value::Array(self.listings.values().cloned().collect());
+        let orders = Value::Array(self.orders.values().cloned().collect());
+        let price_suggestions: BTreeMap<String, Value> = self.price_suggestions
+                                                             .iter()
+                                                             .map(|(id, s)| (id.to_string(), s.clone()))
+                                                             .collect();
+
+        json!({
+            "artists": artists,
+            "labels": labels,
+            "masters": masters,
+            "releases": releases,
+            "lists": lists,
+            "listings": listings,
+            "orders": orders,
+            "messages": (self.messages),
+            "wantlists": (self.wantlists),
+            "collections": (self.collections),
+            "price_suggestions": price_suggestions,
+            "users": (self.users)
+        })
+    }
+}
+
+fn array<'a>(fixtures: &'a Value, key: &str) -> Vec<&'a Value> {
+    fixtures[key].as_array().map_or(Vec::new(), |a| a.iter().collect())
+}
+
+fn id_of(entity: &Value) -> Result<u32, QueryError> {
+    entity["id"].as_u64()
+                .map(|id| id as u32)
+                .ok_or_else(|| invalid(&format!("An entity without an id: {}", entity)))
+}
+
+fn invalid(reason: &str) -> QueryError {
+    QueryError::InvalidRequestError {
+        reason: format!("Invalid fixtures, {}", reason)
+    }
+}
+
+/// The current time, formatted like the timestamps of the API
+pub fn timestamp() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    format_timestamp(secs)
+}
+
+/// Formats seconds since the unix epoch as `2017-03-01T12:00:00-00:00`
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rest = secs % 86400;
+
+    // Days to a civil date, from Howard Hinnant's date algorithms
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}-00:00",
+            year, month, day, rest / 3600, rest % 3600 / 60, rest % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00-00:00".to_string());
+        assert_eq!(format_timestamp(1488369600), "2017-03-01T12:00:00-00:00".to_string());
+    }
+
+    #[test]
+    fn test_seed() {
+        let mut state = State::new();
+        state.seed(&json!({
+            "artists": [{ "id": 1, "name": "The Persuader", "resource_url": "" }],
+            "orders": [{ "id": "1-1" }],
+            "price_suggestions": { "1": {} },
+            "users": { "TOKEN": "seller" }
+        })).ok().unwrap();
+
+        assert_eq!(state.artists[&1]["name"], json!("The Persuader"));
+        assert!(state.orders.contains_key("1-1"));
+        assert!(state.price_suggestions.contains_key(&1));
+        assert_eq!(state.users["TOKEN"], "seller".to_string());
+
+        assert!(state.seed(&json!({ "releases": [{ "title": "No id" }] })).is_err());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut state = State::new();
+        let start = Instant::now();
+        state.rate_limit = Some(2);
+
+        assert!(state.within_rate_limit(start));
+        assert!(state.within_rate_limit(start));
+        assert!(!state.within_rate_limit(start));
+        assert!(state.within_rate_limit(start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_injected_errors() {
+        let mut state = State::new();
+        state.errors.push(InjectedError {
+            path_prefix: "/releases".to_string(),
+            status: 500,
+            remaining: 1,
+        });
+
+        assert_eq!(state.injected_error("/artists/1"), None);
+        assert_eq!(state.injected_error("/releases/1"), Some(500));
+        assert_eq!(state.injected_error("/releases/1"), None);
+    }
+}
@@ -26,6 +26,17 @@ extern crate serde;
 #[macro_use] extern crate serde_derive;
 #[macro_use] extern crate itertools;
 
+#[cfg(feature = "dump")]
+extern crate flate2;
+#[cfg(feature = "dump")]
+extern crate xml;
+#[cfg(feature = "sqlite")]
+extern crate rusqlite;
+#[cfg(feature = "async")]
+extern crate futures;
+#[cfg(feature = "async")]
+extern crate futures_cpupool;
+
 #[cfg(test)]
 extern crate mockito;
 
@@ -35,5 +46,15 @@ extern crate test;
 pub mod data_structures;
 pub mod query;
 pub mod discogs;
+pub mod reconcile;
+pub mod poller;
+pub mod report;
+pub mod backend;
+pub mod proxy;
+pub mod batch;
+#[cfg(feature = "dump")]
+pub mod dump;
+#[cfg(feature = "fake_server")]
+pub mod fake_server;
 
 pub use discogs::*;
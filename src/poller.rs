@@ -0,0 +1,518 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Notifications about the orders of the authenticated seller
+//!
+//! Discogs has no webhooks, so `OrderPoller` periodically lists the
+//! orders by last activity and compares them with what it saw on the
+//! previous poll. What it has seen is kept in a small json state file
+//! so that nothing is reported twice across restarts.
+
+use data_structures::*;
+use discogs::Discogs;
+use query::*;
+use serde_json;
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Something that happened to an order since the previous poll
+#[derive(Debug, Clone)]
+pub enum OrderEvent {
+    NewOrder {
+        order: Order
+    },
+    StatusChanged {
+        order: Order,
+        from: OrderStatus
+    },
+    NewMessage {
+        order_id: String,
+        message: OrderMessage
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SeenOrder {
+    status: OrderStatus,
+    last_activity: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct PollerState {
+    last_activity: Option<String>,
+    #[serde(default)]
+    orders: HashMap<String, SeenOrder>,
+}
+
+/// Polls the orders of the authenticated seller
+///
+/// The first poll only records the current orders, later polls
+/// report what changed since through `OrderEvent`s, oldest first.
+/// Requests are spaced according to the rate limit of the client.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use discogs::Discogs;
+/// use discogs::poller::OrderPoller;
+/// use std::sync::mpsc::channel;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let mut client = Discogs::new("USER_AGENT");
+/// client.token("USER_TOKEN");
+///
+/// let mut poller = OrderPoller::new("orders.json").ok().unwrap();
+///
+/// let (sender, receiver) = channel();
+/// thread::spawn(move || {
+///     for event in receiver {
+///         println!("{:?}", event);
+///     }
+/// });
+///
+/// loop {
+///     if let Err(e) = poller.poll(&client, |event| sender.send(event).unwrap()) {
+///         println!("Poll failed: {:?}", e);
+///     }
+///     thread::sleep(Duration::from_secs(300));
+/// }
+/// ```
+pub struct OrderPoller {
+    state_path: PathBuf,
+    state: PollerState,
+}
+
+impl OrderPoller {
+    /// Creates a poller keeping its state in `state_path`,
+    /// resuming from it if the file exists
+    pub fn new<P: AsRef<Path>>(state_path: P) -> Result<OrderPoller, QueryError> {
+        let state_path = state_path.as_ref().to_path_buf();
+
+        let state = if state_path.exists() {
+            let mut text = String::new();
+            try!(File::open(&state_path)
+                     .and_then(|mut file| file.read_to_string(&mut text))
+                     .map_err(|e| QueryError::TextReadError { error: e }));
+            try!(serde_json::from_str(&text)
+                     .map_err(|e| QueryError::JsonDecodeError { serde_err: Some(e) }))
+        } else {
+            PollerState::default()
+        };
+
+        Ok(OrderPoller {
+            state_path: state_path,
+            state: state,
+        })
+    }
+
+    /// The last activity seen, `None` before the first poll
+    pub fn last_activity(&self) -> Option<&str> {
+        self.state.last_activity.as_ref().map(|a| a.as_str())
+    }
+
+    /// Looks for changes since the previous poll and passes them to `callback`
+    ///
+    /// Returns the number of events. The state file is written even when
+    /// the poll fails halfway, so the events already passed to `callback`
+    /// are not repeated.
+    pub fn poll<F>(&mut self, client: &Discogs, mut callback: F) -> Result<usize, QueryError>
+        where F: FnMut(OrderEvent)
+    {
+        let first_poll = self.state.last_activity.is_none();
+        let mut count = 0;
+
+        let result = self.changed_orders(client).and_then(|changed| {
+            // Oldest first, so the cursor only moves past reported orders
+            for order in changed.into_iter().rev() {
+                let events = try!(self.events_for(client, &order, first_poll));
+                count += events.len();
+
+                self.state.orders.insert(order.id.clone(), SeenOrder {
+                    status: order.status.clone(),
+                    last_activity: order.last_activity.clone(),
+                });
+                if is_after(&order.last_activity, &self.state.last_activity) {
+                    self.state.last_activity = order.last_activity.clone();
+                }
+
+                for event in events {
+                    callback(event);
+                }
+            }
+            Ok(())
+        });
+
+        try!(self.save());
+        result.map(|_| count)
+    }
+
+    /// Lists the orders active since the previous poll, newest first
+    fn changed_orders(&mut self, client: &Discogs) -> Result<Vec<Order>, QueryError> {
+        let mut changed = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let orders = try!(client.orders()
+                                    .sort(OrderSort::LastActivity, SortOrder::Descending)
+                                    .pagination(page, 50)
+                                    .get());
+            let pages = orders.pagination.pages;
+
+            for order in orders.orders {
+                if !self.is_newer(&order.last_activity) {
+                    return Ok(changed);
+                }
+                changed.push(order);
+            }
+
+            if page >= pages {
+                return Ok(changed);
+            }
+            page += 1;
+        }
+    }
+
+    fn is_newer(&self, last_activity: &Option<String>) -> bool {
+        match self.state.last_activity {
+            None => true,
+            Some(_) => is_after(last_activity, &self.state.last_activity),
+        }
+    }
+
+    fn events_for(&mut self,
+                  client: &Discogs,
+                  order: &Order,
+                  first_poll: bool) -> Result<Vec<OrderEvent>, QueryError> {
+        if first_poll {
+            return Ok(Vec::new());
+        }
+
+        let seen = match self.state.orders.get(&order.id) {
+            Some(seen) => seen.clone(),
+            None => return Ok(vec![OrderEvent::NewOrder { order: order.clone() }]),
+        };
+
+        let mut events = Vec::new();
+
+        if seen.status != order.status {
+            events.push(OrderEvent::StatusChanged {
+                order: order.clone(),
+                from: seen.status,
+            });
+        }
+
+        let mut messages = try!(self.messages_since(client, &order.id, &seen.last_activity));
+        sort_by_time(&mut messages);
+
+        for message in messages {
+            events.push(OrderEvent::NewMessage {
+                order_id: order.id.clone(),
+                message: message,
+            });
+        }
+
+        Ok(events)
+    }
+
+    fn messages_since(&mut self,
+                      client: &Discogs,
+                      order_id: &str,
+                      since: &Option<String>) -> Result<Vec<OrderMessage>, QueryError> {
+        let mut messages = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let result = try!(client.order(order_id)
+                                    .pagination(page, 50)
+                                    .get_messages());
+            let pages = result.pagination.pages;
+
+            messages.extend(result.messages.into_iter().filter(|m| is_after(&m.timestamp, since)));
+
+            if page >= pages {
+                return Ok(messages);
+            }
+            page += 1;
+        }
+    }
+
+    fn save(&self) -> Result<(), QueryError> {
+        let text = try!(serde_json::to_string(&self.state)
+                            .map_err(|e| QueryError::JsonDecodeError { serde_err: Some(e) }));
+
+        // Written aside first so that a crash never leaves half a state behind
+        let mut partial = self.state_path.clone().into_os_string();
+        partial.push(".partial");
+
+        File::create(&partial)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .and_then(|_| fs::rename(&partial, &self.state_path))
+            .map_err(|e| QueryError::TextReadError { error: e })
+    }
+}
+
+/// Sorts `messages` oldest first, comparing their timestamps as instants
+fn sort_by_time(messages: &mut [OrderMessage]) {
+    messages.sort_by_key(|m| (m.timestamp.as_ref().and_then(|t| utc_seconds(t)), m.timestamp.clone()));
+}
+
+/// Whether `timestamp` is later than `since`
+///
+/// The offset of the timestamps changes with daylight saving time, so they
+/// are compared as instants, and only as strings when they can not be parsed
+fn is_after(timestamp: &Option<String>, since: &Option<String>) -> bool {
+    match (timestamp, since) {
+        (&Some(ref timestamp), &Some(ref since)) => {
+            match (utc_seconds(timestamp), utc_seconds(since)) {
+                (Some(t), Some(s)) => t > s,
+                _ => timestamp > since,
+            }
+        },
+        (&Some(_), &None) => true,
+        _ => false,
+    }
+}
+
+/// Seconds since the unix epoch of a timestamp like `2017-03-01T12:00:00-08:00`
+fn utc_seconds(timestamp: &str) -> Option<i64> {
+    if timestamp.len() < 19 || !timestamp.is_char_boundary(19) {
+        return None;
+    }
+    let (datetime, offset) = timestamp.split_at(19);
+
+    let fields = match datetime.split(|c| c == '-' || c == 'T' || c == ':')
+                               .map(|field| field.parse::<i64>().ok())
+                               .collect::<Option<Vec<i64>>>() {
+        Some(ref fields) if fields.len() == 6 => fields.clone(),
+        _ => return None,
+    };
+
+    let offset = match offset {
+        "" | "Z" => 0,
+        _ => {
+            let sign = if offset.starts_with('+') {
+                1
+            } else if offset.starts_with('-') {
+                -1
+            } else {
+                return None;
+            };
+            let digits: String = offset[1..].chars().filter(|&c| c != ':').collect();
+            match digits.parse::<i64>() {
+                Ok(value) if digits.len() == 4 => sign * (value / 100 * 3600 + value % 100 * 60),
+                _ => return None,
+            }
+        },
+    };
+
+    // A civil date to days, from Howard Hinnant's date algorithms
+    let (year, month, day) = (fields[0], fields[1], fields[2]);
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    Some(days * 86400 + fields[3] * 3600 + fields[4] * 60 + fields[5] - offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_structures::*;
+    use discogs::*;
+    use mockito::mock;
+    use serde_json;
+    use serde_json::to_string;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    const ORDERS_PATH: &'static str = "/marketplace/orders?page=1&per_page=50&sort=last_activity&sort_order=desc";
+
+    fn state_path(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("discogs-rs-poller-{}.json", name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn order_json(id: &str, status: &str, last_activity: &str) -> serde_json::Value {
+        json!({
+            "id": id,
+            "resource_url": format!("https://api.discogs.com/marketplace/orders/{}", id),
+            "status": status,
+            "total": {
+                "currency": "USD",
+                "value": 44.5
+            },
+            "last_activity": last_activity,
+            "items": []
+        })
+    }
+
+    fn orders_body(orders: Vec<serde_json::Value>) -> String {
+        to_string(&json!({
+            "pagination": {
+                "per_page": 50,
+                "items": orders.len(),
+                "page": 1,
+                "urls": {},
+                "pages": 1
+            },
+            "orders": orders
+        })).unwrap()
+    }
+
+    fn client() -> Discogs {
+        let mut client = Discogs::new("USER_AGENT");
+        client.token("USER_TOKEN").rate_limit(6000);
+        client
+    }
+
+    #[test]
+    fn test_timestamps_compare_as_instants() {
+        assert_eq!(utc_seconds("2017-03-01T12:00:00-00:00"), Some(1488369600));
+        assert_eq!(utc_seconds("2017-03-01T04:00:00-08:00"), Some(1488369600));
+        assert_eq!(utc_seconds("2017-03-01T12:00:00Z"), Some(1488369600));
+        assert_eq!(utc_seconds("yesterday"), None);
+
+        // 01:10 PST is after 01:30 PDT, the night daylight saving time ends
+        let pdt = Some("2017-11-05T01:30:00-07:00".to_string());
+        let pst = Some("2017-11-05T01:10:00-08:00".to_string());
+        assert!(is_after(&pst, &pdt));
+        assert!(!is_after(&pdt, &pst));
+        assert!(is_after(&pdt, &None));
+        assert!(!is_after(&None, &pdt));
+    }
+
+    #[test]
+    fn test_messages_are_sorted_as_instants() {
+        let message = |timestamp: &str| -> OrderMessage {
+            serde_json::from_value(json!({ "message": timestamp, "timestamp": timestamp })).unwrap()
+        };
+        let mut messages = vec![message("2017-11-05T01:10:00-08:00"), message("2017-11-05T01:30:00-07:00")];
+
+        sort_by_time(&mut messages);
+        assert_eq!(messages[0].message, "2017-11-05T01:30:00-07:00".to_string());
+    }
+
+    #[test]
+    fn test_first_poll_records_orders() {
+        let path = state_path("first");
+
+        mock("GET", ORDERS_PATH)
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(orders_body(vec![
+                order_json("1-2", "New Order", "2017-01-01T12:00:00-08:00"),
+                order_json("1-1", "Shipped", "2017-01-01T11:00:00-08:00"),
+            ]).as_str())
+            .create_for(|| {
+                let client = client();
+                let mut poller = OrderPoller::new(&path).ok().unwrap();
+                let mut events = Vec::new();
+
+                assert_eq!(poller.poll(&client, |e| events.push(e)).ok(), Some(0));
+                assert!(events.is_empty());
+                assert_eq!(poller.last_activity(), Some("2017-01-01T12:00:00-08:00"));
+
+                // The cursor survives a restart
+                let poller = OrderPoller::new(&path).ok().unwrap();
+                assert_eq!(poller.last_activity(), Some("2017-01-01T12:00:00-08:00"));
+            });
+    }
+
+    #[test]
+    fn test_poll_reports_changes() {
+        let path = state_path("changes");
+        File::create(&path).unwrap().write_all(to_string(&json!({
+            "last_activity": "2017-01-01T10:00:00-08:00",
+            "orders": {
+                "1-1": {
+                    "status": "New Order",
+                    "last_activity": "2017-01-01T09:00:00-08:00"
+                }
+            }
+        })).unwrap().as_bytes()).unwrap();
+
+        mock("GET", ORDERS_PATH)
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(orders_body(vec![
+                order_json("1-2", "New Order", "2017-01-01T12:00:00-08:00"),
+                order_json("1-1", "Payment Received", "2017-01-01T11:00:00-08:00"),
+                order_json("1-0", "Shipped", "2017-01-01T08:00:00-08:00"),
+            ]).as_str())
+            .create_for(|| {
+                mock("GET", "/marketplace/orders/1-1/messages?page=1&per_page=50")
+                    .with_status(200)
+                    .with_header("content-type", "text/json")
+                    .with_body(to_string(&json!({
+                        "pagination": {
+                            "per_page": 50,
+                            "items": 2,
+                            "page": 1,
+                            "urls": {},
+                            "pages": 1
+                        },
+                        "messages": [{
+                            "message": "Paid, thanks",
+                            "timestamp": "2017-01-01T11:00:00-08:00",
+                            "type": "message"
+                        }, {
+                            "message": "Order created",
+                            "timestamp": "2017-01-01T09:00:00-08:00",
+                            "type": "status"
+                        }]
+                    })).unwrap().as_str())
+                    .create_for(|| {
+                        let client = client();
+                        let mut poller = OrderPoller::new(&path).ok().unwrap();
+                        let mut events = Vec::new();
+
+                        assert_eq!(poller.poll(&client, |e| events.push(e)).ok(), Some(3));
+
+                        match events[0] {
+                            OrderEvent::StatusChanged { ref order, ref from } => {
+                                assert_eq!(order.id, "1-1".to_string());
+                                assert_eq!(order.status, OrderStatus::PaymentReceived);
+                                assert_eq!(*from, OrderStatus::NewOrder);
+                            },
+                            ref other => panic!("Expected a StatusChanged, got {:?}", other),
+                        }
+                        match events[1] {
+                            OrderEvent::NewMessage { ref order_id, ref message } => {
+                                assert_eq!(*order_id, "1-1".to_string());
+                                assert_eq!(message.message, "Paid, thanks".to_string());
+                            },
+                            ref other => panic!("Expected a NewMessage, got {:?}", other),
+                        }
+                        match events[2] {
+                            OrderEvent::NewOrder { ref order } => assert_eq!(order.id, "1-2".to_string()),
+                            ref other => panic!("Expected a NewOrder, got {:?}", other),
+                        }
+
+                        assert_eq!(poller.last_activity(), Some("2017-01-01T12:00:00-08:00"));
+                    });
+            });
+    }
+}
@@ -0,0 +1,484 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! A local, caching proxy of the Discogs API sharing one rate limit budget
+//!
+//! Services that share an application key also share its requests per
+//! minute. Pointed at the proxy instead of the API, they are answered from
+//! one response cache, and only the misses go upstream, one at a time
+//! through a single rate limited client. Only the database and the lists
+//! are cached, the marketplace and the inventories are always forwarded.
+//!
+//! Everything but the database and the lists is sent with the credentials
+//! of the proxy, the orders and the inventory of the account included, so
+//! those requests and the writes are refused unless the caller sends the
+//! private token in the `X-Proxy-Token` header.
+//!
+//! Callers name themselves with the `X-Proxy-Caller` header (their address
+//! is used otherwise), `GET /_proxy/stats` returns how much of the budget
+//! each of them used.
+
+use discogs::Discogs;
+use hyper::header::Headers;
+use hyper::method::Method;
+use hyper::server::{Listening, Request, Response, Server};
+use hyper::status::StatusCode;
+use hyper::uri::RequestUri;
+use query::{QueryBuilder, QueryError, RequestBody, ResponseCache};
+use query::response_cache::is_cacheable;
+use serde_json;
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The header callers name themselves with
+pub const CALLER_HEADER: &'static str = "X-Proxy-Caller";
+
+/// The header callers send the private token in
+pub const TOKEN_HEADER: &'static str = "X-Proxy-Token";
+
+/// The endpoint answering the statistics of the callers
+pub const STATS_PATH: &'static str = "/_proxy/stats";
+
+// Every caller shares the cache, the upstream credentials are the same
+const CACHE_IDENTITY: &'static str = "proxy";
+
+/// What a caller used of the proxy
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct CallerStats {
+    pub requests: u64,
+    pub cache_hits: u64,
+    // Requests sent upstream, counting against the rate limit
+    pub forwarded: u64,
+    pub errors: u64,
+    pub forwarded_last_minute: u32,
+}
+
+/// The answer to a caller
+#[derive(Debug)]
+pub struct ProxyResponse {
+    pub status: StatusCode,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl ProxyResponse {
+    fn json(status: StatusCode, body: &Value) -> ProxyResponse {
+        let mut headers = Headers::new();
+        headers.set_raw("Content-Type", vec![b"application/json".to_vec()]);
+
+        ProxyResponse {
+            status: status,
+            headers: headers,
+            body: serde_json::to_vec(body).unwrap(),
+        }
+    }
+}
+
+pub struct Proxy {
+    client: Discogs,
+    cache: ResponseCache,
+
+    rate_limit: u32,
+    private_token: Option<String>,
+
+    stats: Mutex<BTreeMap<String, CallerStats>>,
+    // When each forwarded request was sent and for whom, over the last minute
+    recent: Mutex<VecDeque<(Instant, String)>>,
+}
+
+impl Proxy {
+    /// Creates a proxy forwarding the misses of `cache` through `client`,
+    /// at most `rate_limit` per minute
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::proxy::Proxy;
+    /// use discogs::query::ResponseCache;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.key("CLIENT_KEY").secret("CLIENT_SECRET");
+    ///
+    /// let proxy = Proxy::new(client, 60, ResponseCache::memory(10000));
+    /// ```
+    pub fn new(mut client: Discogs, rate_limit: u32, cache: ResponseCache) -> Proxy {
+        client.rate_limit(rate_limit);
+
+        Proxy {
+            client: client,
+            cache: cache,
+            rate_limit: rate_limit,
+            private_token: None,
+            stats: Mutex::new(BTreeMap::new()),
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Lets the callers sending `token` in the `X-Proxy-Token` header go
+    /// past the cached endpoints and write, with the credentials of the
+    /// proxy. Only the database and the lists are served otherwise.
+    pub fn private_token(&mut self, token: &str) -> &mut Proxy {
+        self.private_token = Some(token.to_owned());
+        self
+    }
+
+    /// Answers a request of `caller`, from the cache when possible
+    ///
+    /// `path` is the path of the endpoint along with its query, `token` the
+    /// one sent by the caller. Only the GETs of the database and the lists
+    /// are cached, the other requests are forwarded only when `token`
+    /// matches the private token.
+    pub fn handle(&self,
+                  caller: &str,
+                  token: Option<&str>,
+                  method: Method,
+                  path: &str,
+                  body: Option<String>) -> ProxyResponse {
+        if method == Method::Get && path == STATS_PATH {
+            return ProxyResponse::json(StatusCode::Ok, &self.stats());
+        }
+        self.update(caller, |stats| stats.requests += 1);
+
+        let query = self.client.raw(path);
+        let url = query.get_query_url();
+        let public = method == Method::Get && is_cacheable(&url);
+
+        let trusted = match (self.private_token.as_ref(), token) {
+            (Some(expected), Some(token)) => expected == token,
+            _ => false,
+        };
+        if !public && !trusted {
+            self.update(caller, |stats| stats.errors += 1);
+            return ProxyResponse::json(StatusCode::Forbidden,
+                                       &json!({ "message": "This request needs the private token of the proxy" }));
+        }
+
+        if public {
+            let mut forwarded = false;
+            let result = self.cache.fetch(&url, CACHE_IDENTITY, |conditional| {
+                forwarded = true;
+                self.count(caller);
+                query.send_uncached_request(Method::Get, None, conditional)
+            });
+
+            if !forwarded {
+                self.update(caller, |stats| stats.cache_hits += 1);
+            }
+            return self.reply(caller, forwarded, result.map(|(headers, body)| (StatusCode::Ok, headers, body)));
+        }
+
+        self.count(caller);
+        let result = query.send_raw_request(method, body.map(RequestBody::Json), Headers::new())
+                          .and_then(|mut response| {
+                              let mut body = Vec::new();
+                              try!(response.read_to_end(&mut body)
+                                           .map_err(|e| QueryError::TextReadError { error: e }));
+                              Ok((response.status, response.headers.clone(), body))
+                          });
+        self.reply(caller, true, result)
+    }
+
+    /// The statistics of every caller, along with the use of the rate limit
+    /// over the last minute
+    pub fn stats(&self) -> Value {
+        let per_caller = self.forwarded_last_minute();
+        let used: u32 = per_caller.values().sum();
+
+        let callers: BTreeMap<String, CallerStats> = self.stats
+                                                         .lock()
+                                                         .unwrap()
+                                                         .iter()
+                                                         .map(|(caller, stats)| {
+                                                             let mut stats = stats.clone();
+                                                             stats.forwarded_last_minute =
+                                                                 per_caller.get(caller).cloned().unwrap_or(0);
+                                                             (caller.clone(), stats)
+                                                         })
+                                                         .collect();
+
+        json!({
+            "rate_limit": (self.rate_limit),
+            "used_last_minute": used,
+            "remaining": (self.rate_limit.saturating_sub(used)),
+            "callers": callers
+        })
+    }
+
+    /// The statistics of `caller`, if it made any request
+    pub fn caller_stats(&self, caller: &str) -> Option<CallerStats> {
+        let forwarded_last_minute = self.forwarded_last_minute().get(caller).cloned().unwrap_or(0);
+
+        self.stats.lock().unwrap().get(caller).cloned().map(|mut stats| {
+            stats.forwarded_last_minute = forwarded_last_minute;
+            stats
+        })
+    }
+
+    /// Serves the proxy on `address`, like `127.0.0.1:8080`
+    ///
+    /// The server stops when the returned `Listening` is closed,
+    /// dropping it waits for the server.
+    pub fn listen(self, address: &str) -> Result<Listening, QueryError> {
+        let proxy = Arc::new(self);
+
+        let mut server = try!(Server::http(address).map_err(|e| QueryError::HyperSendError { hyper_err: e }));
+        // Every connection holds a thread while kept alive
+        server.keep_alive(None);
+
+        server.handle(move |request: Request, response: Response| serve(&proxy, request, response))
+              .map_err(|e| QueryError::HyperSendError { hyper_err: e })
+    }
+
+    // Counts a request forwarded for `caller`, the client spaces them
+    fn count(&self, caller: &str) {
+        self.recent.lock().unwrap().push_back((Instant::now(), caller.to_owned()));
+        self.update(caller, |stats| stats.forwarded += 1);
+    }
+
+    // The upstream answer as the caller sees it, errors of the API are passed on as they are
+    fn reply(&self,
+             caller: &str,
+             forwarded: bool,
+             result: Result<(StatusCode, Headers, Vec<u8>), QueryError>) -> ProxyResponse {
+        match result {
+            Ok((status, upstream, body)) => {
+                if !status.is_success() {
+                    self.update(caller, |stats| stats.errors += 1);
+                }
+
+                let mut headers = Headers::new();
+                headers.set_raw("Content-Type",
+                                vec![upstream.get_raw("Content-Type")
+                                             .and_then(|values| values.first().cloned())
+                                             .unwrap_or(b"application/json".to_vec())]);
+                headers.set_raw("X-Proxy-Cache",
+                                vec![if forwarded { b"MISS".to_vec() } else { b"HIT".to_vec() }]);
+
+                ProxyResponse {
+                    status: status,
+                    headers: headers,
+                    body: body,
+                }
+            },
+            Err(QueryError::HyperStatusError { mut response }) => {
+                self.update(caller, |stats| stats.errors += 1);
+
+                let mut body = Vec::new();
+                let _ = response.read_to_end(&mut body);
+
+                ProxyResponse {
+                    status: response.status,
+                    headers: response.headers.clone(),
+                    body: body,
+                }
+            },
+            Err(error) => {
+                self.update(caller, |stats| stats.errors += 1);
+                ProxyResponse::json(StatusCode::BadGateway, &json!({ "message": (format!("{:?}", error)) }))
+            },
+        }
+    }
+
+    fn update<F: FnOnce(&mut CallerStats)>(&self, caller: &str, change: F) {
+        change(self.stats.lock().unwrap().entry(caller.to_owned()).or_insert_with(CallerStats::default));
+    }
+
+    fn forwarded_last_minute(&self) -> BTreeMap<String, u32> {
+        let mut recent = self.recent.lock().unwrap();
+        let now = Instant::now();
+
+        while recent.front().map_or(false, |&(t, _)| now.duration_since(t) >= Duration::from_secs(60)) {
+            recent.pop_front();
+        }
+
+        let mut counts = BTreeMap::new();
+        for &(_, ref caller) in recent.iter() {
+            *counts.entry(caller.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+fn serve(proxy: &Proxy, mut request: Request, mut response: Response) {
+    let path = match request.uri {
+        RequestUri::AbsolutePath(ref path) => path.clone(),
+        RequestUri::AbsoluteUri(ref url) => {
+            match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_owned(),
+            }
+        },
+        _ => "/".to_owned(),
+    };
+
+    let caller = request.headers
+                        .get_raw(CALLER_HEADER)
+                        .and_then(|values| values.first())
+                        .map(|value| String::from_utf8_lossy(value).into_owned())
+                        .unwrap_or_else(|| request.remote_addr.ip().to_string());
+
+    let mut body = String::new();
+    let body = match request.read_to_string(&mut body) {
+        Ok(size) if size > 0 => Some(body),
+        _ => None,
+    };
+
+    let token = request.headers
+                       .get_raw(TOKEN_HEADER)
+                       .and_then(|values| values.first())
+                       .map(|value| String::from_utf8_lossy(value).into_owned());
+
+    let reply = proxy.handle(&caller, token.as_ref().map(|t| t.as_str()), request.method.clone(), &path, body);
+
+    *response.status_mut() = reply.status;
+    for header in reply.headers.iter() {
+        let name = header.name().to_lowercase();
+
+        // The length is the one of the body sent
+        if name != "content-length" && name != "transfer-encoding" {
+            response.headers_mut().set_raw(header.name().to_owned(), vec![header.value_string().into_bytes()]);
+        }
+    }
+    let _ = response.send(&reply.body);
+}
+
+#[cfg(test)]
+mod tests {
+    use discogs::*;
+    use proxy::*;
+    use mockito::mock;
+
+    fn proxy() -> Proxy {
+        Proxy::new(Discogs::new("USER_AGENT"), 6000, ResponseCache::memory(10))
+    }
+
+    #[test]
+    fn test_misses_are_forwarded_once() {
+        let proxy = proxy();
+
+        mock("GET", "/releases/4601")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 4601}")
+            .create_for(|| {
+                let response = proxy.handle("a", None, Method::Get, "/releases/4601", None);
+                assert_eq!(response.status, StatusCode::Ok);
+                assert_eq!(response.body, b"{\"id\": 4601}".to_vec());
+            });
+
+        // The mock is gone, the response comes from the cache
+        let response = proxy.handle("b", None, Method::Get, "/releases/4601", None);
+        assert_eq!(response.status, StatusCode::Ok);
+        assert_eq!(response.body, b"{\"id\": 4601}".to_vec());
+
+        assert_eq!(proxy.caller_stats("a"),
+                   Some(CallerStats { requests: 1, cache_hits: 0, forwarded: 1, errors: 0, forwarded_last_minute: 1 }));
+        assert_eq!(proxy.caller_stats("b"),
+                   Some(CallerStats { requests: 1, cache_hits: 1, forwarded: 0, errors: 0, forwarded_last_minute: 0 }));
+    }
+
+    #[test]
+    fn test_upstream_errors_are_passed_on() {
+        let proxy = proxy();
+
+        mock("GET", "/releases/4602")
+            .with_status(404)
+            .with_header("content-type", "application/json")
+            .with_body("{\"message\": \"Release not found.\"}")
+            .create_for(|| {
+                let response = proxy.handle("a", None, Method::Get, "/releases/4602", None);
+                assert_eq!(response.status, StatusCode::NotFound);
+                assert_eq!(response.body, b"{\"message\": \"Release not found.\"}".to_vec());
+            });
+
+        assert_eq!(proxy.caller_stats("a").unwrap().errors, 1);
+    }
+
+    #[test]
+    fn test_marketplace_is_not_cached() {
+        let mut proxy = proxy();
+        proxy.private_token("PRIVATE_TOKEN");
+
+        mock("GET", "/marketplace/listings/4604")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 4604}")
+            .create_for(|| {
+                let response = proxy.handle("a", Some("PRIVATE_TOKEN"), Method::Get, "/marketplace/listings/4604", None);
+                assert_eq!(response.body, b"{\"id\": 4604}".to_vec());
+            });
+
+        // Forwarded again, the listing may have changed
+        let response = proxy.handle("a", Some("PRIVATE_TOKEN"), Method::Get, "/marketplace/listings/4604", None);
+        assert!(response.status != StatusCode::Ok);
+        assert_eq!(proxy.caller_stats("a").unwrap().forwarded, 2);
+    }
+
+    #[test]
+    fn test_private_requests_need_the_token() {
+        let mut proxy = proxy();
+        proxy.private_token("PRIVATE_TOKEN");
+
+        // The orders of the account are not served to anyone
+        let refused = proxy.handle("a", None, Method::Get, "/marketplace/orders", None);
+        assert_eq!(refused.status, StatusCode::Forbidden);
+
+        mock("POST", "/marketplace/listings")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body("{\"listing_id\": 4605}")
+            .create_for(|| {
+                let body = Some("{\"release_id\": 1}".to_owned());
+
+                let refused = proxy.handle("a", None, Method::Post, "/marketplace/listings", body.clone());
+                assert_eq!(refused.status, StatusCode::Forbidden);
+                let refused = proxy.handle("a", Some("OTHER"), Method::Post, "/marketplace/listings", body.clone());
+                assert_eq!(refused.status, StatusCode::Forbidden);
+                assert_eq!(proxy.caller_stats("a").unwrap().forwarded, 0);
+
+                // The status of the API is passed on
+                let created = proxy.handle("a", Some("PRIVATE_TOKEN"), Method::Post, "/marketplace/listings", body);
+                assert_eq!(created.status, StatusCode::Created);
+                assert_eq!(created.body, b"{\"listing_id\": 4605}".to_vec());
+            });
+    }
+
+    #[test]
+    fn test_stats() {
+        let proxy = proxy();
+
+        mock("GET", "/artists/4603")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 4603}")
+            .create_for(|| {
+                proxy.handle("a", None, Method::Get, "/artists/4603", None);
+            });
+
+        let response = proxy.handle("a", None, Method::Get, STATS_PATH, None);
+        let stats: Value = serde_json::from_slice(&response.body).unwrap();
+
+        assert_eq!(stats["rate_limit"], json!(6000));
+        assert_eq!(stats["used_last_minute"], json!(1));
+        assert_eq!(stats["remaining"], json!(5999));
+        assert_eq!(stats["callers"]["a"]["forwarded"], json!(1));
+        assert!(proxy.caller_stats("b").is_none());
+    }
+}
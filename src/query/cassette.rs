@@ -0,0 +1,455 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use query::QueryError;
+use hyper;
+use hyper::Url;
+use hyper::client::Response;
+use hyper::header::Headers;
+use hyper::method::Method;
+use hyper::net::NetworkStream;
+use hyper::status::StatusCode;
+use serde_json;
+use std::fs;
+use std::io;
+use std::io::{Cursor, Read, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Written in place of the credentials
+pub const REDACTED: &'static str = "REDACTED";
+
+// Credentials may also be passed as query parameters
+const SECRET_PARAMETERS: [&'static str; 3] = ["token", "key", "secret"];
+
+/// A request and the response the API gave to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    pub url: String,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: Option<String>,
+    pub status: u16,
+    pub response_headers: Vec<(String, String)>,
+    // Only one of the bodies is set, images are kept as bytes
+    pub response_body: Option<String>,
+    pub binary_response_body: Option<Vec<u8>>,
+}
+
+impl Interaction {
+    fn matches(&self, method: &Method, url: &str, body: Option<&str>) -> bool {
+        self.method == method.to_string() &&
+        path_and_query(&self.url) == path_and_query(&redact_url(url)) &&
+        self.request_body.as_ref().map(|b| b.as_str()) == body
+    }
+
+    fn body(&self) -> Vec<u8> {
+        match (&self.response_body, &self.binary_response_body) {
+            (&Some(ref text), _) => text.clone().into_bytes(),
+            (_, &Some(ref bytes)) => bytes.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The response as it would be read from the network
+    fn to_response(&self, url: &str) -> Result<Response, QueryError> {
+        let body = self.body();
+        let reason = StatusCode::from_u16(self.status).canonical_reason().unwrap_or("");
+        let mut raw = format!("HTTP/1.1 {} {}\r\n", self.status, reason).into_bytes();
+
+        for &(ref name, ref value) in &self.response_headers {
+            // The body is stored decoded and whole
+            let lower = name.to_lowercase();
+            if lower == "content-length" || lower == "transfer-encoding" || lower == "content-encoding" {
+                continue;
+            }
+            raw.extend(format!("{}: {}\r\n", name, value).into_bytes());
+        }
+        raw.extend(format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes());
+        raw.extend(body);
+
+        let url = try!(Url::parse(url).map_err(|e| QueryError::CassetteError {
+            reason: format!("Invalid url {}: {}", url, e)
+        }));
+
+        Response::new(url, Box::new(ReplayStream { input: Cursor::new(raw) }))
+            .map_err(|e| QueryError::HyperSendError { hyper_err: e })
+    }
+}
+
+// Feeds a recorded response to hyper
+struct ReplayStream {
+    input: Cursor<Vec<u8>>,
+}
+
+impl Read for ReplayStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for ReplayStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl NetworkStream for ReplayStream {
+    fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        Err(io::Error::new(io::ErrorKind::NotConnected, "Replayed response"))
+    }
+
+    fn set_read_timeout(&self, _: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_write_timeout(&self, _: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+struct CassetteState {
+    path: PathBuf,
+    mode: CassetteMode,
+    interactions: Vec<Interaction>,
+    played: Vec<bool>,
+}
+
+/// A file of requests and their responses
+///
+/// When recording, every request goes to the API and is written to the
+/// cassette along with its response, with the credentials redacted.
+/// When replaying, requests are answered from the cassette, in the order
+/// they were recorded, and requests that were not recorded fail with
+/// `QueryError::CassetteError` without touching the network.
+///
+/// The scheme and host are ignored when matching, so a cassette recorded
+/// against the API can be replayed by a client with a different endpoint.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use discogs::Discogs;
+/// use discogs::query::Cassette;
+///
+/// let mut client = Discogs::new("USER_AGENT");
+/// client.cassette(Cassette::replay("tests/cassettes/artist.json").ok().unwrap());
+///
+/// let artist = client.artist(555).get();
+/// ```
+#[derive(Clone)]
+pub struct Cassette {
+    state: Arc<Mutex<CassetteState>>,
+}
+
+impl Cassette {
+    /// Records to `path`, replacing the cassette there if any
+    pub fn record<P: AsRef<Path>>(path: P) -> Cassette {
+        Cassette::with_interactions(path, CassetteMode::Record, Vec::new())
+    }
+
+    /// Replays the cassette at `path`
+    pub fn replay<P: AsRef<Path>>(path: P) -> Result<Cassette, QueryError> {
+        let mut text = String::new();
+        try!(fs::File::open(path.as_ref())
+                 .and_then(|mut f| f.read_to_string(&mut text))
+                 .map_err(|e| QueryError::TextReadError { error: e }));
+
+        let file: CassetteFile = try!(serde_json::from_str(&text).map_err(|e| {
+            QueryError::JsonDecodeError { serde_err: Some(e) }
+        }));
+
+        Ok(Cassette::with_interactions(path, CassetteMode::Replay, file.interactions))
+    }
+
+    fn with_interactions<P: AsRef<Path>>(path: P, mode: CassetteMode, interactions: Vec<Interaction>) -> Cassette {
+        let played = vec![false; interactions.len()];
+
+        Cassette {
+            state: Arc::new(Mutex::new(CassetteState {
+                path: path.as_ref().to_path_buf(),
+                mode: mode,
+                interactions: interactions,
+                played: played,
+            }))
+        }
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.state.lock().unwrap().mode
+    }
+
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.state.lock().unwrap().interactions.clone()
+    }
+
+    /// The recorded interactions that were not replayed yet
+    pub fn unplayed(&self) -> Vec<Interaction> {
+        let state = self.state.lock().unwrap();
+
+        state.interactions
+             .iter()
+             .zip(state.played.iter())
+             .filter(|&(_, played)| !played)
+             .map(|(interaction, _)| interaction.clone())
+             .collect()
+    }
+
+    /// Answers the request with the first recorded response to it not played yet
+    pub fn play(&self, method: &Method, url: &str, body: Option<&str>) -> Result<Response, QueryError> {
+        let interaction = {
+            let mut state = self.state.lock().unwrap();
+            let index = state.interactions
+                             .iter()
+                             .zip(state.played.iter())
+                             .position(|(interaction, &played)| !played && interaction.matches(method, url, body));
+
+            match index {
+                Some(index) => {
+                    state.played[index] = true;
+                    state.interactions[index].clone()
+                },
+                None => return Err(QueryError::CassetteError {
+                    reason: format!("Unexpected request {} {}", method, redact_url(url))
+                }),
+            }
+        };
+
+        interaction.to_response(url)
+    }
+
+    /// Writes the exchange to the cassette, returning the response to read it again
+    pub fn record(&self,
+                  method: &Method,
+                  url: &str,
+                  request_headers: &Headers,
+                  body: Option<&str>,
+                  mut response: Response)
+                  -> Result<Response, QueryError> {
+        let mut bytes = Vec::new();
+        try!(response.read_to_end(&mut bytes).map_err(|e| QueryError::TextReadError { error: e }));
+
+        let (response_body, binary_response_body) = match String::from_utf8(bytes) {
+            Ok(text) => (Some(text), None),
+            Err(error) => (None, Some(error.into_bytes())),
+        };
+
+        let interaction = Interaction {
+            method: method.to_string(),
+            url: redact_url(url),
+            request_headers: redact_headers(request_headers),
+            request_body: body.map(|b| b.to_owned()),
+            status: response.status.to_u16(),
+            response_headers: response.headers.iter().map(|h| (h.name().to_owned(), h.value_string())).collect(),
+            response_body: response_body,
+            binary_response_body: binary_response_body,
+        };
+
+        {
+            let mut state = self.state.lock().unwrap();
+            state.interactions.push(interaction.clone());
+            state.played.push(true);
+        }
+        try!(self.save());
+
+        interaction.to_response(url)
+    }
+
+    /// Writes the cassette to its file
+    pub fn save(&self) -> Result<(), QueryError> {
+        let state = self.state.lock().unwrap();
+        let file = CassetteFile { interactions: state.interactions.clone() };
+        let text = serde_json::to_string_pretty(&file).unwrap();
+
+        if let Some(parent) = state.path.parent() {
+            try!(fs::create_dir_all(parent).map_err(|e| QueryError::TextReadError { error: e }));
+        }
+        fs::File::create(&state.path)
+            .and_then(|mut f| f.write_all(text.as_bytes()))
+            .map_err(|e| QueryError::TextReadError { error: e })
+    }
+}
+
+fn redact_headers(headers: &Headers) -> Vec<(String, String)> {
+    headers.iter()
+           .map(|h| {
+               let value = if h.name().to_lowercase() == "authorization" {
+                   REDACTED.to_owned()
+               } else {
+                   h.value_string()
+               };
+               (h.name().to_owned(), value)
+           })
+           .collect()
+}
+
+fn redact_url(url: &str) -> String {
+    let (base, query) = match url.find('?') {
+        Some(i) => (&url[..i], &url[i + 1..]),
+        None => return url.to_owned(),
+    };
+
+    let parameters: Vec<String> = query.split('&')
+                                       .map(|p| {
+                                           let name = p.split('=').next().unwrap_or("");
+                                           if SECRET_PARAMETERS.iter().any(|&secret| secret == name) {
+                                               format!("{}={}", name, REDACTED)
+                                           } else {
+                                               p.to_owned()
+                                           }
+                                       })
+                                       .collect();
+
+    format!("{}?{}", base, parameters.join("&"))
+}
+
+fn path_and_query(url: &str) -> &str {
+    let rest = match url.find("://") {
+        Some(i) => &url[i + 3..],
+        None => return url,
+    };
+    match rest.find('/') {
+        Some(i) => &rest[i..],
+        None => "/",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::{Authorization, Headers, UserAgent};
+    use hyper::method::Method;
+    use hyper::status::StatusCode;
+    use std::env;
+    use std::io::Read;
+
+    fn interaction(url: &str, status: u16, body: &str) -> Interaction {
+        Interaction {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            request_headers: Vec::new(),
+            request_body: None,
+            status: status,
+            response_headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            response_body: Some(body.to_string()),
+            binary_response_body: None,
+        }
+    }
+
+    fn path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("discogs-rs-cassette-{}.json", name))
+    }
+
+    #[test]
+    fn test_redaction() {
+        assert_eq!(redact_url("https://api.discogs.com/database/search?q=a&token=abc"),
+                   "https://api.discogs.com/database/search?q=a&token=REDACTED".to_string());
+
+        let mut headers = Headers::new();
+        headers.set(UserAgent("USER_AGENT".to_string()));
+        headers.set(Authorization("Discogs token=abc".to_string()));
+
+        let redacted = redact_headers(&headers);
+        assert!(redacted.contains(&("Authorization".to_string(), REDACTED.to_string())));
+        assert!(redacted.contains(&("User-Agent".to_string(), "USER_AGENT".to_string())));
+    }
+
+    #[test]
+    fn test_replay_in_order() {
+        let cassette = Cassette::with_interactions(path("order"),
+                                                   CassetteMode::Replay,
+                                                   vec![interaction("https://api.discogs.com/releases/1", 200, "first"),
+                                                        interaction("https://api.discogs.com/releases/1", 404, "second")]);
+        let url = "http://127.0.0.1:1234/releases/1";
+
+        let mut first = cassette.play(&Method::Get, url, None).ok().unwrap();
+        let mut text = String::new();
+        first.read_to_string(&mut text).unwrap();
+        assert_eq!(first.status, StatusCode::Ok);
+        assert_eq!(text, "first".to_string());
+
+        let second = cassette.play(&Method::Get, url, None).ok().unwrap();
+        assert_eq!(second.status, StatusCode::NotFound);
+        assert!(cassette.unplayed().is_empty());
+
+        match cassette.play(&Method::Get, url, None) {
+            Err(QueryError::CassetteError { .. }) => (),
+            _ => panic!("Expected a CassetteError"),
+        }
+        match cassette.play(&Method::Post, "https://api.discogs.com/releases/1", None) {
+            Err(QueryError::CassetteError { .. }) => (),
+            _ => panic!("Expected a CassetteError"),
+        }
+    }
+
+    #[test]
+    fn test_save_and_replay() {
+        let path = path("save");
+        let recorded = Cassette::with_interactions(&path,
+                                                   CassetteMode::Record,
+                                                   vec![interaction("https://api.discogs.com/artists/1", 200, "{}")]);
+        recorded.save().ok().unwrap();
+
+        let replayed = Cassette::replay(&path).ok().unwrap();
+        assert_eq!(replayed.mode(), CassetteMode::Replay);
+        assert_eq!(replayed.interactions(), recorded.interactions());
+        assert_eq!(replayed.unplayed().len(), 1);
+    }
+
+    #[test]
+    fn test_record_rereads_response() {
+        let cassette = Cassette::record(path("record"));
+        let source = interaction("https://api.discogs.com/artists/2", 200, "{\"id\": 2}");
+        let response = source.to_response("https://api.discogs.com/artists/2").ok().unwrap();
+
+        let mut headers = Headers::new();
+        headers.set(Authorization("Discogs token=abc".to_string()));
+
+        let mut response = cassette.record(&Method::Get,
+                                           "https://api.discogs.com/artists/2",
+                                           &headers,
+                                           None,
+                                           response)
+                                   .ok()
+                                   .unwrap();
+
+        let mut text = String::new();
+        response.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "{\"id\": 2}".to_string());
+
+        let recorded = Cassette::replay(path("record")).ok().unwrap().interactions();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].response_body, Some("{\"id\": 2}".to_string()));
+        assert_eq!(recorded[0].request_headers, vec![("Authorization".to_string(), REDACTED.to_string())]);
+    }
+}
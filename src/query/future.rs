@@ -0,0 +1,114 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+
+use futures_cpupool::CpuFuture;
+pub use futures_cpupool::CpuPool;
+use query::QueryError;
+use std::sync::{Arc, Mutex};
+
+/// The number of queries running at the same time, unless a pool is given
+pub const DEFAULT_THREADS: usize = 16;
+
+/// A query running on the threads of its client
+///
+/// The blocking methods of the builders have an `_async` variant returning
+/// one, it resolves to the same result and waits on the same rate limiter,
+/// shared with the blocking queries of the client and its clones, and goes
+/// through the same caches and cassettes.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// extern crate discogs;
+/// extern crate futures;
+///
+/// use discogs::Discogs;
+/// use futures::Future;
+///
+/// # fn main() {
+/// let client = Discogs::new("USER_AGENT");
+///
+/// let titles = client.release(8481).get_async()
+///     .join(client.release(8482).get_async())
+///     .map(|(first, second)| (first.title, second.title));
+///
+/// println!("{:?}", titles.wait());
+/// # }
+/// ```
+pub type QueryFuture<T> = CpuFuture<T, QueryError>;
+
+/// The threads the queries of a client run on, shared by its clones
+///
+/// Unless a pool is given there are `DEFAULT_THREADS`, started by the first
+/// query. The queries spend their time waiting on the network and the rate
+/// limiter rather than the cpu, so the pool is not sized by the cpu count.
+#[derive(Clone, Default)]
+pub struct Executor {
+    pool: Arc<Mutex<Option<CpuPool>>>,
+}
+
+impl Executor {
+    pub fn new() -> Executor {
+        Executor::default()
+    }
+
+    /// Runs the queries on `pool`
+    pub fn with_pool(pool: CpuPool) -> Executor {
+        Executor { pool: Arc::new(Mutex::new(Some(pool))) }
+    }
+
+    /// Runs `query` on the pool, the future resolves to its result
+    pub fn spawn<T, F>(&self, query: F) -> QueryFuture<T>
+        where T: Send + 'static,
+              F: FnOnce() -> Result<T, QueryError> + Send + 'static
+    {
+        let mut pool = self.pool.lock().unwrap();
+        if pool.is_none() {
+            *pool = Some(CpuPool::new(DEFAULT_THREADS));
+        }
+        pool.as_ref().unwrap().spawn_fn(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::Future;
+    use query::*;
+
+    #[test]
+    fn test_spawn() {
+        let executor = Executor::new();
+
+        let results: Vec<_> = (0..4u32)
+            .map(|i| executor.spawn(move || Ok(i * 2)))
+            .collect();
+        let results: Vec<u32> = results.into_iter().map(|f| f.wait().ok().unwrap()).collect();
+
+        assert_eq!(results, vec![0, 2, 4, 6]);
+    }
+
+    #[test]
+    fn test_spawn_error() {
+        let executor = Executor::with_pool(CpuPool::new(1));
+        let future = executor.spawn(|| -> Result<(), QueryError> { Err(QueryError::OfflineMiss { url: "url".to_string() }) });
+
+        match future.wait() {
+            Err(QueryError::OfflineMiss { url }) => assert_eq!(url, "url".to_string()),
+            _ => panic!("expected the error of the query"),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use query::QueryError;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// An on-disk cache of downloaded images, keyed by their uri
+///
+/// Once the files in the cache go over `max_bytes` the images stored
+/// first are removed first, reading an image does not keep it longer.
+/// Images larger than `max_bytes` are not cached.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use discogs::Discogs;
+/// use discogs::query::ImageCache;
+///
+/// let cache = ImageCache::new("/tmp/covers", 512 * 1024 * 1024).ok().unwrap();
+///
+/// let mut client = Discogs::new("USER_AGENT");
+/// client.image_cache(cache);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ImageCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl ImageCache {
+    /// Creates a cache in `dir`, creating the directory if needed
+    pub fn new<P: AsRef<Path>>(dir: P, max_bytes: u64) -> Result<ImageCache, QueryError> {
+        let dir = dir.as_ref().to_path_buf();
+        try!(fs::create_dir_all(&dir).map_err(|e| QueryError::TextReadError { error: e }));
+
+        Ok(ImageCache {
+            dir: dir,
+            max_bytes: max_bytes,
+        })
+    }
+
+    /// Returns the cached image for `uri`, if any
+    pub fn get(&self, uri: &str) -> Option<Vec<u8>> {
+        let mut reader = match fs::File::open(self.path_for(uri)) {
+            Ok(file) => BufReader::new(file),
+            Err(_) => return None,
+        };
+
+        let mut line = String::new();
+        if reader.read_line(&mut line).is_err() {
+            return None;
+        }
+        // Another uri with the same hash
+        if line.trim_right_matches('\n') != uri {
+            return None;
+        }
+
+        let mut data = Vec::new();
+        match reader.read_to_end(&mut data) {
+            Ok(_) => Some(data),
+            Err(_) => None,
+        }
+    }
+
+    /// Stores the image for `uri`, evicting older images to stay under the size limit
+    ///
+    /// The uri is written on the first line of the file, the image follows.
+    pub fn put(&self, uri: &str, data: &[u8]) -> Result<(), QueryError> {
+        if (uri.len() + 1 + data.len()) as u64 > self.max_bytes {
+            return Ok(());
+        }
+
+        let path = self.path_for(uri);
+
+        // Written aside first so that readers never see half an image
+        let partial = path.with_extension("partial");
+        try!(fs::File::create(&partial)
+                 .and_then(|mut f| {
+                     try!(writeln!(f, "{}", uri));
+                     f.write_all(data)
+                 })
+                 .and_then(|_| fs::rename(&partial, &path))
+                 .map_err(|e| QueryError::TextReadError { error: e }));
+
+        evict_oldest(&self.dir, "img", self.max_bytes, &path)
+    }
+
+    /// The total size of the cached images
+    pub fn size(&self) -> u64 {
+        files_with_extension(&self.dir, "img").iter().map(|e| e.1).sum()
+    }
+
+    /// Removes every cached image
+    pub fn clear(&self) -> Result<(), QueryError> {
+        for (path, _, _) in files_with_extension(&self.dir, "img") {
+            try!(fs::remove_file(path).map_err(|e| QueryError::TextReadError { error: e }));
+        }
+        Ok(())
+    }
+
+    fn path_for(&self, uri: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.img", fnv1a(uri.as_bytes())))
+    }
+}
+
+/// Removes the oldest files of `dir` ending in `extension` until they fit in `max_bytes`
+///
+/// `keep`, the file just written, is never removed.
+pub fn evict_oldest(dir: &Path, extension: &str, max_bytes: u64, keep: &Path) -> Result<(), QueryError> {
+    let mut entries = files_with_extension(dir, extension);
+    let mut size: u64 = entries.iter().map(|e| e.1).sum();
+
+    // Oldest first
+    entries.sort_by(|a, b| a.2.cmp(&b.2));
+
+    for (path, len, _) in entries {
+        if size <= max_bytes {
+            break;
+        }
+        if path.as_path() == keep {
+            continue;
+        }
+        try!(fs::remove_file(&path).map_err(|e| QueryError::TextReadError { error: e }));
+        size -= len;
+    }
+    Ok(())
+}
+
+/// The files of `dir` ending in `extension`, along with their size and modification time
+pub fn files_with_extension(dir: &Path, extension: &str) -> Vec<(PathBuf, u64, ::std::time::SystemTime)> {
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(_) => return Vec::new(),
+    };
+
+    read_dir.filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().map_or(false, |e| e == extension))
+            .filter_map(|entry| {
+                entry.metadata()
+                     .and_then(|m| m.modified().map(|t| (entry.path(), m.len(), t)))
+                     .ok()
+            })
+            .collect()
+}
+
+// A hash that stays the same across rust versions, so the cache survives upgrades
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    fn cache(name: &str, max_bytes: u64) -> ImageCache {
+        let dir = env::temp_dir().join(format!("discogs-rs-image-cache-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        ImageCache::new(dir, max_bytes).ok().unwrap()
+    }
+
+    #[test]
+    fn test_put_and_get() {
+        let cache = cache("get", 100);
+
+        assert_eq!(cache.get("https://img.discogs.com/a.jpg"), None);
+        cache.put("https://img.discogs.com/a.jpg", b"image").ok().unwrap();
+
+        assert_eq!(cache.get("https://img.discogs.com/a.jpg"), Some(b"image".to_vec()));
+        assert_eq!(cache.get("https://img.discogs.com/b.jpg"), None);
+        assert_eq!(cache.size(), 35);
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let cache = cache("limit", 10);
+
+        cache.put("a", b"aaaaaa").ok().unwrap();
+        cache.put("b", b"bbbbbb").ok().unwrap();
+
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(b"bbbbbb".to_vec()));
+
+        // Too large to be cached at all
+        cache.put("c", b"ccccccccc").ok().unwrap();
+        assert_eq!(cache.get("c"), None);
+        assert_eq!(cache.size(), 8);
+    }
+
+    #[test]
+    fn test_uri_is_checked_on_read() {
+        let cache = cache("collision", 100);
+
+        cache.put("https://img.discogs.com/a.jpg", b"image").ok().unwrap();
+
+        // Another uri landing on the same file is not served its image
+        let path = cache.path_for("https://img.discogs.com/a.jpg");
+        fs::copy(&path, cache.path_for("https://img.discogs.com/b.jpg")).unwrap();
+        assert_eq!(cache.get("https://img.discogs.com/b.jpg"), None);
+    }
+}
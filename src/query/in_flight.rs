@@ -0,0 +1,270 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+
+use hyper::header::Headers;
+use query::QueryError;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Shares one request between identical GETs sent at the same time
+///
+/// The first of them is sent, the others wait for its response.
+/// Errors can not be shared, when it fails one of the waiting ones
+/// is sent in its place and the others wait for that one instead.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate discogs;
+/// # extern crate hyper;
+/// use discogs::query::InFlight;
+/// use hyper::header::Headers;
+///
+/// # fn main() {
+/// let in_flight = InFlight::new();
+/// let response = in_flight.fetch("key:KEY https://api.discogs.com/releases/1",
+///                                || Ok((Headers::new(), b"{}".to_vec())));
+///
+/// assert_eq!(response.ok().unwrap().1, b"{}".to_vec());
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct InFlight {
+    calls: Arc<Mutex<HashMap<String, Arc<Call>>>>,
+}
+
+struct Call {
+    state: Mutex<CallState>,
+    finished: Condvar,
+    // The requests waiting for this one
+    waiters: AtomicUsize,
+}
+
+enum CallState {
+    Running,
+    // Failed while others were waiting, one of them is sent next
+    Failed,
+    // The response, if the request succeeded
+    Done(Option<(Headers, Vec<u8>)>),
+}
+
+// Ends a call even when the request panics, so that nobody waits forever
+struct Finish<'a> {
+    in_flight: &'a InFlight,
+    key: &'a str,
+    call: &'a Call,
+    response: Option<(Headers, Vec<u8>)>,
+}
+
+impl<'a> Drop for Finish<'a> {
+    fn drop(&mut self) {
+        {
+            // Requests only start waiting with the calls locked, so none is missed
+            let mut calls = self.in_flight.calls.lock().unwrap_or_else(|e| e.into_inner());
+            let mut state = self.call.state.lock().unwrap_or_else(|e| e.into_inner());
+
+            if self.response.is_none() && self.call.waiters.load(Ordering::SeqCst) > 0 {
+                *state = CallState::Failed;
+            } else {
+                calls.remove(self.key);
+                *state = CallState::Done(self.response.take());
+            }
+        }
+        self.call.finished.notify_all();
+    }
+}
+
+impl InFlight {
+    pub fn new() -> InFlight {
+        InFlight::default()
+    }
+
+    /// Sends `request`, or waits for the response of the identical one
+    /// already running
+    ///
+    /// `key` identifies the request, the url along with who it is made as.
+    pub fn fetch<F>(&self, key: &str, request: F) -> Result<(Headers, Vec<u8>), QueryError>
+        where F: FnOnce() -> Result<(Headers, Vec<u8>), QueryError>
+    {
+        let (call, first) = match self.calls.lock().unwrap().entry(key.to_owned()) {
+            Entry::Occupied(entry) => {
+                entry.get().waiters.fetch_add(1, Ordering::SeqCst);
+                (entry.get().clone(), false)
+            },
+            Entry::Vacant(entry) => (entry.insert(Arc::new(Call::new())).clone(), true),
+        };
+
+        if !first {
+            if let Some(response) = call.wait() {
+                return Ok(response);
+            }
+        }
+
+        let mut finish = Finish {
+            in_flight: self,
+            key: key,
+            call: &*call,
+            response: None,
+        };
+        let result = request();
+        finish.response = result.as_ref().ok().cloned();
+        result
+    }
+
+    /// The number of requests running
+    pub fn len(&self) -> usize {
+        self.calls.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The number of requests waiting for the one running for `key`
+    #[cfg(test)]
+    fn waiters(&self, key: &str) -> usize {
+        self.calls.lock().unwrap().get(key).map_or(0, |call| call.waiters.load(Ordering::SeqCst))
+    }
+}
+
+impl Call {
+    fn new() -> Call {
+        Call {
+            state: Mutex::new(CallState::Running),
+            finished: Condvar::new(),
+            waiters: AtomicUsize::new(0),
+        }
+    }
+
+    // The response of the running request, or `None` when it failed and
+    // this request is the one sent in its place
+    fn wait(&self) -> Option<(Headers, Vec<u8>)> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            match *state {
+                CallState::Running => {},
+                CallState::Failed => break,
+                CallState::Done(ref response) => return response.clone(),
+            }
+            state = self.finished.wait(state).unwrap();
+        }
+
+        *state = CallState::Running;
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::Headers;
+    use query::QueryError;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    const KEY: &'static str = "key:KEY /releases/1";
+
+    // Starts a request that runs until `finish` is sent to, and `waiting` requests
+    // for the same key. Returns the responses and how many requests were sent.
+    fn run(waiting: usize, succeed: bool) -> (Vec<Result<(Headers, Vec<u8>), QueryError>>, usize) {
+        let in_flight = InFlight::new();
+        let sent = Arc::new(AtomicUsize::new(0));
+        let (started, on_start) = channel();
+        let (finish, on_finish) = channel::<()>();
+
+        let first = {
+            let in_flight = in_flight.clone();
+            let sent = sent.clone();
+            thread::spawn(move || {
+                in_flight.fetch(KEY, || {
+                    sent.fetch_add(1, Ordering::SeqCst);
+                    started.send(()).unwrap();
+                    on_finish.recv().unwrap();
+                    if succeed {
+                        Ok((Headers::new(), b"release".to_vec()))
+                    } else {
+                        Err(QueryError::EmptyResponseError)
+                    }
+                })
+            })
+        };
+        on_start.recv().unwrap();
+
+        let others: Vec<_> = (0..waiting)
+            .map(|_| {
+                let in_flight = in_flight.clone();
+                let sent = sent.clone();
+                thread::spawn(move || {
+                    in_flight.fetch(KEY, || {
+                        sent.fetch_add(1, Ordering::SeqCst);
+                        Ok((Headers::new(), b"own release".to_vec()))
+                    })
+                })
+            })
+            .collect();
+
+        // Waits for the others to reach the running request
+        while in_flight.waiters(KEY) < waiting {
+            thread::yield_now();
+        }
+        assert_eq!(in_flight.len(), 1);
+        finish.send(()).unwrap();
+
+        let mut responses = vec![first.join().unwrap()];
+        responses.extend(others.into_iter().map(|other| other.join().unwrap()));
+        assert!(in_flight.is_empty());
+
+        (responses, sent.load(Ordering::SeqCst))
+    }
+
+    #[test]
+    fn test_identical_requests_are_sent_once() {
+        let (responses, sent) = run(4, true);
+
+        assert_eq!(sent, 1);
+        for response in responses {
+            assert_eq!(response.ok().unwrap().1, b"release".to_vec());
+        }
+    }
+
+    #[test]
+    fn test_failed_requests_are_sent_again_once() {
+        let (responses, sent) = run(3, false);
+
+        assert_eq!(sent, 2);
+        assert!(responses[0].is_err());
+        for response in &responses[1..] {
+            assert_eq!(response.as_ref().ok().unwrap().1, b"own release".to_vec());
+        }
+    }
+
+    #[test]
+    fn test_later_requests_are_sent_again() {
+        let in_flight = InFlight::new();
+
+        let first = in_flight.fetch(KEY, || Ok((Headers::new(), b"first".to_vec())));
+        let second = in_flight.fetch(KEY, || Ok((Headers::new(), b"second".to_vec())));
+
+        assert_eq!(first.ok().unwrap().1, b"first".to_vec());
+        assert_eq!(second.ok().unwrap().1, b"second".to_vec());
+    }
+}
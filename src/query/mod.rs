@@ -18,9 +18,29 @@ pub mod query_error;
 pub mod query_builder;
 pub mod query_token_auth;
 pub mod query_ks_auth;
+pub mod multipart;
+pub mod rate_limiter;
+pub mod image_cache;
+pub mod response_cache;
+pub mod offline;
+pub mod cassette;
+pub mod raw;
+pub mod in_flight;
+#[cfg(feature = "async")]
+pub mod future;
 
 pub use self::query_error::QueryError;
-pub use self::query_builder::QueryBuilder;
+pub use self::query_builder::{QueryBuilder, encode_component};
+pub use self::multipart::{Multipart, RequestBody};
+pub use self::rate_limiter::RateLimiter;
+pub use self::image_cache::ImageCache;
+pub use self::response_cache::{ResponseCache, CacheStore, MemoryStore, DiskStore};
+pub use self::offline::MissReport;
+pub use self::cassette::{Cassette, CassetteMode, Interaction};
+pub use self::raw::RawQueryBuilder;
+pub use self::in_flight::InFlight;
+#[cfg(feature = "async")]
+pub use self::future::{QueryFuture, Executor, CpuPool};
 
 //TODO: Put these under the module auth
 pub use self::query_token_auth::DiscogsTokenAuth;
@@ -0,0 +1,138 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A file sent as part of a `multipart/form-data` request
+struct Part {
+    name: String,
+    filename: String,
+    content_type: String,
+    data: String,
+}
+
+/// A `multipart/form-data` request body
+///
+/// # Examples
+///
+/// ```
+/// use discogs::query::Multipart;
+///
+/// let mut multipart = Multipart::new();
+/// multipart.file("upload", "inventory.csv", "text/csv", "listing_id\n41578241\n");
+///
+/// let boundary = multipart.boundary();
+/// let body = multipart.body(&boundary);
+/// let content_type = Multipart::content_type_for(&boundary);
+/// ```
+pub struct Multipart {
+    parts: Vec<Part>,
+}
+
+impl Multipart {
+    pub fn new() -> Multipart {
+        Multipart {
+            parts: Vec::new(),
+        }
+    }
+
+    /// Adds a file field to the body
+    pub fn file(&mut self, name: &str, filename: &str, content_type: &str, data: &str) -> &mut Self {
+        self.parts.push(Part {
+            name: name.to_owned(),
+            filename: filename.to_owned(),
+            content_type: content_type.to_owned(),
+            data: data.to_owned(),
+        });
+        self
+    }
+
+    /// The boundary separating the parts, guaranteed not to appear in any of them
+    pub fn boundary(&self) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+
+        self.boundary_from(format!("discogs-rs-{:x}", nanos))
+    }
+
+    /// Extends `candidate` until none of the parts contain it
+    fn boundary_from(&self, candidate: String) -> String {
+        let mut boundary = candidate;
+        while self.parts.iter().any(|p| p.data.contains(boundary.as_str())) {
+            boundary.push('-');
+        }
+        boundary
+    }
+
+    /// The value of the content type header for `boundary`
+    pub fn content_type_for(boundary: &str) -> String {
+        format!("multipart/form-data; boundary={}", boundary)
+    }
+
+    /// Serializes the parts, separated by `boundary`
+    pub fn body(&self, boundary: &str) -> String {
+        let mut body = String::new();
+
+        for part in self.parts.iter() {
+            body.push_str(&format!("--{}\r\n", boundary));
+            body.push_str(&format!("Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n",
+                                   part.name, part.filename));
+            body.push_str(&format!("Content-Type: {}\r\n\r\n", part.content_type));
+            body.push_str(&part.data);
+            body.push_str("\r\n");
+        }
+        body.push_str(&format!("--{}--\r\n", boundary));
+
+        body
+    }
+}
+
+/// The body of a request that writes to discogs
+pub enum RequestBody {
+    Json(String),
+    Multipart(Multipart),
+}
+
+#[cfg(test)]
+mod tests {
+    use query::Multipart;
+
+    #[test]
+    fn test_multipart_body() {
+        let mut multipart = Multipart::new();
+        multipart.file("upload", "inventory.csv", "text/csv", "listing_id\n1\n");
+
+        assert_eq!(multipart.body("XYZ"),
+                   "--XYZ\r\n\
+                    Content-Disposition: form-data; name=\"upload\"; filename=\"inventory.csv\"\r\n\
+                    Content-Type: text/csv\r\n\
+                    \r\n\
+                    listing_id\n1\n\r\n\
+                    --XYZ--\r\n".to_string());
+    }
+
+    #[test]
+    fn test_multipart_boundary_not_in_data() {
+        let mut multipart = Multipart::new();
+        multipart.file("upload", "inventory.csv", "text/csv", "a,discogs-rs-abc,discogs-rs-abc-\n");
+
+        let boundary = multipart.boundary_from("discogs-rs-abc".to_string());
+        assert_eq!(boundary, "discogs-rs-abc--");
+        assert!(!multipart.parts[0].data.contains(boundary.as_str()));
+    }
+}
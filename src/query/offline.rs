@@ -0,0 +1,120 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// The urls requested while offline that were not in the cache
+///
+/// Shared by every query of a client, the urls can be fetched once
+/// online to pre-warm the cache.
+///
+/// # Examples
+///
+/// ```
+/// use discogs::Discogs;
+/// use discogs::query::ResponseCache;
+///
+/// let mut client = Discogs::new("USER_AGENT");
+/// client.response_cache(ResponseCache::memory(1000))
+///       .offline(true);
+///
+/// assert!(client.release(8481).get().is_err());
+/// assert_eq!(client.miss_report().urls(), vec![format!("{}/releases/8481", discogs::API_URL)]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MissReport {
+    urls: Arc<Mutex<Vec<String>>>,
+}
+
+impl MissReport {
+    pub fn new() -> MissReport {
+        MissReport::default()
+    }
+
+    /// Records a miss, every url is only listed once
+    pub fn record(&self, url: &str) {
+        let mut urls = self.urls.lock().unwrap();
+
+        if !urls.iter().any(|u| u == url) {
+            urls.push(url.to_owned());
+        }
+    }
+
+    /// The missed urls, in the order they were first requested
+    pub fn urls(&self) -> Vec<String> {
+        self.urls.lock().unwrap().clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.lock().unwrap().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.urls.lock().unwrap().clear();
+    }
+}
+
+/// One url per line
+impl fmt::Display for MissReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for url in self.urls() {
+            try!(writeln!(f, "{}", url));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use discogs::Discogs;
+    use query::{QueryError, ResponseCache};
+
+    #[test]
+    fn test_miss_report() {
+        let report = MissReport::new();
+        let shared = report.clone();
+        assert!(report.is_empty());
+
+        shared.record("https://api.discogs.com/releases/1");
+        shared.record("https://api.discogs.com/artists/1");
+        shared.record("https://api.discogs.com/releases/1");
+
+        assert_eq!(report.urls(),
+                   vec!["https://api.discogs.com/releases/1".to_string(),
+                        "https://api.discogs.com/artists/1".to_string()]);
+        assert_eq!(report.to_string(),
+                   "https://api.discogs.com/releases/1\nhttps://api.discogs.com/artists/1\n".to_string());
+
+        report.clear();
+        assert!(shared.is_empty());
+    }
+
+    #[test]
+    fn test_uncached_queries_are_not_reported() {
+        let mut client = Discogs::new("USER_AGENT");
+        client.token("USER_TOKEN")
+              .response_cache(ResponseCache::memory(10))
+              .offline(true);
+
+        match client.inventory("someone").get() {
+            Err(QueryError::OfflineMiss { .. }) => {},
+            _ => panic!("the inventory was fetched while offline"),
+        }
+        assert!(client.miss_report().is_empty());
+    }
+}
@@ -14,58 +14,180 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use discogs::Discogs;
 use query::QueryError;
 use query::*;
-use hyper;
+use query::image_cache::fnv1a;
+use hyper::client::Response;
 use hyper::header::*;
 use hyper::status::StatusCode;
-use hyper::net::HttpsConnector;
-use hyper_native_tls::NativeTlsClient;
+use hyper::method::Method;
+use std::io;
 use std::io::Read;
+use std::sync::{Arc, Mutex};
 
 pub trait QueryBuilder {
+    // The client the query was made from, its settings apply to the query
+    fn get_client(&self) -> &Discogs;
+
     fn get_key(&self) -> Option<String> {
-        None
+        self.get_client().get_key()
     }
 
     fn get_secret(&self) -> Option<String> {
+        self.get_client().get_secret()
+    }
+
+    // The user token, when present it takes precedence over the key and secret
+    fn get_token(&self) -> Option<String> {
         None
     }
 
     // returns the  url to perform the query
     fn get_query_url(&self) -> String;
 
-    fn get_user_agent(&self) -> String;
+    fn get_user_agent(&self) -> String {
+        self.get_client().get_user_agent()
+    }
+
+    // The cache of GET responses, when the query is cacheable
+    fn get_cache(&self) -> Option<ResponseCache> {
+        None
+    }
+
+    // Where to report the urls missing from the cache, when offline
+    fn get_offline(&self) -> Option<MissReport> {
+        let client = self.get_client();
+
+        if client.is_offline() {
+            Some(client.miss_report())
+        } else {
+            None
+        }
+    }
+
+    // Records the exchanges or replays them instead of using the network
+    fn get_cassette(&self) -> Option<Cassette> {
+        self.get_client().get_cassette()
+    }
+
+    // Shares the GETs with the identical ones sent at the same time
+    fn get_in_flight(&self) -> Option<InFlight> {
+        self.get_client().in_flight()
+    }
+
+    // What every request sent over the network waits on
+    fn get_rate_limiter(&self) -> Arc<Mutex<RateLimiter>> {
+        self.get_client().rate_limiter()
+    }
+
+    // Who the request is made as, so that cached responses are never shared between users.
+    // The credentials are hashed, the identity ends up in the keys of the disk cache
+    fn get_identity(&self) -> String {
+        match self.get_token() {
+            Some(token) => format!("token:{:016x}", fnv1a(token.as_bytes())),
+            None => {
+                let credentials = format!("{}:{}",
+                                          self.get_key().unwrap_or_default(),
+                                          self.get_secret().unwrap_or_default());
+                format!("key:{:016x}", fnv1a(credentials.as_bytes()))
+            },
+        }
+    }
 
-    //TODO: Fix the unwrap()
     fn perform_request(&self) -> Result<String, QueryError> {
-        let ssl = NativeTlsClient::new().unwrap();
-        let connector = HttpsConnector::new(ssl);
-        let client = hyper::Client::with_connector(connector);
-        let response = client.get(self.get_query_url().as_str())
-                             .header(UserAgent(self.get_user_agent()))
-                             .header(Authorization(DiscogsKSAuth {
-                                         key: self.get_key(),
-                                         secret: self.get_secret()
-                             }))
-                             .send();
-
-        match response {
+        self.perform_request_with(Method::Get, None)
+    }
+
+    // Performs the query with the given method, sending `body` as json if present
+    fn perform_request_with(&self, method: Method, body: Option<String>) -> Result<String, QueryError> {
+        self.send_request(method, body.map(RequestBody::Json)).map(|(_, text)| text)
+    }
+
+    // Performs a GET query whose response is not text, like an image
+    fn perform_bytes_request(&self) -> Result<Vec<u8>, QueryError> {
+        self.send_bytes_request(Method::Get, None).map(|(_, bytes)| bytes)
+    }
+
+    // Performs the query returning the response headers along with the body
+    fn send_request(&self, method: Method, body: Option<RequestBody>) -> Result<(Headers, String), QueryError> {
+        let (headers, bytes) = try!(self.send_bytes_request(method, body));
+
+        match String::from_utf8(bytes) {
+            Ok(text) => Ok((headers, text)),
+            Err(error) => Err(QueryError::TextReadError {
+                error: io::Error::new(io::ErrorKind::InvalidData, error)
+            })
+        }
+    }
+
+    // Performs the query returning the response headers along with the raw body,
+    // GETs go through the cache if there is one
+    fn send_bytes_request(&self, method: Method, body: Option<RequestBody>) -> Result<(Headers, Vec<u8>), QueryError> {
+        let cache = match method {
+            Method::Get => self.get_cache(),
+            _ => None
+        };
+
+        // Offline, only the cache can answer
+        if let Some(report) = self.get_offline() {
+            let url = self.get_query_url();
+
+            if let Some(ref cache) = cache {
+                if let Some(response) = cache.cached(&url, &self.get_identity()) {
+                    return Ok(response);
+                }
+                // Only the queries using the cache can be pre-warmed, writes never do
+                report.record(&url);
+            }
+            return Err(QueryError::OfflineMiss {
+                url: url
+            });
+        }
+
+        let in_flight = match method {
+            Method::Get => self.get_in_flight(),
+            _ => None
+        };
+        let send = || match cache {
+            Some(cache) => cache.fetch(&self.get_query_url(),
+                                       &self.get_identity(),
+                                       |conditional| self.send_uncached_request(Method::Get, None, conditional)),
+            None => self.send_uncached_request(method, body, Headers::new())
+        };
+
+        match in_flight {
+            Some(in_flight) => in_flight.fetch(&format!("{} {}", self.get_identity(), self.get_query_url()), send),
+            None => send()
+        }
+    }
+
+    // Performs the query, adding `extra_headers` to the usual ones
+    fn send_uncached_request(&self, method: Method, body: Option<RequestBody>, extra_headers: Headers) -> Result<(Headers, Vec<u8>), QueryError> {
+        let is_get = method == Method::Get;
+
+        match self.send_raw_request(method, body, extra_headers) {
             Ok(mut text) => {
-                let mut json: String = "".to_owned();
+                let mut bytes: Vec<u8> = Vec::new();
 
-                if text.status != StatusCode::Ok {
+                if !text.status.is_success() {
                     return Err(QueryError::HyperStatusError {
                         response: text
                     });
                 }
-                let text_read_result = text.read_to_string(&mut json);
+
+                // Edits and deletes succeed without a body
+                if text.status == StatusCode::NoContent {
+                    return Ok((text.headers.clone(), bytes));
+                }
+
+                let text_read_result = text.read_to_end(&mut bytes);
 
                 if let Ok(sz) = text_read_result {
-                    if sz <= 0 {
+                    if sz <= 0 && is_get {
                         return Err(QueryError::EmptyResponseError);
                     }
-                    return Ok(json);
+                    return Ok((text.headers.clone(), bytes));
                 } else {
                     return Err(QueryError::TextReadError {
                         error: text_read_result.err().unwrap()
@@ -74,9 +196,113 @@ pub trait QueryBuilder {
 
 
             },
-            Err(error) => return Err(QueryError::HyperSendError {
-                hyper_err: error
-            })
+            Err(error) => return Err(error)
+        }
+    }
+
+    // Sends the query and returns the response as is, whatever its status
+    fn send_raw_request(&self, method: Method, body: Option<RequestBody>, extra_headers: Headers) -> Result<Response, QueryError> {
+        let client = self.get_client().http_client();
+        let mut headers = Headers::new();
+        headers.set(UserAgent(self.get_user_agent()));
+
+        if let Some(token) = self.get_token() {
+            headers.set(Authorization(DiscogsTokenAuth {
+                token: token
+            }));
+        } else {
+            headers.set(Authorization(DiscogsKSAuth {
+                key: self.get_key(),
+                secret: self.get_secret()
+            }));
+        }
+        headers.extend(extra_headers.iter());
+
+        let url = self.get_query_url();
+        let payload = match body {
+            Some(RequestBody::Json(json)) => {
+                headers.set(ContentType::json());
+                Some(json)
+            },
+            Some(RequestBody::Multipart(multipart)) => {
+                let boundary = multipart.boundary();
+                headers.set_raw("Content-Type",
+                                vec![Multipart::content_type_for(&boundary).into_bytes()]);
+                Some(multipart.body(&boundary))
+            },
+            None => None
+        };
+
+        let cassette = self.get_cassette();
+        let payload = payload.as_ref().map(|p| p.as_str());
+
+        match cassette {
+            Some(ref cassette) if cassette.mode() == CassetteMode::Replay => {
+                cassette.play(&method, &url, payload)
+            },
+            _ => {
+                self.get_rate_limiter().lock().unwrap().acquire();
+
+                let sent = match payload {
+                    Some(payload) => client.request(method.clone(), url.as_str())
+                                           .headers(headers.clone())
+                                           .body(payload)
+                                           .send(),
+                    None => client.request(method.clone(), url.as_str())
+                                  .headers(headers.clone())
+                                  .send()
+                }.map_err(|error| QueryError::HyperSendError {
+                    hyper_err: error
+                });
+
+                match cassette {
+                    Some(ref cassette) => sent.and_then(|response| {
+                        cassette.record(&method, &url, &headers, payload, response)
+                    }),
+                    None => sent
+                }
+            }
+        }
+    }
+}
+
+/// Percent-encodes `text` so it can be used as a path segment or a query value
+///
+/// Only the unreserved characters of RFC 3986 are left as they are.
+pub fn encode_component(text: &str) -> String {
+    let mut encoded = String::new();
+
+    for byte in text.bytes() {
+        if byte < 128 && ((byte as char).is_alphanumeric() || b"-._~".contains(&byte)) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
         }
     }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QueryBuilder, encode_component};
+    use discogs::Discogs;
+
+    #[test]
+    fn test_encode_component() {
+        assert_eq!(encode_component("Sk-032_a.b~"), "Sk-032_a.b~".to_string());
+        assert_eq!(encode_component("a b/c?d&e=ö"), "a%20b%2Fc%3Fd%26e%3D%C3%B6".to_string());
+    }
+
+    #[test]
+    fn test_identity_covers_the_secret() {
+        let identity = |secret: &str| {
+            let mut client = Discogs::new("USER_AGENT");
+            client.key("KEY").secret(secret);
+            client.release(1).get_identity()
+        };
+
+        assert_eq!(identity("SECRET"), identity("SECRET"));
+        assert!(identity("SECRET") != identity("OTHER"));
+        assert!(!identity("SECRET").contains("SECRET"));
+    }
 }
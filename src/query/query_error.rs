@@ -16,6 +16,8 @@
 
 use serde_json;
 use hyper;
+#[cfg(feature = "sqlite")]
+use rusqlite;
 use std;
 
 /// `QueryError` is a structure of all the errors
@@ -26,6 +28,9 @@ pub enum QueryError {
     AuthenticationMissingError {
         reason: String,
     },
+    InvalidRequestError {
+        reason: String,
+    },
     JsonDecodeError {
         serde_err: Option<serde_json::Error>
     },
@@ -35,6 +40,30 @@ pub enum QueryError {
     HyperStatusError {
         response: hyper::client::response::Response
     },
+    CsvDecodeError {
+        line: usize,
+        reason: String,
+    },
+    DumpDecodeError {
+        reason: String,
+    },
+    #[cfg(feature = "sqlite")]
+    SqliteError {
+        error: rusqlite::Error
+    },
+    NotFoundError {
+        reason: String,
+    },
+    // Offline and the response of `url` is not cached
+    OfflineMiss {
+        url: String,
+    },
+    // Replaying and the request is not in the cassette, or the cassette is unusable
+    CassetteError {
+        reason: String,
+    },
+    // The batch the query was part of was cancelled before it was sent
+    Cancelled,
     EmptyResponseError,
     TextReadError {
         error: std::io::Error
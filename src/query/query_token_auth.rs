@@ -21,7 +21,7 @@ use hyper;
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct DiscogsTokenAuth {
-    token: String,
+    pub token: String,
 }
 
 impl Scheme for DiscogsTokenAuth {
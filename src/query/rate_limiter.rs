@@ -0,0 +1,94 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Spaces requests evenly so that no more than `per_minute`
+/// are sent in any minute
+///
+/// # Examples
+///
+/// ```
+/// use discogs::query::RateLimiter;
+///
+/// let mut limiter = RateLimiter::new(240);
+///
+/// // The first request is never delayed
+/// limiter.acquire();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    interval: Duration,
+    next: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> RateLimiter {
+        let nanos = 60 * 1_000_000_000u64 / if per_minute == 0 { 1 } else { per_minute as u64 };
+
+        RateLimiter {
+            interval: Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32),
+            next: None,
+        }
+    }
+
+    /// Blocks until a request may be sent
+    pub fn acquire(&mut self) {
+        let wait = self.reserve(Instant::now());
+
+        if wait > Duration::new(0, 0) {
+            thread::sleep(wait);
+        }
+    }
+
+    /// Books the next slot and returns how long to wait for it
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let slot = match self.next {
+            Some(next) if next > now => next,
+            _ => now,
+        };
+
+        self.next = Some(slot + self.interval);
+        slot - now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_requests_are_spaced() {
+        let mut limiter = RateLimiter::new(60);
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve(now), Duration::new(0, 0));
+        assert_eq!(limiter.reserve(now), Duration::new(1, 0));
+        assert_eq!(limiter.reserve(now), Duration::new(2, 0));
+    }
+
+    #[test]
+    fn test_idle_time_is_not_banked() {
+        let mut limiter = RateLimiter::new(120);
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve(now), Duration::new(0, 0));
+        assert_eq!(limiter.reserve(now + Duration::new(10, 0)), Duration::new(0, 0));
+        assert_eq!(limiter.reserve(now + Duration::new(10, 0)), Duration::new(0, 500_000_000));
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use discogs::Discogs;
+use query::*;
+use query::response_cache::is_cacheable;
+use hyper::header::Headers;
+use hyper::method::Method;
+
+/// A query of any endpoint, answered with the raw response
+///
+/// Useful for the endpoints without a query builder of their own,
+/// or to pass the responses on untouched.
+#[derive(Clone)]
+pub struct RawQueryBuilder {
+    // The path of the endpoint along with its query, like `/releases/1?curr_abbr=EUR`
+    path: String,
+
+    // The client making the query, sharing its credentials and state
+    client: Discogs,
+}
+
+impl RawQueryBuilder {
+    /// Creates a new instance of `RawQueryBuilder`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::Discogs;
+    /// use discogs::query::RawQueryBuilder;
+    ///
+    /// let rqb = RawQueryBuilder::new("/releases/1".to_string(),
+    ///                                &Discogs::new("USER_AGENT"));
+    /// ```
+    pub fn new(path: String,
+               client: &Discogs) -> RawQueryBuilder {
+        RawQueryBuilder {
+            path: path,
+            client: client.clone()
+        }
+    }
+
+    /// Perform request, returning the response headers and body
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    ///
+    /// let (headers, body) = Discogs::new("USER_AGENT")
+    ///                                .raw("/releases/1")
+    ///                                .get()
+    ///                                .unwrap();
+    /// ```
+    pub fn get(&self) -> Result<(Headers, Vec<u8>), QueryError> {
+        self.send_bytes_request(Method::Get, None)
+    }
+
+    /// Performs the query with any method, sending `body` as json if present
+    pub fn send(&self, method: Method, body: Option<String>) -> Result<(Headers, Vec<u8>), QueryError> {
+        self.send_bytes_request(method, body.map(RequestBody::Json))
+    }
+}
+
+#[cfg(feature = "async")]
+impl RawQueryBuilder {
+    /// `get` without blocking the caller
+    pub fn get_async(&self) -> QueryFuture<(Headers, Vec<u8>)> {
+        let query = self.clone();
+        self.client.spawn(move || query.get())
+    }
+
+    /// `send` without blocking the caller
+    pub fn send_async(&self, method: Method, body: Option<String>) -> QueryFuture<(Headers, Vec<u8>)> {
+        let query = self.clone();
+        self.client.spawn(move || query.send(method, body))
+    }
+}
+
+impl QueryBuilder for RawQueryBuilder {
+    fn get_client(&self) -> &Discogs {
+        &self.client
+    }
+
+    fn get_token(&self) -> Option<String> {
+        self.client.get_token()
+    }
+
+    fn get_query_url(&self) -> String {
+        match self.path.starts_with('/') {
+            true => format!("{}{}", self.client.get_api_endpoint(), self.path),
+            false => format!("{}/{}", self.client.get_api_endpoint(), self.path),
+        }
+    }
+
+    // Raw paths may lead anywhere, only the database and lists are cached
+    fn get_cache(&self) -> Option<ResponseCache> {
+        match is_cacheable(&self.get_query_url()) {
+            true => self.client.get_response_cache(),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use discogs::*;
+    use query::*;
+    use mockito::mock;
+
+    #[test]
+    fn test_query_url() {
+        let client = Discogs::new("USER_AGENT");
+
+        assert_eq!(client.raw("/releases/1?curr_abbr=EUR").get_query_url(),
+                   format!("{}/releases/1?curr_abbr=EUR", API_URL));
+        assert_eq!(client.raw("releases/1").get_query_url(), format!("{}/releases/1", API_URL));
+    }
+
+    #[test]
+    fn test_raw_request() {
+        mock("GET", "/releases/131")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 131}")
+            .create_for(|| {
+                let (_, body) = Discogs::new("USER_AGENT").raw("/releases/131").get().unwrap();
+                assert_eq!(body, b"{\"id\": 131}".to_vec());
+            });
+    }
+}
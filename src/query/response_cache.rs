@@ -0,0 +1,686 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use query::QueryError;
+use query::image_cache::{evict_oldest, files_with_extension, fnv1a};
+use hyper::header::Headers;
+use hyper::status::StatusCode;
+use serde_json;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How long, in seconds, responses are used without asking the API again
+pub const DEFAULT_TTL: u64 = 60 * 60;
+
+/// A response as kept in the cache
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    // Seconds since the unix epoch, reset whenever the API confirms the body
+    pub stored_at: u64,
+}
+
+impl CachedResponse {
+    /// The validators of the response, as headers
+    pub fn headers(&self) -> Headers {
+        let mut headers = Headers::new();
+
+        if let Some(ref etag) = self.etag {
+            headers.set_raw("ETag", vec![etag.clone().into_bytes()]);
+        }
+        if let Some(ref last_modified) = self.last_modified {
+            headers.set_raw("Last-Modified", vec![last_modified.clone().into_bytes()]);
+        }
+        headers
+    }
+
+    /// The headers asking the API to only send the body if it changed
+    pub fn conditional_headers(&self) -> Headers {
+        let mut headers = Headers::new();
+
+        if let Some(ref etag) = self.etag {
+            headers.set_raw("If-None-Match", vec![etag.clone().into_bytes()]);
+        }
+        if let Some(ref last_modified) = self.last_modified {
+            headers.set_raw("If-Modified-Since", vec![last_modified.clone().into_bytes()]);
+        }
+        headers
+    }
+}
+
+/// Where the cached responses are kept
+pub trait CacheStore: Send {
+    fn get(&mut self, key: &str) -> Option<CachedResponse>;
+
+    fn put(&mut self, key: &str, response: CachedResponse) -> Result<(), QueryError>;
+
+    fn clear(&mut self) -> Result<(), QueryError>;
+}
+
+/// Keeps up to `capacity` responses in memory, dropping the least recently used
+#[derive(Debug, Clone)]
+pub struct MemoryStore {
+    capacity: usize,
+    // A list in order of use, linked by index so that moving an entry is O(1)
+    nodes: Vec<Node>,
+    index: HashMap<String, usize>,
+    // The least recently used
+    head: Option<usize>,
+    // The most recently used
+    tail: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    key: String,
+    response: CachedResponse,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+impl MemoryStore {
+    pub fn new(capacity: usize) -> MemoryStore {
+        MemoryStore {
+            capacity: capacity,
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            head: None,
+            tail: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let (prev, next) = (self.nodes[i].prev, self.nodes[i].next);
+
+        match prev {
+            Some(p) => self.nodes[p].next = next,
+            None => self.head = next,
+        }
+        match next {
+            Some(n) => self.nodes[n].prev = prev,
+            None => self.tail = prev,
+        }
+    }
+
+    fn push_back(&mut self, i: usize) {
+        self.nodes[i].prev = self.tail;
+        self.nodes[i].next = None;
+
+        match self.tail {
+            Some(t) => self.nodes[t].next = Some(i),
+            None => self.head = Some(i),
+        }
+        self.tail = Some(i);
+    }
+
+    fn touch(&mut self, i: usize) {
+        self.unlink(i);
+        self.push_back(i);
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&mut self, key: &str) -> Option<CachedResponse> {
+        let i = match self.index.get(key) {
+            Some(&i) => i,
+            None => return None,
+        };
+
+        self.touch(i);
+        Some(self.nodes[i].response.clone())
+    }
+
+    fn put(&mut self, key: &str, response: CachedResponse) -> Result<(), QueryError> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+
+        let existing = self.index.get(key).cloned();
+        match existing {
+            Some(i) => {
+                self.nodes[i].response = response;
+                self.touch(i);
+            },
+            None if self.nodes.len() < self.capacity => {
+                self.nodes.push(Node {
+                    key: key.to_owned(),
+                    response: response,
+                    prev: None,
+                    next: None,
+                });
+                let i = self.nodes.len() - 1;
+                self.index.insert(key.to_owned(), i);
+                self.push_back(i);
+            },
+            None => {
+                // Full, the least recently used entry makes room
+                let i = self.head.unwrap();
+                self.unlink(i);
+
+                let oldest = ::std::mem::replace(&mut self.nodes[i].key, key.to_owned());
+                self.index.remove(&oldest);
+                self.nodes[i].response = response;
+                self.index.insert(key.to_owned(), i);
+                self.push_back(i);
+            },
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), QueryError> {
+        self.nodes.clear();
+        self.index.clear();
+        self.head = None;
+        self.tail = None;
+        Ok(())
+    }
+}
+
+/// Keeps the responses in `dir`, one file each, so they survive restarts
+///
+/// Once the files go over `max_bytes` the responses stored first are
+/// removed first. Revalidated responses are stored again, so the ones
+/// still in use stay.
+#[derive(Debug, Clone)]
+pub struct DiskStore {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+// Written on the first line of every file, the body follows
+#[derive(Serialize, Deserialize)]
+struct DiskEntry {
+    key: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    stored_at: u64,
+}
+
+impl DiskStore {
+    /// Creates a store in `dir` keeping up to `max_bytes`, creating the directory if needed
+    pub fn new<P: AsRef<Path>>(dir: P, max_bytes: u64) -> Result<DiskStore, QueryError> {
+        let dir = dir.as_ref().to_path_buf();
+        try!(fs::create_dir_all(&dir).map_err(|e| QueryError::TextReadError { error: e }));
+
+        Ok(DiskStore {
+            dir: dir,
+            max_bytes: max_bytes,
+        })
+    }
+
+    /// The total size of the stored responses
+    pub fn size(&self) -> u64 {
+        files_with_extension(&self.dir, "response").iter().map(|e| e.1).sum()
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.response", fnv1a(key.as_bytes())))
+    }
+
+    fn read(&self, key: &str) -> io::Result<Option<CachedResponse>> {
+        let mut reader = BufReader::new(try!(fs::File::open(self.path_for(key))));
+        let mut line = String::new();
+        try!(reader.read_line(&mut line));
+
+        let entry: DiskEntry = match serde_json::from_str(&line) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(None),
+        };
+
+        // Another key with the same hash
+        if entry.key != key {
+            return Ok(None);
+        }
+
+        let mut body = Vec::new();
+        try!(reader.read_to_end(&mut body));
+
+        Ok(Some(CachedResponse {
+            body: body,
+            etag: entry.etag,
+            last_modified: entry.last_modified,
+            stored_at: entry.stored_at,
+        }))
+    }
+}
+
+impl CacheStore for DiskStore {
+    fn get(&mut self, key: &str) -> Option<CachedResponse> {
+        self.read(key).ok().and_then(|response| response)
+    }
+
+    fn put(&mut self, key: &str, response: CachedResponse) -> Result<(), QueryError> {
+        let entry = DiskEntry {
+            key: key.to_owned(),
+            etag: response.etag,
+            last_modified: response.last_modified,
+            stored_at: response.stored_at,
+        };
+        let line = serde_json::to_string(&entry).unwrap();
+
+        if (line.len() + 1 + response.body.len()) as u64 > self.max_bytes {
+            return Ok(());
+        }
+
+        let path = self.path_for(key);
+
+        // Written aside first so that readers never see half a response
+        let partial = path.with_extension("partial");
+        try!(fs::File::create(&partial)
+                 .and_then(|mut f| f.write_all(line.as_bytes())
+                                    .and_then(|_| f.write_all(b"\n"))
+                                    .and_then(|_| f.write_all(&response.body)))
+                 .and_then(|_| fs::rename(&partial, &path))
+                 .map_err(|e| QueryError::TextReadError { error: e }));
+
+        evict_oldest(&self.dir, "response", self.max_bytes, &path)
+    }
+
+    fn clear(&mut self) -> Result<(), QueryError> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(_) => return Ok(()),
+        };
+
+        for entry in read_dir.filter_map(|entry| entry.ok()) {
+            if entry.path().extension().map_or(false, |e| e == "response") {
+                try!(fs::remove_file(entry.path()).map_err(|e| QueryError::TextReadError { error: e }));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A cache of API responses, shared by every query of a client
+///
+/// Responses are keyed by their normalized url and by who asked for
+/// them, so that users with different tokens never see each other's
+/// data. Fresh responses are used without touching the network; stale
+/// ones are revalidated with `If-None-Match`/`If-Modified-Since`, and a
+/// `304 Not Modified` only costs the headers.
+///
+/// Only the database and list endpoints are cached, the marketplace
+/// always reflects the live state.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use discogs::Discogs;
+/// use discogs::query::ResponseCache;
+///
+/// let mut cache = ResponseCache::memory(1000);
+/// cache.endpoint_ttl("/database/search", 10 * 60)
+///      .endpoint_ttl("/releases", 24 * 60 * 60);
+///
+/// let mut client = Discogs::new("USER_AGENT");
+/// client.response_cache(cache);
+/// ```
+#[derive(Clone)]
+pub struct ResponseCache {
+    store: Arc<Mutex<Box<CacheStore>>>,
+    ttl: u64,
+    // Path prefixes and their ttl, the longest matching prefix wins
+    endpoint_ttls: Vec<(String, u64)>,
+}
+
+impl ResponseCache {
+    pub fn new<S: CacheStore + 'static>(store: S) -> ResponseCache {
+        ResponseCache {
+            store: Arc::new(Mutex::new(Box::new(store))),
+            ttl: DEFAULT_TTL,
+            endpoint_ttls: Vec::new(),
+        }
+    }
+
+    /// A cache keeping up to `capacity` responses in memory
+    pub fn memory(capacity: usize) -> ResponseCache {
+        ResponseCache::new(MemoryStore::new(capacity))
+    }
+
+    /// A cache keeping up to `max_bytes` of responses in `dir`
+    pub fn disk<P: AsRef<Path>>(dir: P, max_bytes: u64) -> Result<ResponseCache, QueryError> {
+        DiskStore::new(dir, max_bytes).map(ResponseCache::new)
+    }
+
+    /// The ttl, in seconds, of the endpoints without one of their own
+    ///
+    /// A ttl of 0 revalidates the response on every request.
+    pub fn ttl(&mut self, ttl: u64) -> &mut ResponseCache {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The ttl, in seconds, of the urls whose path starts with `prefix`
+    pub fn endpoint_ttl(&mut self, prefix: &str, ttl: u64) -> &mut ResponseCache {
+        self.endpoint_ttls.retain(|&(ref p, _)| p != prefix);
+        self.endpoint_ttls.push((prefix.to_owned(), ttl));
+        self
+    }
+
+    /// The ttl that applies to `url`
+    pub fn ttl_for(&self, url: &str) -> u64 {
+        let path = path_of(url);
+
+        self.endpoint_ttls
+            .iter()
+            .filter(|&&(ref prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|&&(ref prefix, _)| prefix.len())
+            .map_or(self.ttl, |&(_, ttl)| ttl)
+    }
+
+    /// Removes every cached response
+    pub fn clear(&self) -> Result<(), QueryError> {
+        self.store.lock().unwrap().clear()
+    }
+
+    /// The cache key of `url` when requested by `identity`
+    pub fn key(url: &str, identity: &str) -> String {
+        format!("{:016x} {}", fnv1a(identity.as_bytes()), normalize_url(url))
+    }
+
+    /// Returns the cached response to a GET of `url`, however old it is
+    pub fn cached(&self, url: &str, identity: &str) -> Option<(Headers, Vec<u8>)> {
+        let key = ResponseCache::key(url, identity);
+
+        self.store.lock().unwrap().get(&key).map(|response| (response.headers(), response.body))
+    }
+
+    /// Returns the response to a GET of `url`, from the cache when possible
+    ///
+    /// `send` performs the request, with the given extra headers.
+    pub fn fetch<F>(&self, url: &str, identity: &str, send: F) -> Result<(Headers, Vec<u8>), QueryError>
+        where F: FnOnce(Headers) -> Result<(Headers, Vec<u8>), QueryError>
+    {
+        let key = ResponseCache::key(url, identity);
+        let now = now();
+        let cached = self.store.lock().unwrap().get(&key);
+
+        let conditional = match cached {
+            Some(ref response) if now.saturating_sub(response.stored_at) < self.ttl_for(url) => {
+                return Ok((response.headers(), response.body.clone()));
+            },
+            Some(ref response) => response.conditional_headers(),
+            None => Headers::new(),
+        };
+
+        match send(conditional) {
+            Ok((headers, body)) => {
+                let response = CachedResponse {
+                    body: body,
+                    etag: raw_header(&headers, "ETag"),
+                    last_modified: raw_header(&headers, "Last-Modified"),
+                    stored_at: now,
+                };
+
+                // The request succeeded, failing to cache it is not an error
+                let _ = self.store.lock().unwrap().put(&key, response.clone());
+                Ok((headers, response.body))
+            },
+            Err(QueryError::HyperStatusError { ref response }) if response.status == StatusCode::NotModified &&
+                                                               cached.is_some() => {
+                let mut revalidated = cached.unwrap();
+                revalidated.stored_at = now;
+
+                let _ = self.store.lock().unwrap().put(&key, revalidated.clone());
+                Ok((revalidated.headers(), revalidated.body))
+            },
+            Err(error) => Err(error),
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn raw_header(headers: &Headers, name: &str) -> Option<String> {
+    headers.get_raw(name)
+           .and_then(|values| values.first())
+           .map(|value| String::from_utf8_lossy(value).into_owned())
+}
+
+/// The path of `url`, without the scheme, host and query
+fn path_of(url: &str) -> &str {
+    let rest = match url.find("://") {
+        Some(i) => &url[i + 3..],
+        None => url,
+    };
+    let path = match rest.find('/') {
+        Some(i) => &rest[i..],
+        None => "/",
+    };
+    match path.find('?') {
+        Some(i) => &path[..i],
+        None => path,
+    }
+}
+
+/// Whether the response to `url` may be cached, only the database and the
+/// lists are, the marketplace and the inventories change too often
+pub fn is_cacheable(url: &str) -> bool {
+    let segments: Vec<&str> = path_of(url).split('/').filter(|s| !s.is_empty()).collect();
+
+    match segments.first() {
+        Some(&"releases") | Some(&"masters") | Some(&"artists") | Some(&"labels") | Some(&"lists") => true,
+        Some(&"database") => segments.get(1) == Some(&"search"),
+        Some(&"users") => segments.get(2) == Some(&"lists"),
+        _ => false,
+    }
+}
+
+/// Lowercases the scheme and host, and sorts the query parameters,
+/// dropping the empty ones
+pub fn normalize_url(url: &str) -> String {
+    let (base, query) = match url.find('?') {
+        Some(i) => (&url[..i], &url[i + 1..]),
+        None => (url, ""),
+    };
+
+    let base = match base.find("://") {
+        Some(i) => {
+            let host_end = base[i + 3..].find('/').map_or(base.len(), |j| i + 3 + j);
+            format!("{}{}", base[..host_end].to_lowercase(), &base[host_end..])
+        },
+        None => base.to_owned(),
+    };
+
+    let mut parameters: Vec<&str> = query.split('&')
+                                         .filter(|p| !p.is_empty() && !p.ends_with('='))
+                                         .collect();
+    parameters.sort();
+
+    if parameters.is_empty() {
+        base
+    } else {
+        format!("{}?{}", base, parameters.join("&"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::Headers;
+    use std::env;
+    use std::fs;
+
+    fn response(body: &str) -> CachedResponse {
+        CachedResponse {
+            body: body.as_bytes().to_vec(),
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+            stored_at: 100,
+        }
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(normalize_url("HTTPS://API.Discogs.com/database/search?type=release&q=Nirvana&year="),
+                   "https://api.discogs.com/database/search?q=Nirvana&type=release".to_string());
+        assert_eq!(normalize_url("https://api.discogs.com/releases/1"),
+                   "https://api.discogs.com/releases/1".to_string());
+
+        assert!(ResponseCache::key("https://api.discogs.com/releases/1", "token:a") !=
+                ResponseCache::key("https://api.discogs.com/releases/1", "token:b"));
+    }
+
+    #[test]
+    fn test_is_cacheable() {
+        assert!(is_cacheable("https://api.discogs.com/releases/1"));
+        assert!(is_cacheable("https://api.discogs.com/database/search?q=Nirvana"));
+        assert!(is_cacheable("https://api.discogs.com/users/someone/lists"));
+        assert!(!is_cacheable("https://api.discogs.com/marketplace/orders"));
+        assert!(!is_cacheable("https://api.discogs.com/users/someone/inventory"));
+    }
+
+    #[test]
+    fn test_endpoint_ttls() {
+        let mut cache = ResponseCache::memory(10);
+        cache.ttl(5)
+             .endpoint_ttl("/releases", 100)
+             .endpoint_ttl("/releases/1/rating", 0);
+
+        assert_eq!(cache.ttl_for("https://api.discogs.com/artists/1"), 5);
+        assert_eq!(cache.ttl_for("https://api.discogs.com/releases/2?curr_abbr=EUR"), 100);
+        assert_eq!(cache.ttl_for("https://api.discogs.com/releases/1/rating"), 0);
+    }
+
+    #[test]
+    fn test_memory_store_lru() {
+        let mut store = MemoryStore::new(2);
+
+        store.put("a", response("a")).ok().unwrap();
+        store.put("b", response("b")).ok().unwrap();
+        assert!(store.get("a").is_some());
+
+        // b is now the least recently used
+        store.put("c", response("c")).ok().unwrap();
+
+        assert_eq!(store.len(), 2);
+        assert!(store.get("b").is_none());
+        assert_eq!(store.get("a"), Some(response("a")));
+        assert_eq!(store.get("c"), Some(response("c")));
+    }
+
+    #[test]
+    fn test_disk_store() {
+        let dir = env::temp_dir().join("discogs-rs-response-cache");
+        let _ = fs::remove_dir_all(&dir);
+        let mut store = DiskStore::new(&dir, 1024).ok().unwrap();
+
+        assert!(store.get("a").is_none());
+        store.put("a", response("{\"id\": 1}\n")).ok().unwrap();
+        assert_eq!(store.get("a"), Some(response("{\"id\": 1}\n")));
+
+        // Survives being reopened
+        let mut store = DiskStore::new(&dir, 1024).ok().unwrap();
+        assert!(store.get("a").is_some());
+
+        store.clear().ok().unwrap();
+        assert!(store.get("a").is_none());
+    }
+
+    #[test]
+    fn test_disk_store_size_limit() {
+        let dir = env::temp_dir().join("discogs-rs-response-cache-limit");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut store = DiskStore::new(&dir, 1024).ok().unwrap();
+        store.put("a", response("a")).ok().unwrap();
+        let one = store.size();
+
+        // Room for two responses of the same size
+        let mut store = DiskStore::new(&dir, one * 2 + one / 2).ok().unwrap();
+        store.put("b", response("b")).ok().unwrap();
+        store.put("c", response("c")).ok().unwrap();
+
+        assert!(store.size() <= one * 2 + one / 2);
+        assert!(store.get("c").is_some());
+
+        // Too large to be stored at all
+        store.put("d", response(&"d".repeat(1024))).ok().unwrap();
+        assert!(store.get("d").is_none());
+    }
+
+    #[test]
+    fn test_memory_store_replaces_entries() {
+        let mut store = MemoryStore::new(2);
+
+        store.put("a", response("a")).ok().unwrap();
+        store.put("a", response("b")).ok().unwrap();
+        store.put("c", response("c")).ok().unwrap();
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get("a"), Some(response("b")));
+
+        store.clear().ok().unwrap();
+        assert_eq!(store.len(), 0);
+        store.put("d", response("d")).ok().unwrap();
+        assert_eq!(store.get("d"), Some(response("d")));
+    }
+
+    #[test]
+    fn test_fetch_uses_fresh_responses() {
+        let cache = ResponseCache::memory(10);
+        let url = "https://api.discogs.com/releases/1";
+
+        let mut etag = Headers::new();
+        etag.set_raw("ETag", vec![b"\"abc\"".to_vec()]);
+
+        let (_, body) = cache.fetch(url, "anonymous", |conditional| {
+                                 assert!(conditional.get_raw("If-None-Match").is_none());
+                                 Ok((etag, b"release".to_vec()))
+                             })
+                             .ok()
+                             .unwrap();
+        assert_eq!(body, b"release".to_vec());
+
+        let (headers, body) = cache.fetch(url, "anonymous", |_| panic!("Should not be sent"))
+                                   .ok()
+                                   .unwrap();
+        assert_eq!(body, b"release".to_vec());
+        assert_eq!(headers.get_raw("ETag"), Some(&[b"\"abc\"".to_vec()][..]));
+    }
+
+    #[test]
+    fn test_fetch_revalidates_stale_responses() {
+        let mut cache = ResponseCache::memory(10);
+        cache.ttl(0);
+        let url = "https://api.discogs.com/releases/1";
+
+        let key = ResponseCache::key(url, "anonymous");
+        cache.store.lock().unwrap().put(&key, response("old")).ok().unwrap();
+
+        let (_, body) = cache.fetch(url, "anonymous", |conditional| {
+                                 assert_eq!(conditional.get_raw("If-None-Match"),
+                                            Some(&[b"\"abc\"".to_vec()][..]));
+                                 Ok((Headers::new(), b"new".to_vec()))
+                             })
+                             .ok()
+                             .unwrap();
+        assert_eq!(body, b"new".to_vec());
+    }
+}
@@ -0,0 +1,558 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Keeps a seller's inventory in sync with a local stock file
+//!
+//! The local stock lists how many copies of each release are on hand in
+//! each media condition and at which price. `reconcile` compares it with
+//! the listings of the seller and produces a `Plan`, which can be printed
+//! for a dry run or applied through the listing endpoints.
+
+use data_structures::*;
+use discogs::Discogs;
+use query::*;
+use serde_json;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Prices closer than this are considered equal
+const PRICE_TOLERANCE: f64 = 0.005;
+
+fn default_quantity() -> u32 {
+    1
+}
+
+/// A line of the local stock file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StockItem {
+    pub release_id: u32,
+    pub condition: MediaCondition,
+    pub price: f64,
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    pub sleeve_condition: Option<SleeveCondition>,
+    pub comments: Option<String>,
+}
+
+/// Parses a json array of stock items
+///
+/// # Examples
+///
+/// ```
+/// use discogs::reconcile::stock_from_json;
+///
+/// let stock = stock_from_json(r#"[{"release_id": 1, "condition": "Mint (M)", "price": 42.0}]"#);
+/// assert_eq!(stock.ok().unwrap()[0].quantity, 1);
+/// ```
+pub fn stock_from_json(text: &str) -> Result<Vec<StockItem>, QueryError> {
+    serde_json::from_str(text).map_err(|e| QueryError::JsonDecodeError { serde_err: Some(e) })
+}
+
+/// Parses a csv of stock items, with the columns `release_id`,
+/// `media_condition`, `price` and optionally `quantity`,
+/// `sleeve_condition` and `comments`
+///
+/// # Examples
+///
+/// ```
+/// use discogs::reconcile::stock_from_csv;
+///
+/// let stock = stock_from_csv("release_id,media_condition,price,quantity\n1,Mint (M),42.00,2\n");
+/// assert_eq!(stock.ok().unwrap()[0].quantity, 2);
+/// ```
+pub fn stock_from_csv(text: &str) -> Result<Vec<StockItem>, QueryError> {
+    let records = try!(parse_csv_lines(text));
+    let mut records = records.iter();
+
+    let columns: HashMap<String, usize> = match records.next() {
+        Some(&(_, ref header)) => header.iter()
+                              .enumerate()
+                              .map(|(i, name)| (name.trim().to_string(), i))
+                              .collect(),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut stock = Vec::new();
+
+    for &(line, ref fields) in records {
+        // Skip blank lines
+        if fields.len() == 1 && fields[0].is_empty() {
+            continue;
+        }
+
+        let field = |column: &str| -> Option<String> {
+            columns.get(column)
+                   .and_then(|&c| fields.get(c))
+                   .and_then(|f| if f.is_empty() { None } else { Some(f.clone()) })
+        };
+        let error = |reason: String| QueryError::CsvDecodeError { line: line, reason: reason };
+
+        let release_id = try!(field("release_id").and_then(|r| r.parse().ok())
+                              .ok_or(error("Invalid release_id".to_string())));
+        let price = try!(field("price").and_then(|p| p.parse().ok())
+                         .ok_or(error("Invalid price".to_string())));
+        let condition = try!(field("media_condition")
+                             .ok_or("Missing media_condition".to_string())
+                             .and_then(|c| c.parse::<MediaCondition>())
+                             .map_err(&error));
+        let sleeve_condition = match field("sleeve_condition") {
+            Some(s) => Some(try!(s.parse::<SleeveCondition>().map_err(&error))),
+            None => None,
+        };
+        let quantity = match field("quantity") {
+            Some(q) => try!(q.parse().map_err(|_| error("Invalid quantity".to_string()))),
+            None => default_quantity(),
+        };
+
+        stock.push(StockItem {
+            release_id: release_id,
+            condition: condition,
+            price: price,
+            quantity: quantity,
+            sleeve_condition: sleeve_condition,
+            comments: field("comments"),
+        });
+    }
+
+    Ok(stock)
+}
+
+/// A change needed to bring the inventory in line with the stock
+#[derive(Debug, Clone)]
+pub enum Action {
+    Create {
+        request: ListingRequest
+    },
+    Reprice {
+        listing_id: u64,
+        from: f64,
+        request: ListingRequest
+    },
+    Delete {
+        listing_id: u64,
+        release_id: u32,
+        condition: MediaCondition
+    },
+}
+
+impl Action {
+    /// Performs the action through the listing endpoints
+    pub fn apply(&self, client: &Discogs) -> Result<(), QueryError> {
+        match *self {
+            Action::Create { ref request } => client.create_listing(request.clone())
+                                                    .create()
+                                                    .map(|_| ()),
+            Action::Reprice { listing_id, ref request, .. } => client.listing(listing_id)
+                                                                     .edit(request),
+            Action::Delete { listing_id, .. } => client.listing(listing_id)
+                                                       .delete(),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Action::Create { ref request } =>
+                write!(f, "create  release {} {} at {:.2}",
+                       request.release_id, request.condition.to_string(), request.price),
+            Action::Reprice { listing_id, from, ref request } =>
+                write!(f, "reprice listing {} (release {} {}) from {:.2} to {:.2}",
+                       listing_id, request.release_id, request.condition.to_string(), from, request.price),
+            Action::Delete { listing_id, release_id, ref condition } =>
+                write!(f, "delete  listing {} (release {} {})",
+                       listing_id, release_id, condition.to_string()),
+        }
+    }
+}
+
+/// A listing priced in another currency than the stock, which is left alone
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurrencyMismatch {
+    pub listing_id: u64,
+    pub release_id: u32,
+    pub condition: MediaCondition,
+    pub price: Price,
+}
+
+impl fmt::Display for CurrencyMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "skip    listing {} (release {} {}) priced in {}",
+               self.listing_id, self.release_id, self.condition.to_string(), self.price.currency.to_string())
+    }
+}
+
+/// The actions needed to bring the inventory in line with the stock
+///
+/// The listings whose prices can not be compared with the stock are
+/// in `mismatches`, they need to be looked at by hand.
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub actions: Vec<Action>,
+    pub mismatches: Vec<CurrencyMismatch>,
+}
+
+impl Plan {
+    /// Whether there is nothing to apply, mismatches aside
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Applies every action, carrying on past failures
+    ///
+    /// The results are in the same order as `actions`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use discogs::Discogs;
+    /// use discogs::data_structures::Currency;
+    /// use discogs::reconcile::*;
+    ///
+    /// let mut client = Discogs::new("USER_AGENT");
+    /// client.token("USER_TOKEN");
+    ///
+    /// let stock = stock_from_csv("release_id,media_condition,price\n1,Mint (M),42.00\n").ok().unwrap();
+    /// let listings = fetch_inventory(&client, "username").ok().unwrap();
+    /// let plan = reconcile(&stock, &listings, Currency::USD);
+    ///
+    /// // Dry run
+    /// print!("{}", plan);
+    ///
+    /// for (action, result) in plan.actions.iter().zip(plan.apply(&client)) {
+    ///     if let Err(e) = result {
+    ///         println!("{} failed: {:?}", action, e);
+    ///     }
+    /// }
+    /// ```
+    pub fn apply(&self, client: &Discogs) -> Vec<Result<(), QueryError>> {
+        self.actions.iter().map(|action| action.apply(client)).collect()
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for action in self.actions.iter() {
+            try!(writeln!(f, "{}", action));
+        }
+        for mismatch in self.mismatches.iter() {
+            try!(writeln!(f, "{}", mismatch));
+        }
+        Ok(())
+    }
+}
+
+/// Fetches every listing of `username`, following the pagination
+pub fn fetch_inventory(client: &Discogs, username: &str) -> Result<Vec<Listing>, QueryError> {
+    let mut listings = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let inventory = try!(client.inventory(username)
+                                   .pagination(page, 100)
+                                   .get());
+        let pages = inventory.pagination.pages;
+
+        listings.extend(inventory.listings);
+
+        if page >= pages {
+            return Ok(listings);
+        }
+        page += 1;
+    }
+}
+
+/// Compares the stock, priced in `currency`, with the listings of the seller
+///
+/// Stock and listings are matched by release and media condition, the
+/// listings already at the price of a copy first. Missing copies are
+/// listed for sale, listings that differ in price are repriced and
+/// listings without a copy in stock are deleted. Sold and expired
+/// listings are left alone, and so are listings in another currency,
+/// which are reported as mismatches.
+pub fn reconcile(stock: &[StockItem], listings: &[Listing], currency: Currency) -> Plan {
+    // Sorted so that plans are stable between runs
+    let mut wanted: BTreeMap<(u32, MediaCondition), Vec<&StockItem>> = BTreeMap::new();
+    let mut listed: BTreeMap<(u32, MediaCondition), Vec<&Listing>> = BTreeMap::new();
+
+    for item in stock.iter() {
+        wanted.entry((item.release_id, item.condition)).or_insert_with(Vec::new).push(item);
+    }
+
+    let mut mismatches = Vec::new();
+
+    for listing in listings.iter() {
+        match listing.status {
+            ListingStatus::ForSale | ListingStatus::Draft if listing.price.currency != currency => {
+                mismatches.push(CurrencyMismatch {
+                    listing_id: listing.id,
+                    release_id: listing.release.id,
+                    condition: listing.condition,
+                    price: listing.price.clone(),
+                });
+            },
+            ListingStatus::ForSale | ListingStatus::Draft => {
+                listed.entry((listing.release.id, listing.condition))
+                      .or_insert_with(Vec::new)
+                      .push(listing);
+            },
+            _ => (),
+        }
+    }
+
+    let mut actions = Vec::new();
+
+    for (key, items) in wanted.iter() {
+        let mut existing = listed.remove(key).unwrap_or_default();
+        existing.sort_by_key(|l| l.id);
+
+        let copies: Vec<&StockItem> = items.iter()
+                                           .flat_map(|item| (0..item.quantity).map(move |_| *item))
+                                           .collect();
+
+        // The listings already at the price of a copy are kept first, so that
+        // identical copies at different prices are not repriced into each other
+        let kept: Vec<Option<&Listing>> = copies.iter()
+                                                .map(|copy| {
+                                                    let at_price = existing.iter().position(|l| {
+                                                        (l.price.value - copy.price).abs() <= PRICE_TOLERANCE
+                                                    });
+                                                    at_price.map(|i| existing.remove(i))
+                                                })
+                                                .collect();
+        let mut existing = existing.into_iter();
+
+        for (item, kept) in copies.iter().zip(kept) {
+            if kept.is_some() {
+                continue;
+            }
+            match existing.next() {
+                Some(listing) => actions.push(Action::Reprice {
+                    listing_id: listing.id,
+                    from: listing.price.value,
+                    request: reprice_request(listing, item.price),
+                }),
+                None => actions.push(Action::Create {
+                    request: create_request(item),
+                }),
+            }
+        }
+
+        for listing in existing {
+            actions.push(delete_action(listing));
+        }
+    }
+
+    for (_, remaining) in listed.iter() {
+        for listing in remaining.iter() {
+            actions.push(delete_action(listing));
+        }
+    }
+
+    Plan {
+        actions: actions,
+        mismatches: mismatches,
+    }
+}
+
+fn create_request(item: &StockItem) -> ListingRequest {
+    let mut request = ListingRequest::new(item.release_id,
+                                          item.condition,
+                                          item.price,
+                                          NewListingStatus::ForSale);
+    request.sleeve_condition = item.sleeve_condition;
+    request.comments = item.comments.clone();
+    request
+}
+
+/// Editing a listing replaces it, so every field is carried over
+fn reprice_request(listing: &Listing, price: f64) -> ListingRequest {
+    let status = match listing.status {
+        ListingStatus::Draft => NewListingStatus::Draft,
+        _ => NewListingStatus::ForSale,
+    };
+
+    let mut request = ListingRequest::new(listing.release.id, listing.condition, price, status);
+    request.sleeve_condition = listing.sleeve_condition;
+    request.comments = listing.comments.clone();
+    request.allow_offers = listing.allow_offers;
+    request.external_id = listing.external_id.clone();
+    request.location = listing.location.clone();
+    request.weight = listing.weight;
+    request.format_quantity = listing.format_quantity;
+    request
+}
+
+fn delete_action(listing: &Listing) -> Action {
+    Action::Delete {
+        listing_id: listing.id,
+        release_id: listing.release.id,
+        condition: listing.condition,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_structures::*;
+    use discogs::*;
+    use mockito::mock;
+    use serde_json::to_string;
+
+    fn listings() -> Vec<Listing> {
+        let csv = "listing_id,release_id,status,price,media_condition\n\
+                   100,1,For Sale,40.00,Mint (M)\n\
+                   101,1,For Sale,42.00,Mint (M)\n\
+                   102,2,For Sale,7.00,Good (G)\n\
+                   103,3,Sold,9.00,Fair (F)\n\
+                   104,4,Draft,5.00,Poor (P)\n";
+
+        listings_from_csv(csv, Currency::USD, API_URL).ok().unwrap()
+    }
+
+    #[test]
+    fn test_stock_from_csv() {
+        let stock = stock_from_csv("release_id,media_condition,price,quantity,sleeve_condition\n\
+                                    1,Mint (M),42.00,3,\n\
+                                    2,Good (G),7,,Generic\n").ok().unwrap();
+
+        assert_eq!(stock.len(), 2);
+        assert_eq!(stock[0].quantity, 3);
+        assert_eq!(stock[0].condition, MediaCondition::Mint);
+        assert_eq!(stock[1].quantity, 1);
+        assert_eq!(stock[1].sleeve_condition, Some(SleeveCondition::Generic));
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let stock = stock_from_json(r#"[
+            {"release_id": 1, "condition": "Mint (M)", "price": 42.0, "quantity": 3},
+            {"release_id": 4, "condition": "Poor (P)", "price": 5.0}
+        ]"#).ok().unwrap();
+
+        let plan = reconcile(&stock, &listings(), Currency::USD);
+
+        // 100 is repriced, 101 is kept, a third copy of 1 is listed,
+        // 102 is no longer in stock, 103 is sold and 104 is unchanged
+        assert_eq!(plan.to_string(),
+                   "reprice listing 100 (release 1 Mint (M)) from 40.00 to 42.00\n\
+                    create  release 1 Mint (M) at 42.00\n\
+                    delete  listing 102 (release 2 Good (G))\n".to_string());
+    }
+
+    #[test]
+    fn test_reconcile_in_sync() {
+        let stock = stock_from_json(r#"[
+            {"release_id": 1, "condition": "Mint (M)", "price": 40.0},
+            {"release_id": 1, "condition": "Mint (M)", "price": 42.0},
+            {"release_id": 2, "condition": "Good (G)", "price": 7.001},
+            {"release_id": 4, "condition": "Poor (P)", "price": 5.0}
+        ]"#).ok().unwrap();
+
+        assert!(reconcile(&stock, &listings(), Currency::USD).is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_matches_prices_first() {
+        // The copies come in the opposite order of the listings
+        let stock = stock_from_json(r#"[
+            {"release_id": 1, "condition": "Mint (M)", "price": 42.0},
+            {"release_id": 1, "condition": "Mint (M)", "price": 40.0},
+            {"release_id": 1, "condition": "Mint (M)", "price": 45.0},
+            {"release_id": 2, "condition": "Good (G)", "price": 7.0},
+            {"release_id": 4, "condition": "Poor (P)", "price": 5.0}
+        ]"#).ok().unwrap();
+
+        let plan = reconcile(&stock, &listings(), Currency::USD);
+
+        assert_eq!(plan.to_string(), "create  release 1 Mint (M) at 45.00\n".to_string());
+    }
+
+    #[test]
+    fn test_reconcile_skips_other_currencies() {
+        let stock = stock_from_json(r#"[
+            {"release_id": 2, "condition": "Good (G)", "price": 9.0}
+        ]"#).ok().unwrap();
+        let mut listings = listings();
+        listings[2].price.currency = Currency::EUR;
+
+        let plan = reconcile(&stock, &listings[2..3], Currency::USD);
+
+        // Listed as there is no listing in dollars, but 102 is not repriced
+        assert_eq!(plan.to_string(),
+                   "create  release 2 Good (G) at 9.00\n\
+                    skip    listing 102 (release 2 Good (G)) priced in EUR\n".to_string());
+        assert_eq!(plan.mismatches[0].listing_id, 102);
+    }
+
+    #[test]
+    fn test_plan_apply() {
+        mock("DELETE", "/marketplace/listings/102")
+            .with_status(204)
+            .create_for(|| {
+                let plan = reconcile(&[], &listings()[2..3], Currency::USD);
+                let results = plan.apply(Discogs::new("USER_AGENT").token("USER_TOKEN"));
+
+                assert_eq!(results.len(), 1);
+                assert!(results[0].is_ok());
+            });
+    }
+
+    #[test]
+    fn test_fetch_inventory_follows_pages() {
+        let page = |page: i16, id: u64| to_string(&json!({
+            "pagination": {
+                "per_page": 100,
+                "items": 2,
+                "page": page,
+                "urls": {},
+                "pages": 2
+            },
+            "listings": [{
+                "id": id,
+                "resource_url": "",
+                "status": "For Sale",
+                "condition": "Mint (M)",
+                "price": {
+                    "currency": "USD",
+                    "value": 1.0
+                },
+                "release": {
+                    "id": 1,
+                    "resource_url": ""
+                }
+            }]
+        })).unwrap();
+
+        mock("GET", "/users/seller/inventory?page=1&per_page=100")
+            .with_status(200)
+            .with_header("content-type", "text/json")
+            .with_body(page(1, 100).as_str())
+            .create_for(|| {
+                mock("GET", "/users/seller/inventory?page=2&per_page=100")
+                    .with_status(200)
+                    .with_header("content-type", "text/json")
+                    .with_body(page(2, 101).as_str())
+                    .create_for(|| {
+                        let listings = fetch_inventory(&Discogs::new("USER_AGENT"), "seller")
+                            .ok()
+                            .unwrap();
+
+                        assert_eq!(listings.iter().map(|l| l.id).collect::<Vec<u64>>(), vec![100, 101]);
+                    });
+            });
+    }
+}
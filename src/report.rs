@@ -0,0 +1,411 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Sales reports for bookkeeping
+//!
+//! A `SalesReport` summarises orders per month and currency, and lists
+//! the items sold per release and condition. It can be built from the
+//! orders fetched live or from a snapshot saved earlier.
+
+use data_structures::*;
+use discogs::Discogs;
+use query::*;
+use serde_json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// The totals of a month in one currency
+///
+/// `gross` is what the buyers paid, shipping included,
+/// `net` is `gross` minus the discogs `fee`. Refunded orders are
+/// left out of both and added up in `refunds` instead, orders not
+/// paid yet are added up in `unpaid`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MonthlySummary {
+    pub month: String,
+    pub currency: Currency,
+    pub orders: u32,
+    pub gross: Price,
+    pub fee: Price,
+    pub shipping: Price,
+    pub net: Price,
+    pub refunds: Price,
+    pub unpaid: Price,
+}
+
+/// The copies of a release sold in a month, in one condition and currency
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ItemSales {
+    pub month: String,
+    pub release_id: u32,
+    pub description: Option<String>,
+    pub condition: Option<MediaCondition>,
+    pub quantity: u32,
+    pub gross: Price,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SalesReport {
+    pub months: Vec<MonthlySummary>,
+    pub items: Vec<ItemSales>,
+}
+
+// Amounts are added up in hundredths to avoid floating point drift
+struct Totals {
+    currency: Currency,
+    orders: u32,
+    gross: i64,
+    fee: i64,
+    shipping: i64,
+    refunds: i64,
+    unpaid: i64,
+}
+
+struct ItemTotals {
+    currency: Currency,
+    description: Option<String>,
+    quantity: u32,
+    gross: i64,
+}
+
+fn to_hundredths(value: f64) -> i64 {
+    (value * 100.0).round() as i64
+}
+
+fn to_price(hundredths: i64, currency: Currency) -> Price {
+    Price::new(hundredths as f64 / 100.0, currency)
+}
+
+impl SalesReport {
+    /// Summarises `orders`
+    ///
+    /// Cancelled and merged orders, and orders without a creation
+    /// date, are left out. Refunded orders only count as refunds and
+    /// unpaid orders as unpaid. Months are taken from the creation date
+    /// in the timezone of the seller.
+    pub fn from_orders(orders: &[Order]) -> SalesReport {
+        let mut months: BTreeMap<(String, String), Totals> = BTreeMap::new();
+        let mut items: BTreeMap<(String, u32, Option<MediaCondition>, String), ItemTotals> = BTreeMap::new();
+
+        for order in orders.iter() {
+            let month = match order.created {
+                Some(ref created) if created.len() >= 7 => created[..7].to_string(),
+                _ => continue,
+            };
+            if order.status.is_cancelled() {
+                continue;
+            }
+
+            let currency = order.total.currency;
+
+            let totals = months.entry((month.clone(), currency.to_string()))
+                               .or_insert(Totals {
+                                   currency: currency,
+                                   orders: 0,
+                                   gross: 0,
+                                   fee: 0,
+                                   shipping: 0,
+                                   refunds: 0,
+                                   unpaid: 0,
+                               });
+            if order.status == OrderStatus::RefundSent {
+                totals.refunds += to_hundredths(order.total.value);
+                continue;
+            }
+            if !order.status.is_paid() {
+                totals.unpaid += to_hundredths(order.total.value);
+                continue;
+            }
+            totals.orders += 1;
+            totals.gross += to_hundredths(order.total.value);
+            totals.fee += order.fee.as_ref().map_or(0, |f| to_hundredths(f.value));
+            totals.shipping += order.shipping.as_ref().map_or(0, |s| to_hundredths(s.value));
+
+            for item in order.items.iter() {
+                let sales = items.entry((month.clone(),
+                                         item.release.id,
+                                         item.media_condition,
+                                         currency.to_string()))
+                                 .or_insert(ItemTotals {
+                                     currency: currency,
+                                     description: item.release.description.clone(),
+                                     quantity: 0,
+                                     gross: 0,
+                                 });
+                sales.quantity += 1;
+                sales.gross += to_hundredths(item.price.value);
+            }
+        }
+
+        SalesReport {
+            months: months.into_iter()
+                          .map(|((month, _), t)| MonthlySummary {
+                              month: month,
+                              currency: t.currency,
+                              orders: t.orders,
+                              gross: to_price(t.gross, t.currency),
+                              fee: to_price(t.fee, t.currency),
+                              shipping: to_price(t.shipping, t.currency),
+                              net: to_price(t.gross - t.fee, t.currency),
+                              refunds: to_price(t.refunds, t.currency),
+                              unpaid: to_price(t.unpaid, t.currency),
+                          })
+                          .collect(),
+            items: items.into_iter()
+                        .map(|((month, release_id, condition, _), t)| ItemSales {
+                            month: month,
+                            release_id: release_id,
+                            description: t.description,
+                            condition: condition,
+                            quantity: t.quantity,
+                            gross: to_price(t.gross, t.currency),
+                        })
+                        .collect(),
+        }
+    }
+
+    /// The whole report as json
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// The monthly totals, one row per month and currency
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discogs::report::SalesReport;
+    ///
+    /// let report = SalesReport::from_orders(&[]);
+    /// assert_eq!(report.months_csv(), "month,currency,orders,gross,fee,shipping,net,refunds,unpaid\n");
+    /// ```
+    pub fn months_csv(&self) -> String {
+        let mut csv = write_csv_record(&fields(&["month", "currency", "orders", "gross", "fee", "shipping",
+                                                 "net", "refunds", "unpaid"]));
+
+        for m in self.months.iter() {
+            csv.push_str(&write_csv_record(&[m.month.clone(),
+                                             m.currency.to_string(),
+                                             m.orders.to_string(),
+                                             format!("{:.2}", m.gross.value),
+                                             format!("{:.2}", m.fee.value),
+                                             format!("{:.2}", m.shipping.value),
+                                             format!("{:.2}", m.net.value),
+                                             format!("{:.2}", m.refunds.value),
+                                             format!("{:.2}", m.unpaid.value)]));
+        }
+        csv
+    }
+
+    /// The items sold, one row per month, release, condition and currency
+    pub fn items_csv(&self) -> String {
+        let mut csv = write_csv_record(&fields(&["month", "release_id", "description", "media_condition",
+                                                 "currency", "quantity", "gross"]));
+
+        for i in self.items.iter() {
+            csv.push_str(&write_csv_record(&[i.month.clone(),
+                                             i.release_id.to_string(),
+                                             i.description.clone().unwrap_or_default(),
+                                             i.condition.map(|c| c.to_string()).unwrap_or_default(),
+                                             i.gross.currency.to_string(),
+                                             i.quantity.to_string(),
+                                             format!("{:.2}", i.gross.value)]));
+        }
+        csv
+    }
+}
+
+fn fields(names: &[&str]) -> Vec<String> {
+    names.iter().map(|n| n.to_string()).collect()
+}
+
+/// Fetches every order of the authenticated seller, oldest first
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use discogs::Discogs;
+/// use discogs::report::*;
+///
+/// let mut client = Discogs::new("USER_AGENT");
+/// client.token("USER_TOKEN");
+///
+/// let orders = fetch_orders(&client).ok().unwrap();
+/// save_snapshot(&orders, "orders-2017-03.json").ok().unwrap();
+///
+/// print!("{}", SalesReport::from_orders(&orders).months_csv());
+/// ```
+pub fn fetch_orders(client: &Discogs) -> Result<Vec<Order>, QueryError> {
+    let mut orders = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let result = try!(client.orders()
+                                .sort(OrderSort::Created, SortOrder::Ascending)
+                                .pagination(page, 100)
+                                .get());
+        let pages = result.pagination.pages;
+
+        orders.extend(result.orders);
+
+        if page >= pages {
+            return Ok(orders);
+        }
+        page += 1;
+    }
+}
+
+/// Saves orders to a json file, to be reported on later
+pub fn save_snapshot<P: AsRef<Path>>(orders: &[Order], path: P) -> Result<(), QueryError> {
+    let text = try!(serde_json::to_string(orders)
+                        .map_err(|e| QueryError::JsonDecodeError { serde_err: Some(e) }));
+
+    // Written aside first so that a crash never leaves half a snapshot behind
+    let mut partial = path.as_ref().to_path_buf().into_os_string();
+    partial.push(".partial");
+
+    File::create(&partial)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .and_then(|_| fs::rename(&partial, path))
+        .map_err(|e| QueryError::TextReadError { error: e })
+}
+
+/// Loads the orders saved by `save_snapshot`
+pub fn load_snapshot<P: AsRef<Path>>(path: P) -> Result<Vec<Order>, QueryError> {
+    let mut text = String::new();
+    try!(File::open(path)
+             .and_then(|mut file| file.read_to_string(&mut text))
+             .map_err(|e| QueryError::TextReadError { error: e }));
+
+    serde_json::from_str(&text).map_err(|e| QueryError::JsonDecodeError { serde_err: Some(e) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_structures::*;
+    use serde_json;
+    use std::env;
+
+    fn order(id: &str, status: &str, created: &str, currency: &str, items: Vec<(u32, &str, f64)>) -> Order {
+        let total: f64 = items.iter().map(|i| i.2).sum::<f64>() + 5.0;
+
+        serde_json::from_value(json!({
+            "id": id,
+            "resource_url": "",
+            "status": status,
+            "created": created,
+            "total": { "currency": currency, "value": total },
+            "fee": { "currency": currency, "value": total * 0.08 },
+            "shipping": { "currency": currency, "value": 5.0 },
+            "items": items.iter().map(|&(release, condition, price)| json!({
+                "id": 1,
+                "release": {
+                    "id": release,
+                    "resource_url": "",
+                    "description": format!("Release {}", release)
+                },
+                "price": { "currency": currency, "value": price },
+                "media_condition": condition
+            })).collect::<Vec<serde_json::Value>>()
+        })).unwrap()
+    }
+
+    fn orders() -> Vec<Order> {
+        vec![order("1-1", "Shipped", "2017-01-03T10:00:00-08:00", "USD",
+                   vec![(1, "Mint (M)", 10.0), (2, "Good (G)", 2.5)]),
+             order("1-2", "Payment Received", "2017-01-20T10:00:00-08:00", "USD",
+                   vec![(1, "Mint (M)", 10.0)]),
+             order("1-3", "Cancelled (Non-Paying Buyer)", "2017-01-21T10:00:00-08:00", "USD",
+                   vec![(3, "Mint (M)", 100.0)]),
+             order("1-4", "Shipped", "2017-02-01T10:00:00-08:00", "EUR",
+                   vec![(1, "Mint (M)", 8.0)])]
+    }
+
+    #[test]
+    fn test_monthly_summary() {
+        let report = SalesReport::from_orders(&orders());
+
+        assert_eq!(report.months.len(), 2);
+        assert_eq!(report.months[0], MonthlySummary {
+            month: "2017-01".to_string(),
+            currency: Currency::USD,
+            orders: 2,
+            gross: Price::new(32.5, Currency::USD),
+            fee: Price::new(2.6, Currency::USD),
+            shipping: Price::new(10.0, Currency::USD),
+            net: Price::new(29.9, Currency::USD),
+            refunds: Price::new(0.0, Currency::USD),
+            unpaid: Price::new(0.0, Currency::USD),
+        });
+        assert_eq!(report.months[1].month, "2017-02".to_string());
+        assert_eq!(report.months[1].net, Price::new(11.96, Currency::EUR));
+    }
+
+    #[test]
+    fn test_items_sold() {
+        let report = SalesReport::from_orders(&orders());
+
+        assert_eq!(report.items_csv(),
+                   "month,release_id,description,media_condition,currency,quantity,gross\n\
+                    2017-01,1,Release 1,Mint (M),USD,2,20.00\n\
+                    2017-01,2,Release 2,Good (G),USD,1,2.50\n\
+                    2017-02,1,Release 1,Mint (M),EUR,1,8.00\n".to_string());
+    }
+
+    #[test]
+    fn test_report_from_snapshot() {
+        let path = env::temp_dir().join("discogs-rs-report-snapshot.json");
+
+        save_snapshot(&orders(), &path).ok().unwrap();
+        let report = SalesReport::from_orders(&load_snapshot(&path).ok().unwrap());
+
+        assert_eq!(report.months_csv(),
+                   "month,currency,orders,gross,fee,shipping,net,refunds,unpaid\n\
+                    2017-01,USD,2,32.50,2.60,10.00,29.90,0.00,0.00\n\
+                    2017-02,EUR,1,13.00,1.04,5.00,11.96,0.00,0.00\n".to_string());
+    }
+
+    #[test]
+    fn test_refunds_are_reported_apart() {
+        let mut orders = orders();
+        orders.push(order("1-5", "Refund Sent", "2017-01-25T10:00:00-08:00", "USD",
+                          vec![(4, "Mint (M)", 20.0)]));
+        let report = SalesReport::from_orders(&orders);
+
+        assert_eq!(report.months[0].orders, 2);
+        assert_eq!(report.months[0].gross, Price::new(32.5, Currency::USD));
+        assert_eq!(report.months[0].fee, Price::new(2.6, Currency::USD));
+        assert_eq!(report.months[0].refunds, Price::new(25.0, Currency::USD));
+        assert!(!report.items.iter().any(|i| i.release_id == 4));
+    }
+
+    #[test]
+    fn test_unpaid_orders_are_reported_apart() {
+        let mut orders = orders();
+        orders.push(order("1-5", "Invoice Sent", "2017-01-25T10:00:00-08:00", "USD",
+                          vec![(4, "Mint (M)", 20.0)]));
+        let report = SalesReport::from_orders(&orders);
+
+        assert_eq!(report.months[0].orders, 2);
+        assert_eq!(report.months[0].gross, Price::new(32.5, Currency::USD));
+        assert_eq!(report.months[0].unpaid, Price::new(25.0, Currency::USD));
+        assert!(!report.items.iter().any(|i| i.release_id == 4));
+    }
+}
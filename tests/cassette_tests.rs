@@ -0,0 +1,56 @@
+// Copyright (C) 2016  Afonso Bordado <afonsobordado@az8.co>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+extern crate discogs;
+extern crate hyper;
+
+use discogs::Discogs;
+use discogs::query::{Cassette, QueryError};
+use hyper::status::StatusCode;
+
+fn replaying(name: &str) -> (Discogs, Cassette) {
+    let cassette = Cassette::replay(format!("tests/cassettes/{}.json", name)).ok().unwrap();
+    let mut client = Discogs::new("USER_AGENT");
+    client.cassette(cassette.clone());
+
+    (client, cassette)
+}
+
+#[test]
+fn test_replay_artist() {
+    let (client, cassette) = replaying("artist");
+
+    let artist = client.artist(555).get().ok().unwrap();
+    assert_eq!(artist.id, 555);
+    assert_eq!(artist.name, "Ryoji Ikeda".to_string());
+
+    match client.artist(0).get() {
+        Err(QueryError::HyperStatusError { response }) => assert_eq!(response.status, StatusCode::NotFound),
+        _ => panic!("Expected a HyperStatusError"),
+    }
+
+    assert!(cassette.unplayed().is_empty());
+}
+
+#[test]
+fn test_replay_unexpected_request() {
+    let (client, _) = replaying("artist");
+
+    match client.artist(1).get() {
+        Err(QueryError::CassetteError { .. }) => (),
+        _ => panic!("Expected a CassetteError"),
+    }
+}
@@ -33,7 +33,7 @@ fn test_search() {
     use discogs::query::QueryError::HyperStatusError;
     use std::io::Read;
 
-    let mut client = build_discogs();
+    let client = build_discogs();
 
     let search_res = client.search()
                            .query("Na Wyspach Dni".to_string())
@@ -66,7 +66,7 @@ fn test_search() {
 
 #[test]
 fn test_request_artist() {
-    let mut client = build_discogs();
+    let client = build_discogs();
 
     let artist_res = client.artist(555).get();
 